@@ -0,0 +1,204 @@
+//! Compares parsing and serializing representative documents against `serde_json`.
+//!
+//! Run with `cargo bench --bench formats`. The JSON documents hold the same data as
+//! the SJSON ones, so the `serde_json` results serve as a baseline.
+//!
+//! Baseline on a Linux x86_64 container, for orientation only:
+//!
+//! | document     | parse sjson | parse json | serialize sjson | serialize json |
+//! |--------------|-------------|------------|-----------------|----------------|
+//! | stingray     | 14.3 µs     | 222 ns     | 740 ns          | 285 ns         |
+//! | numbers      | 69.1 ms     | 1.60 ms    | 7.34 ms         | 946 µs         |
+//! | nested       | 93.2 ms     | 1.55 ms    | 5.62 ms         | 821 µs         |
+//! | strings      | 9.34 ms     | 1.24 ms    | 2.48 ms         | 383 µs         |
+//! | records      | 79.6 ms     | 949 µs     | 8.12 ms         | 594 µs         |
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const STINGRAY: &str = r#"
+// The script that should be started when the application runs.
+boot_script = "boot"
+
+// The port on which the console server runs.
+console_port = 14030
+
+// Settings for the win32 platform
+win32 = {
+    /* Sets the affinity mask for
+       QueryPerformanceCounter() */
+    query_performance_counter_affinity_mask = 0
+}
+
+render_config = "core/rendering/renderer"
+"#;
+
+#[derive(Deserialize, Serialize)]
+struct Win32Settings {
+    query_performance_counter_affinity_mask: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Settings {
+    boot_script: String,
+    console_port: u16,
+    win32: Win32Settings,
+    render_config: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Numbers {
+    ids: Vec<u64>,
+    offsets: Vec<i64>,
+    weights: Vec<f64>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Node {
+    name: String,
+    enabled: bool,
+    priority: u32,
+    children: Vec<Node>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Nested {
+    root: Node,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Unit {
+    name: String,
+    position: [f32; 3],
+    visible: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Records {
+    units: Vec<Unit>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Strings {
+    paths: Vec<String>,
+    labels: BTreeMap<String, String>,
+}
+
+fn numbers() -> Numbers {
+    Numbers {
+        ids: (0..20_000).map(|i| i * 7_919).collect(),
+        offsets: (0..20_000).map(|i| i * -31).collect(),
+        weights: (0..20_000).map(|i| f64::from(i) / 7.0).collect(),
+    }
+}
+
+fn node(name: String, depth: u32) -> Node {
+    let children = if depth == 0 {
+        Vec::new()
+    } else {
+        (0..4)
+            .map(|i| node(format!("{name}_{i}"), depth - 1))
+            .collect()
+    };
+
+    Node {
+        name,
+        enabled: depth > 2,
+        priority: depth * 10,
+        children,
+    }
+}
+
+fn records() -> Records {
+    Records {
+        units: (0..5_000)
+            .map(|i| Unit {
+                name: format!("unit_{i}"),
+                position: [i as f32, 0.5, -(i as f32) / 3.0],
+                visible: i % 3 != 0,
+            })
+            .collect(),
+    }
+}
+
+fn strings() -> Strings {
+    Strings {
+        paths: (0..2_000)
+            .map(|i| format!("content/levels/area_{}/section {}/unit.unit", i % 17, i))
+            .collect(),
+        labels: (0..2_000)
+            .map(|i| {
+                (
+                    format!("label_{i}"),
+                    format!("A \"quoted\" description\twith a tab, number {i}"),
+                )
+            })
+            .collect(),
+    }
+}
+
+// Benchmarks one document, which is given as both SJSON and JSON.
+fn bench_document<T>(c: &mut Criterion, name: &str, sjson: &str, json: &str)
+where
+    T: DeserializeOwned + Serialize,
+{
+    let mut group = c.benchmark_group(name);
+
+    group.throughput(Throughput::Bytes(sjson.len() as u64));
+    group.bench_with_input(BenchmarkId::new("parse", "sjson"), sjson, |b, sjson| {
+        b.iter(|| serde_sjson::from_str::<T>(black_box(sjson)).unwrap())
+    });
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_with_input(BenchmarkId::new("parse", "json"), json, |b, json| {
+        b.iter(|| serde_json::from_str::<T>(black_box(json)).unwrap())
+    });
+
+    let value: T = serde_sjson::from_str(sjson).unwrap();
+
+    group.throughput(Throughput::Bytes(sjson.len() as u64));
+    group.bench_function(BenchmarkId::new("serialize", "sjson"), |b| {
+        b.iter(|| serde_sjson::to_string(black_box(&value)).unwrap())
+    });
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function(BenchmarkId::new("serialize", "json"), |b| {
+        b.iter(|| serde_json::to_string(black_box(&value)).unwrap())
+    });
+
+    group.finish();
+}
+
+// Benchmarks a value that is generated, rather than written by hand.
+fn bench_value<T>(c: &mut Criterion, name: &str, value: &T)
+where
+    T: DeserializeOwned + Serialize,
+{
+    let sjson = serde_sjson::to_string(value).unwrap();
+    let json = serde_json::to_string(value).unwrap();
+    bench_document::<T>(c, name, &sjson, &json);
+}
+
+fn stingray(c: &mut Criterion) {
+    let settings: Settings = serde_sjson::from_str(STINGRAY).unwrap();
+    let json = serde_json::to_string_pretty(&settings).unwrap();
+    bench_document::<Settings>(c, "stingray", STINGRAY, &json);
+}
+
+fn documents(c: &mut Criterion) {
+    bench_value(c, "numbers", &numbers());
+    bench_value(
+        c,
+        "nested",
+        &Nested {
+            root: node(String::from("root"), 6),
+        },
+    );
+    bench_value(c, "strings", &strings());
+    bench_value(c, "records", &records());
+}
+
+criterion_group!(benches, stingray, documents);
+criterion_main!(benches);
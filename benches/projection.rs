@@ -0,0 +1,62 @@
+//! Compares full deserialization of a wide document against projecting a few keys out of it.
+//!
+//! Run with `cargo bench --bench projection`.
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+const ITERATIONS: u32 = 50;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Entry {
+    name: String,
+    values: Vec<f64>,
+    nested: BTreeMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Projected {
+    entry_10: Entry,
+    entry_500: Entry,
+    entry_990: Entry,
+}
+
+fn document() -> String {
+    let mut sjson = String::new();
+    for i in 0..1000 {
+        sjson.push_str(&format!(
+            "entry_{i} = {{\n  name = \"entry {i}\"\n  values = [1.5, 2.5, 3.5, 4.5]\n  nested = {{ a = 1, b = 2, c = 3 }}\n}}\n"
+        ));
+    }
+    sjson
+}
+
+fn measure(name: &str, f: impl Fn()) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed: Duration = start.elapsed() / ITERATIONS;
+    println!("{name:>10}: {elapsed:?} per iteration");
+}
+
+fn main() {
+    let sjson = document();
+
+    measure("full", || {
+        let value: BTreeMap<String, Entry> = serde_sjson::from_str(&sjson).unwrap();
+        black_box(value);
+    });
+
+    measure("projection", || {
+        let value: Projected =
+            serde_sjson::from_str_projection(&sjson, &["entry_10", "entry_500", "entry_990"])
+                .unwrap();
+        black_box(value);
+    });
+}
@@ -1,707 +1,4687 @@
-use nom::IResult;
-use serde::de::{EnumAccess, IntoDeserializer, VariantAccess};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::marker::PhantomData;
+use std::ops::{Range, RangeInclusive};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use nom::{IResult, Slice};
+use serde::de::value::SeqDeserializer;
+use serde::de::{
+    DeserializeOwned, EnumAccess, IgnoredAny, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+};
 use serde::Deserialize;
 
 use crate::error::{Error, ErrorCode, Result};
+use crate::float::NonFiniteSpellings;
 use crate::parser::*;
+use crate::ser::BytesEncoding;
 
-/// A container for deserializing Rust values from SJSON.
-pub struct Deserializer<'de> {
-    input: Span<'de>,
-    is_top_level: bool,
+/// The location of a value in the source text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// The byte range of the value's text.
+    pub range: Range<usize>,
+    /// The line the value starts on, starting at 1.
+    pub line: u32,
+    /// The column the value starts at, starting at 1.
+    pub column: usize,
 }
 
-impl<'de> Deserializer<'de> {
-    #![allow(clippy::should_implement_trait)]
-    pub(crate) fn from_str(input: &'de str) -> Self {
-        Self {
-            input: Span::from(input),
-            is_top_level: true,
-        }
-    }
+/// A token that separates a key from its value.
+///
+/// Only [`Assignment::Equals`] is accepted by default. Others can be enabled
+/// with [`Deserializer::with_assignments`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assignment {
+    /// `key = value`
+    Equals,
+    /// `key: value`
+    Colon,
+    /// `key => value`
+    FatArrow,
+    /// `key -> value`
+    ///
+    /// Since `-` and `>` are valid in unquoted strings, an unquoted key has to be
+    /// followed by whitespace.
+    Arrow,
+}
 
-    fn parse(&mut self, f: &dyn Fn(Span) -> IResult<Span, Token>) -> Result<Token> {
-        f(self.input)
-            .map(|(span, token)| {
-                self.input = span;
-                token
-            })
-            .map_err(|err| self.error(ErrorCode::Message(err.to_string())))
-    }
+impl Assignment {
+    // Longer tokens come first, so that `=>` isn't read as `=` followed by `>`.
+    const ALL: [Assignment; 4] = [
+        Assignment::FatArrow,
+        Assignment::Arrow,
+        Assignment::Equals,
+        Assignment::Colon,
+    ];
 
-    fn next_token(&mut self) -> Result<Token> {
-        match parse_next_token(self.input) {
-            Ok((span, token)) => {
-                self.input = span;
-                Ok(token)
-            }
-            Err(err) => Err(self.error(ErrorCode::Message(err.to_string()))),
+    fn as_str(self) -> &'static str {
+        match self {
+            Assignment::Equals => "=",
+            Assignment::Colon => ":",
+            Assignment::FatArrow => "=>",
+            Assignment::Arrow => "->",
         }
     }
+}
 
-    fn peek_token(&mut self) -> Result<Token> {
-        match parse_next_token(self.input) {
-            Ok((_, token)) => Ok(token),
-            Err(err) => Err(self.error(ErrorCode::Message(err.to_string()))),
-        }
+/// A token that separates the elements of an array.
+///
+/// Commas and line breaks are accepted by default. Others can be enabled
+/// with [`Deserializer::with_array_separators`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArraySeparator {
+    /// `[1, 2]`
+    Comma,
+    /// A line break, either `\n` or `\r\n`.
+    Newline,
+    /// `[1; 2]`
+    ///
+    /// Since `;` is valid in unquoted strings, an unquoted string has to be
+    /// followed by whitespace, like `[a ; b]`.
+    Semicolon,
+}
+
+impl ArraySeparator {
+    // Returns the length of the separator at the start of `input`, if it is there.
+    fn len_at(self, input: &str) -> Option<usize> {
+        let separator = match self {
+            ArraySeparator::Comma => ",",
+            ArraySeparator::Newline if input.starts_with("\r\n") => "\r\n",
+            ArraySeparator::Newline => "\n",
+            ArraySeparator::Semicolon => ";",
+        };
+
+        input.starts_with(separator).then_some(separator.len())
     }
+}
 
-    fn error(&self, code: ErrorCode) -> Error {
-        Error::new(
-            code,
-            self.input.location_line(),
-            self.input.get_utf8_column(),
-            Some(self.input.fragment().to_string()),
-        )
+/// The kind of collection a value is, as reported by [`Deserializer::peek_is_collection`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CollectionKind {
+    /// An array, like `[1, 2]`.
+    Array,
+    /// An object, like `{ foo = 1 }`.
+    Object,
+}
+
+/// The unparsed source text of a single value, as returned by [`Deserializer::read_tagged`].
+///
+/// It remembers where it was found, so errors from deserializing it later
+/// still point into the original document.
+#[derive(Clone, Debug)]
+pub struct RawValue<'de> {
+    input: Span<'de>,
+}
+
+impl<'de> RawValue<'de> {
+    /// Returns the value's source text.
+    pub fn get(&self) -> &'de str {
+        self.input.fragment()
     }
 
-    fn error_with_token(&self, code: ErrorCode, token: Token) -> Error {
-        Error::with_token(
-            code,
-            self.input.location_line(),
-            self.input.get_utf8_column(),
-            Some(self.input.fragment().to_string()),
-            token,
-        )
+    /// Returns the byte offset of the value in the original document.
+    pub fn offset(&self) -> usize {
+        self.input.location_offset()
     }
-}
 
-/// Deserializes an SJSON string to a Rust value.
-#[inline]
-pub fn from_str<'a, T>(input: &'a str) -> Result<T>
-where
-    T: Deserialize<'a>,
-{
-    let mut de = Deserializer::from_str(input);
-    let t = T::deserialize(&mut de)?;
-    if de.input.is_empty() || parse_trailing_characters(de.input).is_ok() {
+    /// Deserializes the value with the default options.
+    ///
+    /// To enable other options, create a [`Deserializer::from_raw`] instead.
+    pub fn deserialize<T>(&self) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        let mut de = Deserializer::from_raw(self);
+        let t = T::deserialize(&mut de)?;
+        de.end()?;
         Ok(t)
-    } else {
-        Err(de.error(ErrorCode::TrailingCharacters))
     }
 }
 
-impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
-    type Error = Error;
+/// An iterator over a stream of values, as returned by [`Deserializer::into_iter`].
+///
+/// Each item is read like a nested value, so objects need their braces:
+///
+/// ```
+/// use serde_sjson::{Deserializer, Value};
+///
+/// let input = "{ level = debug, message = started }\n// The next one\n{ level = warn }";
+/// let values: Vec<Value> = Deserializer::from_str(input)
+///     .into_iter()
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+///
+/// assert_eq!(values.len(), 2);
+/// assert_eq!(values[1]["level"].as_str(), Some("warn"));
+/// ```
+///
+/// Whitespace and comments between values are skipped. The iterator ends at the end
+/// of the input, or after the first error.
+pub struct StreamDeserializer<'de, T> {
+    de: Deserializer<'de>,
+    failed: bool,
+    output: PhantomData<T>,
+}
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
-        }
+impl<'de, T> Iterator for StreamDeserializer<'de, T>
+where
+    T: Deserialize<'de>,
+{
+    type Item = Result<T>;
 
-        match self.peek_token()? {
-            Token::Boolean(_) => self.deserialize_bool(visitor),
-            Token::Float(_) => self.deserialize_f64(visitor),
-            Token::Integer(_) => self.deserialize_i64(visitor),
-            Token::Null => self.deserialize_unit(visitor),
-            Token::String(_) => self.deserialize_str(visitor),
-            Token::ArrayStart => self.deserialize_seq(visitor),
-            Token::ObjectStart => self.deserialize_map(visitor),
-            token => Err(self.error_with_token(ErrorCode::ExpectedValue, token)),
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
         }
+
+        let start = match parse_trailing_characters(self.de.input) {
+            Ok((span, _)) if span.is_empty() => return None,
+            Ok((span, _)) => span,
+            Err(err) => {
+                self.failed = true;
+                return Some(Err(self.de.parse_error(err)));
+            }
+        };
+
+        self.de.advance(start);
+        let value = T::deserialize(&mut self.de).map_err(|err| self.de.locate(err, start));
+        self.failed = value.is_err();
+        Some(value)
     }
+}
 
-    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+/// A container for deserializing Rust values from SJSON.
+pub struct Deserializer<'de> {
+    input: Span<'de>,
+    is_top_level: bool,
+    non_finite: NonFiniteSpellings,
+    // The keys and array indices leading to the current value.
+    // Only tracked while recording spans.
+    path: Vec<String>,
+    spans: Option<BTreeMap<String, SourceSpan>>,
+    doc_comments: Option<BTreeMap<String, String>>,
+    // All comments read so far, ordered by their byte offset.
+    comments: Option<Vec<(usize, String)>>,
+    indexed_arrays: bool,
+    bare_keys: bool,
+    deny_duplicate_keys: bool,
+    // The top-level keys to deserialize. Others are skipped.
+    projection: Option<Vec<String>>,
+    bytes_encoding: BytesEncoding,
+    assignments: Vec<Assignment>,
+    array_separators: Vec<ArraySeparator>,
+    // Where the top-level object ends, when embedded in a larger text.
+    terminator: Option<String>,
+    // The most recently peeked token, along with the offsets it can be read from,
+    // up to where it starts, and the input after it, so that reading it next
+    // doesn't parse it again.
+    peeked: Option<(RangeInclusive<usize>, Span<'de>, SpannedToken<'de>)>,
+}
+
+impl<'de> Deserializer<'de> {
+    #![allow(clippy::should_implement_trait)]
+    /// Creates a new `Deserializer` reading from a string.
+    pub fn from_str(input: &'de str) -> Self {
+        Self {
+            input: Span::from(input),
+            is_top_level: true,
+            non_finite: NonFiniteSpellings::default(),
+            path: Vec::new(),
+            spans: None,
+            doc_comments: None,
+            comments: None,
+            indexed_arrays: false,
+            bare_keys: false,
+            deny_duplicate_keys: false,
+            projection: None,
+            bytes_encoding: BytesEncoding::default(),
+            assignments: vec![Assignment::Equals],
+            array_separators: vec![ArraySeparator::Comma, ArraySeparator::Newline],
+            terminator: None,
+            peeked: None,
         }
+    }
 
-        if let Ok(Token::Boolean(val)) = self.parse(&parse_bool) {
-            visitor.visit_bool(val)
-        } else {
-            Err(self.error(ErrorCode::ExpectedBoolean))
+    /// Creates a new `Deserializer` reading a single value captured by [`Deserializer::read_tagged`].
+    ///
+    /// Unlike a whole document, the value doesn't have to be an object.
+    pub fn from_raw(raw: &RawValue<'de>) -> Self {
+        Self {
+            input: raw.input,
+            is_top_level: false,
+            ..Self::from_str("")
         }
     }
 
-    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_i64(visitor)
+    /// Enables reading objects with index keys as arrays.
+    ///
+    /// Some tables exported from Lua write arrays as `{ [1] = a, [2] = b }`.
+    /// With this enabled, such an object is accepted wherever an array is expected,
+    /// and its values are ordered by their index. Indices don't need to be contiguous:
+    /// gaps are closed up, so `{ [1] = a, [5] = b }` reads as `[a, b]`.
+    pub fn with_indexed_arrays(mut self) -> Self {
+        self.indexed_arrays = true;
+        self
     }
 
-    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_i64(visitor)
+    /// Enables reading object keys without a value.
+    ///
+    /// A key that isn't followed by `=`, like `value` instead of `value = null`,
+    /// reads as a unit value. This is what [`UnitFieldMode::BareKey`] writes.
+    ///
+    /// [`UnitFieldMode::BareKey`]: crate::UnitFieldMode::BareKey
+    pub fn with_bare_keys(mut self) -> Self {
+        self.bare_keys = true;
+        self
     }
 
-    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_i64(visitor)
+    /// Rejects objects that contain the same key more than once.
+    ///
+    /// By default, all entries are passed on, and it depends on the type being
+    /// deserialized which one is kept, or whether that is an error.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use serde::Deserialize;
+    ///
+    /// let mut de = serde_sjson::Deserializer::from_str("a = 1\nb = 2\na = 3")
+    ///     .with_deny_duplicate_keys();
+    /// let err = HashMap::<String, u8>::deserialize(&mut de).unwrap_err();
+    ///
+    /// assert_eq!(err.to_string(), "duplicate key `a` at line 3 column 1");
+    /// ```
+    pub fn with_deny_duplicate_keys(mut self) -> Self {
+        self.deny_duplicate_keys = true;
+        self
     }
 
-    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
-        }
+    /// Restricts deserialization to the given top-level keys.
+    ///
+    /// The values of all other top-level keys are skipped over without being
+    /// deserialized, which is considerably faster for wide documents where only
+    /// a few keys are of interest. Nested objects are not affected.
+    pub fn with_projection(mut self, keys: &[&str]) -> Self {
+        self.projection = Some(keys.iter().map(|key| key.to_string()).collect());
+        self
+    }
 
-        if let Ok(Token::Integer(val)) = self.parse(&parse_integer) {
-            visitor.visit_i64(val)
-        } else {
-            Err(self.error(ErrorCode::ExpectedInteger))
-        }
+    /// Enables recording the source span of every object value.
+    ///
+    /// Values are keyed by their path from the top level, with keys and array indices
+    /// joined by `.`, e.g. `settings.windows.0.title`.
+    /// The recorded spans can be retrieved via [`Deserializer::take_spans`].
+    pub fn with_spans(mut self) -> Self {
+        self.spans = Some(BTreeMap::new());
+        self
     }
 
-    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_i64(visitor)
+    /// Returns the spans recorded so far, leaving an empty map in their place.
+    ///
+    /// If recording wasn't enabled with [`Deserializer::with_spans`],
+    /// this is always empty.
+    pub fn take_spans(&mut self) -> BTreeMap<String, SourceSpan> {
+        self.spans.as_mut().map(std::mem::take).unwrap_or_default()
     }
 
-    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_i64(visitor)
+    /// Sets how byte arrays are represented.
+    ///
+    /// An array of integers is always accepted. With [`BytesEncoding::Base64`],
+    /// a base64 string is accepted as well, including for a plain `Vec<u8>`.
+    pub fn with_bytes_encoding(mut self, encoding: BytesEncoding) -> Self {
+        self.bytes_encoding = encoding;
+        self
     }
 
-    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_i64(visitor)
+    /// Enables reading floats written as hexadecimal literals, like `0x1.8p3`.
+    ///
+    /// The binary exponent is required. This is what [`Serializer::with_hex_floats`] writes.
+    ///
+    /// [`Serializer::with_hex_floats`]: crate::Serializer::with_hex_floats
+    pub fn with_hex_floats(mut self) -> Self {
+        self.input.extra.hex_floats = true;
+        self
     }
 
-    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_i64(visitor)
+    /// Sets the tokens that are accepted between a key and its value.
+    ///
+    /// This replaces the default of only accepting `=`.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_sjson::{Assignment, Deserializer};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Data {
+    ///     foo: u64,
+    ///     bar: u64,
+    /// }
+    ///
+    /// let mut de = Deserializer::from_str("foo => 1\nbar = 2")
+    ///     .with_assignments(&[Assignment::Equals, Assignment::FatArrow]);
+    /// let data = Data::deserialize(&mut de).unwrap();
+    /// assert_eq!((data.foo, data.bar), (1, 2));
+    /// ```
+    pub fn with_assignments(mut self, assignments: &[Assignment]) -> Self {
+        self.assignments = assignments.to_vec();
+        self.input.extra.colon_assignment = assignments.contains(&Assignment::Colon);
+        self
     }
 
-    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_f64(visitor)
+    /// Sets the tokens that are accepted between the elements of an array.
+    ///
+    /// This replaces the default of accepting commas and line breaks. Any of the given
+    /// separators may be used, even within the same array. Objects are not affected.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_sjson::{ArraySeparator, Deserializer};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Data {
+    ///     values: Vec<u64>,
+    /// }
+    ///
+    /// let mut de = Deserializer::from_str("values = [1; 2, 3]").with_array_separators(&[
+    ///     ArraySeparator::Comma,
+    ///     ArraySeparator::Newline,
+    ///     ArraySeparator::Semicolon,
+    /// ]);
+    /// let data = Data::deserialize(&mut de).unwrap();
+    /// assert_eq!(data.values, [1, 2, 3]);
+    /// ```
+    pub fn with_array_separators(mut self, separators: &[ArraySeparator]) -> Self {
+        self.array_separators = separators.to_vec();
+        self
     }
 
-    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
-        }
+    /// Enables treating all Unicode whitespace, like a non-breaking space, as whitespace
+    /// between tokens.
+    ///
+    /// By default, only spaces, tabs and line breaks are whitespace, and any other
+    /// whitespace character is part of an unquoted string. Line breaks still have to be
+    /// `\n` or `\r\n` to separate values.
+    pub fn with_unicode_whitespace(mut self) -> Self {
+        self.input.extra.unicode_whitespace = true;
+        self
+    }
 
-        if let Ok(Token::Float(val)) = self.parse(&parse_float) {
-            visitor.visit_f64(val)
-        } else {
-            Err(self.error(ErrorCode::ExpectedFloat))
-        }
+    /// Sets the maximum length of a string in bytes, including keys.
+    ///
+    /// For untrusted input, this stops reading a string once it gets too long, instead of
+    /// copying all of it. Escape sequences count as they are written, so `\n` is two bytes.
+    pub fn with_max_string_length(mut self, max: usize) -> Self {
+        self.input.extra.max_string_length = Some(max);
+        self
     }
 
-    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_str(visitor)
+    /// Enables recording the comments directly before each top-level key.
+    ///
+    /// Line comments are joined with line breaks, with a single leading space removed
+    /// from each line. A blank line between a comment and its key ends the block,
+    /// so such comments are not recorded.
+    /// The recorded comments can be retrieved via [`Deserializer::take_doc_comments`].
+    pub fn with_doc_comments(mut self) -> Self {
+        self.doc_comments = Some(BTreeMap::new());
+        self
     }
 
-    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
-        }
+    /// Returns the comments recorded so far, keyed by the top-level key they precede,
+    /// leaving an empty map in their place.
+    ///
+    /// If recording wasn't enabled with [`Deserializer::with_doc_comments`],
+    /// this is always empty.
+    pub fn take_doc_comments(&mut self) -> BTreeMap<String, String> {
+        self.doc_comments
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
 
-        if let Ok(Token::String(val)) = self.parse(&parse_string) {
-            visitor.visit_str(&val)
-        } else {
-            Err(self.error(ErrorCode::ExpectedString))
-        }
+    /// Enables recording every comment that is read, along with its byte offset in the input.
+    ///
+    /// Unlike [`Deserializer::with_doc_comments`], this includes comments anywhere
+    /// in the document, and keeps them verbatim, including the `//` or `/* */` delimiters.
+    /// The recorded comments can be retrieved via [`Deserializer::comments`].
+    pub fn with_comments(mut self) -> Self {
+        self.comments = Some(Vec::new());
+        self
     }
 
-    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_str(visitor)
+    /// Returns the comments recorded so far, ordered by their byte offset.
+    ///
+    /// Comments after the last value are only included once [`Deserializer::end`]
+    /// has been called. If recording wasn't enabled with [`Deserializer::with_comments`],
+    /// this is always empty.
+    pub fn comments(&self) -> &[(usize, String)] {
+        self.comments.as_deref().unwrap_or_default()
     }
 
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        unimplemented!()
+    /// Sets the spellings that are accepted for non-finite floating point numbers.
+    pub fn with_non_finite_spellings(mut self, spellings: NonFiniteSpellings) -> Self {
+        self.non_finite = spellings;
+        self
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        unimplemented!()
+    /// Returns whether the next value is an array or an object, without consuming it.
+    ///
+    /// Scalars return `None`. The implicit top-level object has no delimiters,
+    /// so it is not reported as an object.
+    /// With [`Deserializer::with_indexed_arrays`], objects with index keys are arrays.
+    pub fn peek_is_collection(&mut self) -> Result<Option<CollectionKind>> {
+        match self.peek_token()? {
+            Token::ArrayStart => Ok(Some(CollectionKind::Array)),
+            Token::ObjectStart if self.indexed_arrays && self.peek_indexed_object() => {
+                Ok(Some(CollectionKind::Array))
+            }
+            Token::ObjectStart => Ok(Some(CollectionKind::Object)),
+            _ => Ok(None),
+        }
     }
 
-    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+    /// Reads an object of a type tag and its content, like `{ type = vec3, value = [1, 2, 3] }`,
+    /// and returns the tag along with the content's source text.
+    ///
+    /// This allows choosing the type to deserialize the content into based on the tag,
+    /// with [`RawValue::deserialize`]. The keys may be in any order, and other keys are skipped.
+    /// At the top level, the document itself is read as the object.
+    ///
+    /// ```
+    /// use serde_sjson::Deserializer;
+    ///
+    /// let mut de = Deserializer::from_str("type = vec3\nvalue = [1, 2, 3]");
+    /// let (tag, value) = de.read_tagged("type", "value").unwrap();
+    /// de.end().unwrap();
+    ///
+    /// assert_eq!(tag, "vec3");
+    /// assert_eq!(value.deserialize::<[f32; 3]>().unwrap(), [1.0, 2.0, 3.0]);
+    /// ```
+    pub fn read_tagged(
+        &mut self,
+        tag_key: &str,
+        content_key: &str,
+    ) -> Result<(String, RawValue<'de>)> {
+        let start = self.input;
+        let top_level = std::mem::replace(&mut self.is_top_level, false);
+
+        if !top_level {
+            self.expect_token(Token::ObjectStart, ErrorCode::ExpectedMap)?;
         }
 
-        if self.peek_token()? == Token::Null {
-            let _ = self.next_token()?;
-            visitor.visit_none()
-        } else {
-            visitor.visit_some(self)
+        let value = self
+            .parse_tagged_entries(top_level, tag_key, content_key)
+            .map_err(|err| self.locate(err, start))?;
+
+        if !top_level {
+            self.expect_token(Token::ObjectEnd, ErrorCode::ExpectedMapEnd)?;
         }
+
+        Ok(value)
     }
 
-    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
-        }
+    /// Checks that only whitespace and comments remain in the input.
+    ///
+    /// This should be called after deserializing a value, to reject trailing characters.
+    pub fn end(&mut self) -> Result<()> {
+        self.record_comments(usize::MAX);
 
-        if let Ok(Token::Null) = self.parse(&parse_null) {
-            visitor.visit_unit()
-        } else {
-            Err(self.error(ErrorCode::ExpectedNull))
+        match parse_trailing_characters(self.input) {
+            Ok((span, _)) if span.is_empty() => Ok(()),
+            Err(err) => match unterminated_comment(self.input, &err) {
+                Some(span) => Err(self.error_at(ErrorCode::UnterminatedComment, span)),
+                None => Err(self.error(ErrorCode::TrailingCharacters)),
+            },
+            _ => Err(self.error(ErrorCode::TrailingCharacters)),
         }
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_unit(visitor)
+    /// Returns the part of the input that has not been consumed yet.
+    ///
+    /// This can be used instead of [`Deserializer::end`], when the SJSON is followed
+    /// by other content.
+    pub fn remaining(&self) -> &'de str {
+        self.input.fragment()
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    /// Turns the deserializer into an iterator over a stream of values, like
+    /// a log file with one object per line.
+    ///
+    /// See [`StreamDeserializer`].
+    pub fn into_iter<T>(mut self) -> StreamDeserializer<'de, T>
     where
-        V: serde::de::Visitor<'de>,
+        T: Deserialize<'de>,
     {
-        visitor.visit_newtype_struct(self)
+        self.is_top_level = false;
+        StreamDeserializer {
+            de: self,
+            failed: false,
+            output: PhantomData,
+        }
     }
 
-    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
-        }
+    fn parse(&mut self, f: &dyn Fn(Span) -> IResult<Span, Token>) -> Result<Token> {
+        f(self.input)
+            .map(|(span, token)| {
+                self.advance(span);
+                token
+            })
+            .map_err(|err| self.parse_error(err))
+    }
+
+    fn next_token(&mut self) -> Result<Token> {
+        self.next_spanned_token().map(|token| token.token)
+    }
 
-        if self.next_token()? != Token::ArrayStart {
-            return Err(self.error(ErrorCode::ExpectedArray));
+    fn next_spanned_token(&mut self) -> Result<SpannedToken<'de>> {
+        if let Some((span, token)) = self.take_peeked() {
+            self.advance(span);
+            return Ok(token);
         }
 
-        let value = visitor.visit_seq(Separated::new(self))?;
+        match parse_next_token(self.input) {
+            Ok((span, token)) => {
+                self.advance(span);
+                Ok(token)
+            }
+            Err(err) => Err(self.parse_error(err)),
+        }
+    }
 
-        if self.next_token()? == Token::ArrayEnd {
-            Ok(value)
+    // Consumes the next token, and reports an error at it if it isn't the expected one.
+    fn expect_token(&mut self, expected: Token, code: ErrorCode) -> Result<()> {
+        let token = self.next_spanned_token()?;
+        if token.token == expected {
+            Ok(())
         } else {
-            Err(self.error(ErrorCode::ExpectedArrayEnd))
+            Err(self.error_at(code, token.start))
         }
     }
 
-    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_seq(visitor)
+    fn peek_token(&mut self) -> Result<Token> {
+        self.peek_spanned_token().map(|token| token.token)
     }
 
-    fn deserialize_tuple_struct<V>(
-        self,
-        _name: &'static str,
-        _len: usize,
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_seq(visitor)
+    fn peek_spanned_token(&mut self) -> Result<SpannedToken<'de>> {
+        let offset = self.input.location_offset();
+        if let Some((range, _, token)) = &self.peeked {
+            if range.contains(&offset) {
+                return Ok(token.clone());
+            }
+        }
+
+        // The token can be reused until the input moves past where it starts.
+        match parse_next_token(self.input) {
+            Ok((span, token)) => {
+                let range = offset..=token.start.location_offset();
+                self.peeked = Some((range, span, token.clone()));
+                Ok(token)
+            }
+            Err(err) => Err(self.parse_error(err)),
+        }
     }
 
-    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if self.is_top_level {
-            self.is_top_level = false;
+    // Returns the most recently peeked token, if it is the next one in the input.
+    // Anything else that moves the input on leaves it behind, so it can't be used by mistake.
+    fn take_peeked(&mut self) -> Option<(Span<'de>, SpannedToken<'de>)> {
+        self.take_peeked_if(|_| true)
+    }
 
-            visitor.visit_map(Separated::new(self))
-        } else {
-            if self.next_token()? != Token::ObjectStart {
-                return Err(self.error(ErrorCode::ExpectedMap));
+    // Like `take_peeked`, but only if the token is the expected kind. Otherwise it stays
+    // available, so that a type-specific parser can try the input instead.
+    fn take_peeked_if(&mut self, f: fn(&Token) -> bool) -> Option<(Span<'de>, SpannedToken<'de>)> {
+        let offset = self.input.location_offset();
+        match self.peeked.take() {
+            Some((range, span, token)) if range.contains(&offset) && f(&token.token) => {
+                Some((span, token))
             }
-
-            let value = visitor.visit_map(Separated::new(self))?;
-            if self.next_token()? == Token::ObjectEnd {
-                Ok(value)
-            } else {
-                Err(self.error(ErrorCode::ExpectedMapEnd))
+            peeked => {
+                self.peeked = peeked;
+                None
             }
         }
     }
 
-    fn deserialize_struct<V>(
-        self,
-        _name: &'static str,
-        _fields: &'static [&'static str],
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_map(visitor)
+    // Returns the input after the token between a key and its value, if one is next.
+    fn peek_assignment(&self) -> Result<Option<Span<'de>>> {
+        let (span, _) =
+            parse_trailing_characters(self.input).map_err(|err| self.parse_error(err))?;
+
+        let assignment = Assignment::ALL.into_iter().find(|assignment| {
+            self.assignments.contains(assignment)
+                && span.fragment().starts_with(assignment.as_str())
+        });
+
+        Ok(assignment.map(|assignment| span.slice(assignment.as_str().len()..)))
     }
 
-    fn deserialize_enum<V>(
-        self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
-        visitor: V,
-    ) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        match self.next_token()? {
-            Token::String(val) => visitor.visit_enum(val.into_deserializer()),
-            Token::ObjectStart => {
-                let value = visitor.visit_enum(Enum::new(self))?;
+    // Consumes the token between a key and its value, and returns whether there was one.
+    fn parse_assignment(&mut self) -> Result<bool> {
+        match self.peek_assignment()? {
+            Some(span) => {
+                self.advance(span);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 
-                if self.next_token()? == Token::ObjectEnd {
-                    Ok(value)
-                } else {
-                    Err(self.error(ErrorCode::ExpectedMapEnd))
-                }
+    // Consumes the separator between array elements, and returns whether there was one.
+    fn parse_array_separator(&mut self) -> Result<bool> {
+        let (span, _) = parse_horizontal_space(self.input).map_err(|err| self.parse_error(err))?;
+
+        let len = self
+            .array_separators
+            .iter()
+            .find_map(|separator| separator.len_at(span.fragment()));
+
+        match len {
+            Some(len) => {
+                self.advance(span.slice(len..));
+                Ok(true)
             }
-            _ => Err(self.error(ErrorCode::ExpectedEnum)),
+            None => Ok(false),
         }
     }
 
-    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        if let Ok(Token::String(val)) = self.parse(&parse_identifier) {
-            visitor.visit_str(&val)
+    // Checks whether the terminator of an embedded document is next, and returns its offset.
+    fn peek_terminator(&self) -> Option<usize> {
+        let terminator = self.terminator.as_deref()?;
+        let (span, _) = parse_trailing_characters(self.input).ok()?;
+
+        if span.fragment().starts_with(terminator) {
+            Some(span.location_offset())
         } else {
-            Err(self.error(ErrorCode::ExpectedString))
+            None
         }
     }
 
-    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        self.deserialize_any(visitor)
+    // Checks whether there is nothing but whitespace and comments from `start`
+    // to the end of the document.
+    fn is_empty_document(&self, start: Span<'de>) -> bool {
+        let Ok((span, _)) = parse_trailing_characters(start) else {
+            return false;
+        };
+
+        match &self.terminator {
+            Some(terminator) => span.is_empty() || span.fragment().starts_with(terminator.as_str()),
+            None => span.is_empty(),
+        }
     }
-}
 
-struct Separated<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
-    first: bool,
-}
+    // Moves the input on to `span`, recording the comments that are skipped on the way.
+    fn advance(&mut self, span: Span<'de>) {
+        self.record_comments(span.location_offset());
+        self.input = span;
+    }
 
-impl<'a, 'de: 'a> Separated<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        Self { de, first: true }
+    // Records the comments at the start of the input that begin before `end`.
+    // Parts of the input may be read more than once, so known comments are skipped.
+    fn record_comments(&mut self, end: usize) {
+        let Some(comments) = self.comments.as_mut() else {
+            return;
+        };
+
+        for (offset, text) in parse_comments(self.input) {
+            if offset >= end {
+                break;
+            }
+
+            if let Err(index) = comments.binary_search_by_key(&offset, |(offset, _)| *offset) {
+                comments.insert(index, (offset, text.to_string()));
+            }
+        }
     }
-}
 
-impl<'de, 'a> serde::de::SeqAccess<'de> for Separated<'a, 'de> {
-    type Error = Error;
+    // Records the comments before the next key, if there are any.
+    fn record_doc_comment(&mut self) {
+        let Some(doc_comments) = self.doc_comments.as_mut() else {
+            return;
+        };
 
-    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
-    where
-        T: serde::de::DeserializeSeed<'de>,
-    {
-        if self.de.peek_token()? == Token::ArrayEnd {
-            return Ok(None);
+        let Ok((_, Token::String(key))) = parse_string(self.input) else {
+            return;
+        };
+
+        let lines: Vec<_> = parse_leading_comments(self.input)
+            .into_iter()
+            .map(|comment| comment.strip_prefix(' ').unwrap_or(comment).trim_end())
+            .collect();
+
+        if !lines.is_empty() {
+            doc_comments.insert(key, lines.join("\n"));
         }
+    }
+
+    // Consumes the next value without deserializing it.
+    // Nested objects and arrays are skipped by counting delimiters, rather than recursing.
+    fn skip_value(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+
+        loop {
+            // Keys in nested objects may be followed by any of the enabled assignments.
+            if depth > 0 && self.parse_assignment()? {
+                continue;
+            }
 
-        if !self.first && self.de.parse(&parse_separator)? != Token::Separator {
-            return Err(self.de.error(ErrorCode::ExpectedArraySeparator));
+            let token = self.next_spanned_token()?;
+            match token.token {
+                Token::ObjectStart | Token::ArrayStart => depth += 1,
+                Token::ObjectEnd | Token::ArrayEnd if depth > 0 => depth -= 1,
+                Token::Eof => return Err(self.error(ErrorCode::ExpectedValue)),
+                Token::ObjectEnd | Token::ArrayEnd | Token::Equals | Token::Separator
+                    if depth == 0 =>
+                {
+                    return Err(self.error_with_token(ErrorCode::ExpectedValue, token));
+                }
+                _ => {}
+            }
+
+            if depth == 0 {
+                return Ok(());
+            }
         }
+    }
 
-        self.first = false;
+    // Reads the entries of the object for `read_tagged`, up to its end.
+    fn parse_tagged_entries(
+        &mut self,
+        top_level: bool,
+        tag_key: &str,
+        content_key: &str,
+    ) -> Result<(String, RawValue<'de>)> {
+        let mut access = Separated::new(self);
+        access.top_level = top_level;
+
+        let mut tag = None;
+        let mut content = None;
+
+        while let Some(key) = access.next_key_seed(StringSeed)? {
+            if key == tag_key {
+                tag = Some(access.next_value_seed(StringSeed)?);
+            } else if key == content_key {
+                if !access.de.parse_assignment()? {
+                    return Err(access.de.error(ErrorCode::ExpectedMapEquals));
+                }
+
+                access.key = None;
+                content = Some(access.de.parse_raw()?);
+            } else {
+                access.next_value::<IgnoredAny>()?;
+            }
+        }
 
-        // TODO: Shouldn't I check that this is a valid value?
-        seed.deserialize(&mut *self.de).map(Some)
+        let missing = |key| serde::de::Error::custom(format_args!("missing field `{}`", key));
+        match (tag, content) {
+            (Some(tag), Some(content)) => Ok((tag, content)),
+            (None, _) => Err(missing(tag_key)),
+            (_, None) => Err(missing(content_key)),
+        }
     }
-}
 
-impl<'de, 'a> serde::de::MapAccess<'de> for Separated<'a, 'de> {
-    type Error = Error;
+    // Consumes the next value, and returns its source text.
+    fn parse_raw(&mut self) -> Result<RawValue<'de>> {
+        // Skip whitespace and comments, so that the text starts at the value itself.
+        let start = parse_trailing_characters(self.input)
+            .map(|(span, _)| span)
+            .unwrap_or(self.input);
 
-    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
-    where
-        K: serde::de::DeserializeSeed<'de>,
-    {
-        if matches!(self.de.peek_token()?, Token::ObjectEnd | Token::Eof) {
-            return Ok(None);
+        self.skip_value()?;
+
+        let len = self.input.location_offset() - start.location_offset();
+        Ok(RawValue {
+            input: start.slice(..len),
+        })
+    }
+
+    // Checks whether the next key is excluded by the projection, and if so,
+    // consumes it along with its value.
+    fn skip_unprojected_entry(&mut self) -> Result<bool> {
+        let Some(projection) = &self.projection else {
+            return Ok(false);
+        };
+
+        let Ok((span, Token::String(key))) = parse_string(self.input) else {
+            return Ok(false);
+        };
+
+        if projection.contains(&key) {
+            return Ok(false);
         }
 
-        if !self.first && self.de.parse(&parse_separator)? != Token::Separator {
-            return Err(self.de.error(ErrorCode::ExpectedMapSeparator));
+        self.advance(span);
+
+        if self.bare_keys && self.peek_assignment()?.is_none() {
+            return Ok(true);
         }
 
-        self.first = false;
+        if !self.parse_assignment()? {
+            return Err(self.error(ErrorCode::ExpectedMapEquals));
+        }
 
-        // TODO: Shouldn't I check that this is a valid identifier?
-        seed.deserialize(&mut *self.de).map(Some)
+        self.skip_value()?;
+        Ok(true)
     }
 
-    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
-    where
-        V: serde::de::DeserializeSeed<'de>,
-    {
-        if self.de.next_token()? != Token::Equals {
-            return Err(self.de.error(ErrorCode::ExpectedMapEquals));
+    // Reads a base64 string as bytes, if that encoding is enabled and a string is next.
+    fn parse_base64(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.bytes_encoding != BytesEncoding::Base64
+            || matches!(self.peek_token()?, Token::ArrayStart | Token::ObjectStart)
+        {
+            return Ok(None);
         }
 
-        // TODO: Shouldn't I check that this is a valid value?
-        seed.deserialize(&mut *self.de)
+        let Ok((span, Token::String(val))) = parse_string(self.input) else {
+            return Ok(None);
+        };
+
+        let bytes = BASE64
+            .decode(val)
+            .map_err(|_| self.error(ErrorCode::InvalidBase64))?;
+        self.advance(span);
+        Ok(Some(bytes))
     }
-}
 
-struct Enum<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
-}
+    // Checks whether the next value is an object with index keys, like `{ [1] = a }`.
+    fn peek_indexed_object(&self) -> bool {
+        let Ok((
+            span,
+            SpannedToken {
+                token: Token::ObjectStart,
+                ..
+            },
+        )) = parse_next_token(self.input)
+        else {
+            return false;
+        };
 
-impl<'a, 'de> Enum<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        Self { de }
+        matches!(
+            parse_next_token(span),
+            Ok((
+                _,
+                SpannedToken {
+                    token: Token::ArrayStart,
+                    ..
+                }
+            ))
+        )
     }
-}
 
-impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de> {
-    type Error = Error;
-    type Variant = Self;
+    // Reads an object with index keys, and returns the start of each value, ordered by index.
+    fn parse_indexed_entries(&mut self) -> Result<Vec<Span<'de>>> {
+        self.expect_token(Token::ObjectStart, ErrorCode::ExpectedArray)?;
 
-    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
-    where
-        V: serde::de::DeserializeSeed<'de>,
-    {
-        let val = seed.deserialize(&mut *self.de)?;
+        let mut entries = Vec::new();
 
-        if self.de.next_token()? == Token::Equals {
-            Ok((val, self))
-        } else {
-            Err(self.de.error(ErrorCode::ExpectedMapEquals))
+        loop {
+            if self.peek_token()? == Token::ObjectEnd {
+                break;
+            }
+
+            if !entries.is_empty() && self.parse(&parse_separator)? != Token::Separator {
+                return Err(self.error(ErrorCode::ExpectedMapSeparator));
+            }
+
+            if self.peek_token()? == Token::ObjectEnd {
+                break;
+            }
+
+            self.expect_token(Token::ArrayStart, ErrorCode::ExpectedArrayIndex)?;
+
+            let Token::Integer(index) = self.next_token()? else {
+                return Err(self.error(ErrorCode::ExpectedArrayIndex));
+            };
+
+            self.expect_token(Token::ArrayEnd, ErrorCode::ExpectedArrayIndex)?;
+
+            if !self.parse_assignment()? {
+                return Err(self.error(ErrorCode::ExpectedMapEquals));
+            }
+
+            let start = parse_trailing_characters(self.input)
+                .map(|(span, _)| span)
+                .unwrap_or(self.input);
+            IgnoredAny::deserialize(&mut *self)?;
+
+            entries.push((index, start));
         }
+
+        self.expect_token(Token::ObjectEnd, ErrorCode::ExpectedMapEnd)?;
+
+        // The sort is stable, so duplicate indices keep the order they were written in.
+        entries.sort_by_key(|(index, _)| *index);
+        Ok(entries.into_iter().map(|(_, start)| start).collect())
     }
-}
 
-impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
-    type Error = Error;
+    // An unquoted string ends at a tab. If there is more content after it on the same line,
+    // it was most likely meant to be part of the string, so we can give a better
+    // error than whatever would fail later on.
+    fn check_unquoted_tab(&self, start: Span<'de>) -> Result<()> {
+        let quoted = parse_trailing_characters(start)
+            .map(|(span, _)| span.fragment().starts_with('"'))
+            .unwrap_or_default();
 
-    fn unit_variant(self) -> Result<()> {
-        Err(self.de.error(ErrorCode::ExpectedString))
+        let rest = self.input.fragment();
+        if quoted || !rest.starts_with('\t') {
+            return Ok(());
+        }
+
+        let rest = rest.trim_start_matches([' ', '\t']);
+        if rest.is_empty() || rest.starts_with(['\n', '\r', ',', '}', ']', '/']) {
+            Ok(())
+        } else {
+            Err(self.error(ErrorCode::UnquotedTab))
+        }
     }
 
-    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
-    where
-        T: serde::de::DeserializeSeed<'de>,
-    {
-        seed.deserialize(self.de)
+    // Places an error without a location at the value that starts at `start`.
+    fn locate(&self, err: Error, start: Span<'de>) -> Error {
+        let start = parse_trailing_characters(start)
+            .map(|(span, _)| span)
+            .unwrap_or(start);
+        err.or_located_at(
+            start.location_line(),
+            start.get_utf8_column(),
+            start.fragment(),
+        )
     }
 
-    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        serde::Deserializer::deserialize_seq(self.de, visitor)
+    fn parse_error(&self, err: nom::Err<nom::error::Error<Span<'_>>>) -> Error {
+        if let Some(span) = unterminated_comment(self.input, &err) {
+            self.error_at(ErrorCode::UnterminatedComment, span)
+        } else if is_string_too_long(&err) {
+            self.string_too_long()
+        } else if is_invalid_unicode_escape(&err) {
+            self.error(ErrorCode::InvalidUnicodeEscape)
+        } else {
+            self.error(ErrorCode::Message(err.to_string()))
+        }
     }
 
-    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        serde::Deserializer::deserialize_map(self.de, visitor)
+    // Reports that a value other than the one expected was found. Since the typed parsers
+    // can't tell a broken comment from any other mismatch, that is checked separately.
+    fn expected(&self, code: ErrorCode) -> Error {
+        // The mismatched value is reported where it starts, after any whitespace.
+        let start = match parse_trailing_characters(self.input) {
+            Ok((span, _)) => span,
+            Err(err) => match unterminated_comment(self.input, &err) {
+                Some(span) => return self.error_at(ErrorCode::UnterminatedComment, span),
+                None => self.input,
+            },
+        };
+
+        match parse_string(self.input) {
+            Err(err) if is_string_too_long(&err) => self.string_too_long(),
+            Err(err) if is_invalid_unicode_escape(&err) => {
+                self.error(ErrorCode::InvalidUnicodeEscape)
+            }
+            _ => self.error_at(code, start),
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::path::PathBuf;
+    fn parse_f64(&mut self) -> Result<f64> {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
 
-    use crate::error::{Error, ErrorCode};
-    use crate::from_str;
+        // Non-finite spellings may look like the start of a regular number,
+        // so they have to be checked first.
+        if let Some((span, val)) = self.parse_non_finite() {
+            self.advance(span);
+            return Ok(val);
+        }
 
-    macro_rules! assert_value_ok {
-        ($type:ty, $json:expr) => {
-            assert_value_ok!($type, Default::default(), $json)
-        };
-        ($type:ty, $expected:expr, $json:expr) => {{
-            #[derive(Debug, serde::Deserialize, PartialEq)]
-            struct Value {
-                value: $type,
-            }
+        if let Ok((span, Token::Float(val))) = parse_hex_float(self.input) {
+            self.advance(span);
+            return Ok(val);
+        }
 
-            let expected = Value { value: $expected };
+        if let Ok(Token::Float(val)) = self.parse(&parse_float) {
+            Ok(val)
+        } else {
+            Err(self.expected(ErrorCode::ExpectedFloat))
+        }
+    }
 
-            let json = format!("value = {}", $json);
-            let actual = from_str::<Value>(&json).unwrap();
-            assert_eq!(actual, expected);
-        }};
+    // Only an unquoted spelling is a float, a quoted one stays a string.
+    fn parse_non_finite(&self) -> Option<(Span<'de>, f64)> {
+        let (span, val) = parse_identifier(self.input).ok()?;
+        let val = self.non_finite.parse(val)?;
+        Some((span, val))
     }
 
-    macro_rules! assert_ok {
-        ($type:ty, $expected:expr, $json:expr) => {{
-            let actual = from_str::<$type>($json).unwrap();
-            assert_eq!(actual, $expected);
-        }};
+    // Reports digits that denote an integer, but are out of range, at the start of the number.
+    // Returns `None` if the next value isn't an integer at all.
+    fn integer_overflow(&self) -> Option<Error> {
+        let (start, _) = parse_trailing_characters(self.input).ok()?;
+        parse_integer_literal(start).ok()?;
+
+        Some(self.error_at(ErrorCode::IntegerOverflow, start))
     }
 
-    macro_rules! assert_value_err {
-        ($type:ty, $expected:expr, $json:expr) => {{
-            #[derive(Debug, serde::Deserialize, PartialEq)]
-            struct Value {
-                value: $type,
-            }
+    fn string_too_long(&self) -> Error {
+        let max = self.input.extra.max_string_length.unwrap_or_default();
+        self.error(ErrorCode::StringTooLong(max))
+    }
+
+    fn error(&self, code: ErrorCode) -> Error {
+        self.error_at(code, self.input)
+    }
+
+    fn error_at(&self, code: ErrorCode, span: Span<'_>) -> Error {
+        Error::new(
+            code,
+            span.location_line(),
+            span.get_utf8_column(),
+            Some(span.fragment().to_string()),
+        )
+    }
+
+    fn error_with_token(&self, code: ErrorCode, token: SpannedToken<'_>) -> Error {
+        Error::with_token(
+            code,
+            token.line(),
+            token.column(),
+            Some(token.start.fragment().to_string()),
+            token.token,
+        )
+    }
+}
+
+/// Deserializes an SJSON string to a Rust value.
+#[inline]
+pub fn from_str<'a, T>(input: &'a str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input);
+    let t = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(t)
+}
+
+/// Deserializes a Rust value from SJSON read from an `io::Read`.
+///
+/// The whole input is read into memory before it is parsed. Since the input is
+/// dropped afterwards, the value cannot borrow from it.
+pub fn from_reader<R, T>(mut reader: R) -> Result<T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    from_slice(&buf)
+}
+
+/// Deserializes an SJSON byte slice to a Rust value.
+///
+/// The input must be valid UTF-8. Otherwise, the error points at the first
+/// invalid byte.
+pub fn from_slice<'a, T>(input: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let input = std::str::from_utf8(input).map_err(|err| invalid_utf8(input, err))?;
+    from_str(input)
+}
+
+fn invalid_utf8(input: &[u8], err: std::str::Utf8Error) -> Error {
+    // Everything before the error is valid, so the location can be counted in there.
+    let valid = std::str::from_utf8(&input[..err.valid_up_to()]).unwrap_or_default();
+    let line = valid.matches('\n').count() as u32 + 1;
+    let column = valid
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+
+    let msg = format!("input is not valid UTF-8: {}", err);
+    Error::new(ErrorCode::Message(msg), line, column, None)
+}
+
+/// Deserializes only the given top-level keys of an SJSON string to a Rust value.
+///
+/// See [`Deserializer::with_projection`].
+pub fn from_str_projection<'a, T>(input: &'a str, keys: &[&str]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input).with_projection(keys);
+    let t = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(t)
+}
+
+/// Deserializes an SJSON string to a Rust value, and returns it along with the number
+/// of bytes that were consumed.
+///
+/// Deserialization stops at the end of the input, or at a `}` that closes the
+/// top-level object, so the SJSON may be followed by other content. Whitespace and
+/// comments after the last value are not counted as consumed.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Settings {
+///     width: u32,
+/// }
+///
+/// let document = "width = 800\n} height = 600";
+/// let (settings, len) = serde_sjson::from_str_with_len::<Settings>(document).unwrap();
+///
+/// assert_eq!(settings.width, 800);
+/// assert_eq!(&document[len..], "\n} height = 600");
+/// ```
+pub fn from_str_with_len<'a, T>(input: &'a str) -> Result<(T, usize)>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input);
+    let t = T::deserialize(&mut de)?;
+    Ok((t, de.input.location_offset()))
+}
+
+/// Deserializes an SJSON string to a Rust value, and returns it along with the rest
+/// of the input that was not consumed.
+///
+/// Where deserialization stops is the same as for [`from_str_with_len`].
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Header {
+///     version: u32,
+/// }
+///
+/// let blob = "version = 2\n}\x00\x01\x02";
+/// let (header, rest) = serde_sjson::from_str_partial::<Header>(blob).unwrap();
+///
+/// assert_eq!(header.version, 2);
+/// assert_eq!(rest, "\n}\x00\x01\x02");
+/// ```
+pub fn from_str_partial<'a, T>(input: &'a str) -> Result<(T, &'a str)>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input);
+    let t = T::deserialize(&mut de)?;
+    Ok((t, de.remaining()))
+}
+
+/// Deserializes a Rust value from SJSON embedded in a larger text, and returns it
+/// along with the byte offset where the SJSON ended.
+///
+/// Deserialization stops before the first top-level key that starts with `terminator`,
+/// such as a closing `---` fence, or at the end of the input. The returned offset is
+/// the start of the terminator.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct FrontMatter {
+///     title: String,
+/// }
+///
+/// let document = "title = Example\n---\nThe rest of the document.";
+/// let (front_matter, offset) =
+///     serde_sjson::from_str_until::<FrontMatter>(document, "---").unwrap();
+///
+/// assert_eq!(front_matter.title, "Example");
+/// assert_eq!(&document[offset..], "---\nThe rest of the document.");
+/// ```
+pub fn from_str_until<'a, T>(input: &'a str, terminator: &str) -> Result<(T, usize)>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input);
+    de.terminator = Some(terminator.to_string());
+
+    let t = T::deserialize(&mut de)?;
+
+    match de.peek_terminator() {
+        Some(offset) => Ok((t, offset)),
+        None => {
+            de.end()?;
+            Ok((t, input.len()))
+        }
+    }
+}
+
+/// Deserializes several SJSON documents, separated by lines that consist of only `separator`,
+/// such as `---`.
+///
+/// Each document is deserialized as its own top-level object. Documents that are empty,
+/// or only contain comments, are skipped, so the input may also start or end with a separator.
+/// The line and column of an error refer to the whole input.
+///
+/// The input is split before it is parsed, so a separator line inside a multi-line string
+/// ends the document, too.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Record {
+///     level: String,
+/// }
+///
+/// let input = "level = info\n---\nlevel = warn\n---\n";
+/// let records: Vec<Record> = serde_sjson::from_str_multi(input, "---").unwrap();
+///
+/// assert_eq!(records.len(), 2);
+/// assert_eq!(records[1].level, "warn");
+/// ```
+pub fn from_str_multi<'a, T>(input: &'a str, separator: &str) -> Result<Vec<T>>
+where
+    T: Deserialize<'a>,
+{
+    let source = Span::from(input);
+    let mut values = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+
+    for line in input.split_inclusive('\n') {
+        if line.trim() == separator {
+            values.extend(from_span(source.slice(start..offset))?);
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+
+    values.extend(from_span(source.slice(start..))?);
+    Ok(values)
+}
+
+// Deserializes one of the documents read by `from_str_multi`. Since the span still knows
+// where it is in the whole input, errors are located there.
+fn from_span<'a, T>(input: Span<'a>) -> Result<Option<T>>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer {
+        input,
+        ..Deserializer::from_str("")
+    };
+
+    if de.is_empty_document(input) {
+        return Ok(None);
+    }
+
+    let t = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(Some(t))
+}
+
+/// Deserializes an SJSON string to a Rust value, and records the comments before
+/// each top-level key.
+///
+/// See [`Deserializer::with_doc_comments`] for how comments are recorded.
+pub fn from_str_with_doc_comments<'a, T>(input: &'a str) -> Result<(T, BTreeMap<String, String>)>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input).with_doc_comments();
+    let t = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok((t, de.take_doc_comments()))
+}
+
+/// Deserializes an SJSON string to a Rust value, and records where each object value
+/// was found in the source.
+///
+/// See [`Deserializer::with_spans`] for how values are keyed.
+pub fn from_str_with_spans<'a, T>(input: &'a str) -> Result<(T, BTreeMap<String, SourceSpan>)>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_str(input).with_spans();
+    let t = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok((t, de.take_spans()))
+}
+
+impl<'de> serde::de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // The document itself is an object, unless it is a single array. This is what
+        // self-describing types, like internally tagged enums, end up with.
+        if self.is_top_level {
+            if self.peek_token().ok() == Some(Token::ArrayStart) {
+                return self.deserialize_seq(visitor);
+            }
+
+            return self.deserialize_map(visitor);
+        }
+
+        let token = self.peek_spanned_token()?;
+
+        // Integers too large for `u64` are valid floats, but would lose precision.
+        // Hexadecimal ones aren't even that, but are still no string.
+        if matches!(token.token, Token::Float(_) | Token::String(_)) {
+            if let Some(err) = self.integer_overflow() {
+                return Err(err);
+            }
+        }
+
+        match token.token {
+            Token::Boolean(_) => self.deserialize_bool(visitor),
+            Token::Float(_) => self.deserialize_f64(visitor),
+            Token::Integer(_) | Token::UnsignedInteger(_) => self.deserialize_i64(visitor),
+            Token::Null => self.deserialize_unit(visitor),
+            Token::String(_) if self.parse_non_finite().is_some() => self.deserialize_f64(visitor),
+            Token::String(_) => self.deserialize_str(visitor),
+            Token::ArrayStart => self.deserialize_seq(visitor),
+            Token::ObjectStart if self.indexed_arrays && self.peek_indexed_object() => {
+                self.deserialize_seq(visitor)
+            }
+            Token::ObjectStart => self.deserialize_map(visitor),
+            _ => Err(self.error_with_token(ErrorCode::ExpectedValue, token)),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
+
+        // In arrays, the element has usually been peeked already.
+        if let Some((
+            span,
+            SpannedToken {
+                token: Token::Boolean(val),
+                ..
+            },
+        )) = self.take_peeked_if(|token| matches!(token, Token::Boolean(_)))
+        {
+            self.advance(span);
+            return visitor.visit_bool(val);
+        }
+
+        if let Ok(Token::Boolean(val)) = self.parse(&parse_bool) {
+            visitor.visit_bool(val)
+        } else {
+            Err(self.expected(ErrorCode::ExpectedBoolean))
+        }
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
+
+        // In arrays, the element has usually been peeked already.
+        let token = match self
+            .take_peeked_if(|token| matches!(token, Token::Integer(_) | Token::UnsignedInteger(_)))
+        {
+            Some((span, token)) => {
+                self.advance(span);
+                Ok(token.token)
+            }
+            None => self.parse(&parse_integer),
+        };
+
+        match token {
+            Ok(Token::Integer(val)) => visitor.visit_i64(val),
+            Ok(Token::UnsignedInteger(val)) => visitor.visit_u64(val),
+            _ => Err(self
+                .integer_overflow()
+                .unwrap_or_else(|| self.expected(ErrorCode::ExpectedInteger))),
+        }
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let start = self.input;
+        let val = self.parse_f64()?;
+
+        // Only finite values that don't fit are rejected, not spellings of infinity.
+        if val.is_finite() && (val as f32).is_infinite() {
+            let start = parse_trailing_characters(start).map_or(start, |(span, _)| span);
+            return Err(self.error_at(ErrorCode::FloatOverflow, start));
+        }
+
+        visitor.visit_f32(val as f32)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let val = self.parse_f64()?;
+        visitor.visit_f64(val)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
+
+        let start = self.input;
+
+        if let Ok((span, val)) = parse_str(self.input) {
+            let mut chars = val.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                self.advance(span);
+                self.check_unquoted_tab(start)?;
+                return visitor.visit_char(c);
+            }
+        }
+
+        Err(self.expected(ErrorCode::ExpectedChar))
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
+
+        let start = self.input;
+
+        if let Ok((span, val)) = parse_str(self.input) {
+            self.advance(span);
+            self.check_unquoted_tab(start)?;
+            match val {
+                Cow::Borrowed(val) => visitor.visit_borrowed_str(val),
+                Cow::Owned(val) => visitor.visit_str(&val),
+            }
+        } else {
+            Err(self.expected(ErrorCode::ExpectedString))
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
+
+        if let Some(bytes) = self.parse_base64()? {
+            return visitor.visit_bytes(&bytes);
+        }
+
+        self.expect_token(Token::ArrayStart, ErrorCode::ExpectedArray)?;
+
+        let mut bytes = Vec::new();
+        let mut seq = Separated::new(self);
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+
+        self.expect_token(Token::ArrayEnd, ErrorCode::ExpectedArrayEnd)?;
+        visitor.visit_bytes(&bytes)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
+
+        if self.peek_token()? == Token::Null {
+            let _ = self.next_token()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        if self.is_top_level {
+            return Err(self.error(ErrorCode::ExpectedTopLevelObject));
+        }
+
+        if let Ok(Token::Null) = self.parse(&parse_null) {
+            visitor.visit_unit()
+        } else {
+            Err(self.expected(ErrorCode::ExpectedNull))
+        }
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // At the top level, it is read like a struct without fields, which ignores
+        // any entries.
+        if self.is_top_level {
+            serde::Deserializer::deserialize_map(&mut *self, serde::de::IgnoredAny)?;
+            return visitor.visit_unit();
+        }
+
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // A document of key-value pairs has no order to read them in.
+        // But a document can also consist of a single array.
+        if self.is_top_level {
+            if self.peek_token().ok() != Some(Token::ArrayStart) {
+                return Err(self.error(ErrorCode::TopLevelSequence));
+            }
+
+            self.is_top_level = false;
+        }
+
+        if self.indexed_arrays && self.peek_token()? == Token::ObjectStart {
+            let entries = self.parse_indexed_entries()?;
+            let end = self.input;
+
+            let value = visitor.visit_seq(Indexed::new(self, entries))?;
+
+            self.advance(end);
+            return Ok(value);
+        }
+
+        if let Some(bytes) = self.parse_base64()? {
+            return visitor.visit_seq(SeqDeserializer::new(bytes.into_iter()));
+        }
+
+        self.expect_token(Token::ArrayStart, ErrorCode::ExpectedArray)?;
+
+        let value = visitor.visit_seq(Separated::new(self))?;
+
+        self.expect_token(Token::ArrayEnd, ErrorCode::ExpectedArrayEnd)?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // The top-level object may also be wrapped in braces, like a nested one.
+        if self.is_top_level && self.peek_token().ok() != Some(Token::ObjectStart) {
+            self.is_top_level = false;
+
+            let start = self.input;
+            let mut access = Separated::new(self);
+            access.top_level = true;
+            // Errors about the map as a whole, like a missing field, or those from values
+            // that serde buffered, like flattened fields, are placed at its start.
+            visitor.visit_map(access).map_err(|err| {
+                if self.is_empty_document(start) {
+                    err.in_empty_document(start.location_line(), start.get_utf8_column())
+                } else {
+                    self.locate(err, start)
+                }
+            })
+        } else {
+            self.is_top_level = false;
+
+            let start = self.input;
+            self.expect_token(Token::ObjectStart, ErrorCode::ExpectedMap)?;
+
+            let value = visitor
+                .visit_map(Separated::new(self))
+                .map_err(|err| self.locate(err, start))?;
+            self.expect_token(Token::ObjectEnd, ErrorCode::ExpectedMapEnd)?;
+            Ok(value)
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        match self.next_token()? {
+            Token::String(val) => visitor.visit_enum(val.into_deserializer()),
+            Token::ObjectStart => {
+                let value = visitor.visit_enum(Enum::new(self))?;
+
+                self.expect_token(Token::ObjectEnd, ErrorCode::ExpectedMapEnd)?;
+                Ok(value)
+            }
+            _ => Err(self.error(ErrorCode::ExpectedEnum)),
+        }
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // Borrowed, so that keys buffered by `#[serde(flatten)]` can still be read as `&str`.
+        // Quoted keys, like those written with escaped non-ASCII characters, are read too.
+        if let Ok((span, val)) = parse_str(self.input) {
+            self.advance(span);
+            match val {
+                Cow::Borrowed(val) => visitor.visit_borrowed_str(val),
+                Cow::Owned(val) => visitor.visit_str(&val),
+            }
+        } else {
+            Err(self.expected(ErrorCode::ExpectedString))
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+}
+
+struct Separated<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+    // The index of the next array element.
+    index: usize,
+    // The most recent object key. Only tracked while recording spans.
+    key: Option<String>,
+    // Where the most recent object key starts, to name it in errors.
+    key_input: Option<Span<'de>>,
+    // Whether this is the implicit object that makes up the whole document.
+    top_level: bool,
+    // The keys read so far. Only tracked when duplicate keys are denied.
+    keys: HashSet<String>,
+}
+
+impl<'a, 'de: 'a> Separated<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self {
+            de,
+            first: true,
+            index: 0,
+            key: None,
+            key_input: None,
+            top_level: false,
+            keys: HashSet::new(),
+        }
+    }
+
+    // Adds the current key to the path of an error from its value.
+    fn in_key(&self, err: Error) -> Error {
+        match self.key_input.map(parse_string) {
+            Some(Ok((_, Token::String(key)))) => err.in_key(&key),
+            _ => err,
+        }
+    }
+
+    fn deserialize_recorded<T>(&mut self, segment: String, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        // Skip whitespace and comments, so that the span starts at the value itself.
+        let start = parse_trailing_characters(self.de.input)
+            .map(|(span, _)| span)
+            .unwrap_or(self.de.input);
+
+        self.de.path.push(segment);
+        let value = seed.deserialize(&mut *self.de);
+        let path = self.de.path.join(".");
+        self.de.path.pop();
+        let value = value?;
+
+        if let Some(spans) = self.de.spans.as_mut() {
+            let span = SourceSpan {
+                range: start.location_offset()..self.de.input.location_offset(),
+                line: start.location_line(),
+                column: start.get_utf8_column(),
+            };
+            spans.insert(path, span);
+        }
+
+        Ok(value)
+    }
+}
+
+impl<'de, 'a> serde::de::SeqAccess<'de> for Separated<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        if self.de.peek_token()? == Token::ArrayEnd {
+            return Ok(None);
+        }
+
+        if !self.first {
+            if !self.de.parse_array_separator()? {
+                return Err(self.de.error(ErrorCode::ExpectedArraySeparator));
+            }
+
+            // A trailing separator after the last element.
+            if self.de.peek_token()? == Token::ArrayEnd {
+                return Ok(None);
+            }
+        }
+
+        self.first = false;
+        let start = self.de.input;
+        let index = self.index;
+        self.index += 1;
+
+        let value = if self.de.spans.is_some() {
+            self.deserialize_recorded(index.to_string(), seed)
+        } else {
+            // TODO: Shouldn't I check that this is a valid value?
+            seed.deserialize(&mut *self.de)
+        };
+
+        value
+            .map(Some)
+            .map_err(|err| self.de.locate(err, start).in_index(index))
+    }
+}
+
+impl<'de, 'a> serde::de::MapAccess<'de> for Separated<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        loop {
+            if matches!(self.de.peek_token()?, Token::ObjectEnd | Token::Eof) {
+                return Ok(None);
+            }
+
+            if self.top_level && self.de.peek_terminator().is_some() {
+                return Ok(None);
+            }
+
+            if !self.first {
+                if self.de.parse(&parse_separator)? != Token::Separator {
+                    return Err(self.de.error(ErrorCode::ExpectedMapSeparator));
+                }
+
+                // A trailing separator after the last entry.
+                if matches!(self.de.peek_token()?, Token::ObjectEnd | Token::Eof) {
+                    return Ok(None);
+                }
+            }
+
+            self.first = false;
+
+            if !self.top_level || !self.de.skip_unprojected_entry()? {
+                break;
+            }
+        }
+
+        if self.top_level {
+            self.de.record_doc_comment();
+        }
+
+        if self.de.spans.is_some() {
+            if let Ok((_, Token::String(key))) = parse_string(self.de.input) {
+                self.key = Some(key);
+            }
+        }
+
+        // TODO: Shouldn't I check that this is a valid identifier?
+        let start = self.de.input;
+
+        if self.de.deny_duplicate_keys {
+            if let Ok((_, Token::String(key))) = parse_string(start) {
+                if self.keys.contains(&key) {
+                    let err = Error::new(ErrorCode::DuplicateKey(key), 0, 0, None);
+                    return Err(self.de.locate(err, start));
+                }
+                self.keys.insert(key);
+            }
+        }
+
+        self.key_input = Some(start);
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|err| self.de.locate(err, start))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        if self.de.bare_keys && self.de.peek_assignment()?.is_none() {
+            self.key = None;
+            return seed.deserialize(().into_deserializer());
+        }
+
+        if !self.de.parse_assignment()? {
+            return Err(self.de.error(ErrorCode::ExpectedMapEquals));
+        }
+
+        let start = self.de.input;
+
+        let value = if let Some(key) = self.key.take() {
+            self.deserialize_recorded(key, seed)
+        } else {
+            // TODO: Shouldn't I check that this is a valid value?
+            seed.deserialize(&mut *self.de)
+        };
+
+        value.map_err(|err| {
+            let err = self.de.locate(err, start);
+            self.in_key(err)
+        })
+    }
+}
+
+// Reads a string, since `String` only implements `Deserialize` with serde's `std` feature.
+struct StringSeed;
+
+impl<'de> serde::de::DeserializeSeed<'de> for StringSeed {
+    type Value = String;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_string(self)
+    }
+}
+
+impl<'de> serde::de::Visitor<'de> for StringSeed {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(v.to_string())
+    }
+}
+
+// Visits values that have already been located in the input, in the given order.
+struct Indexed<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+    entries: std::vec::IntoIter<Span<'de>>,
+    // The index of the next element, once they are ordered.
+    index: usize,
+}
+
+impl<'a, 'de: 'a> Indexed<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>, entries: Vec<Span<'de>>) -> Self {
+        Self {
+            de,
+            entries: entries.into_iter(),
+            index: 0,
+        }
+    }
+}
+
+impl<'de, 'a> serde::de::SeqAccess<'de> for Indexed<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        let Some(start) = self.entries.next() else {
+            return Ok(None);
+        };
+
+        let index = self.index;
+        self.index += 1;
+
+        self.de.advance(start);
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|err| self.de.locate(err, start).in_index(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct Enum<'a, 'de: 'a> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'a, 'de> Enum<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self { de }
+    }
+}
+
+impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let start = self.de.input;
+        let val = seed
+            .deserialize(&mut *self.de)
+            .map_err(|err| self.de.locate(err, start))?;
+
+        if self.de.parse_assignment()? {
+            Ok((val, self))
+        } else {
+            Err(self.de.error(ErrorCode::ExpectedMapEquals))
+        }
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Err(self.de.error(ErrorCode::ExpectedString))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        serde::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        serde::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::error::{Error, ErrorCode, ErrorKind};
+    use crate::{
+        from_reader, from_slice, from_str, from_str_multi, from_str_partial, from_str_projection,
+        from_str_until, from_str_with_doc_comments, from_str_with_len, from_str_with_spans,
+        to_string, to_string_compact, ArraySeparator, Assignment, BytesEncoding, CollectionKind,
+        Deserializer, IndentMode, NonFiniteMode, NonFiniteSpellings, Serializer, UnitFieldMode,
+    };
+
+    macro_rules! assert_value_ok {
+        ($type:ty, $json:expr) => {
+            assert_value_ok!($type, Default::default(), $json)
+        };
+        ($type:ty, $expected:expr, $json:expr) => {{
+            #[derive(Debug, serde::Deserialize, PartialEq)]
+            struct Value {
+                value: $type,
+            }
+
+            let expected = Value { value: $expected };
+
+            let json = format!("value = {}", $json);
+            let actual = from_str::<Value>(&json).unwrap();
+            assert_eq!(actual, expected);
+        }};
+    }
+
+    macro_rules! assert_ok {
+        ($type:ty, $expected:expr, $json:expr) => {{
+            let actual = from_str::<$type>($json).unwrap();
+            assert_eq!(actual, $expected);
+        }};
+    }
+
+    macro_rules! assert_value_err {
+        ($type:ty, $expected:expr, $json:expr) => {{
+            #[derive(Debug, serde::Deserialize, PartialEq)]
+            struct Value {
+                value: $type,
+            }
+
+            let json = format!("value = {}", $json);
+            let actual = from_str::<Value>(&json);
+            assert_eq!(actual, Err($expected.in_key("value")));
+        }};
+    }
+
+    #[test]
+    fn deserialize_null() {
+        assert_value_ok!((), "null");
+
+        let err = Error::new(ErrorCode::ExpectedNull, 1, 9, Some("foo".to_string()));
+        assert_value_err!((), err, "foo");
+    }
+
+    #[test]
+    fn deserialize_bool() {
+        assert_value_ok!(bool, true, "true");
+        assert_value_ok!(bool, false, "false");
+
+        let err = Error::new(ErrorCode::ExpectedBoolean, 1, 9, Some("foo".to_string()));
+        assert_value_err!(bool, err, "foo");
+    }
+
+    #[test]
+    fn deserialize_integer() {
+        assert_value_ok!(i64, 0, "0");
+        assert_value_ok!(i64, -1, "-1");
+        assert_value_ok!(i64, i64::MAX, i64::MAX.to_string());
+        assert_value_ok!(i64, i64::MIN, i64::MIN.to_string());
+
+        assert_value_ok!(i8, 0, "0");
+        assert_value_ok!(i8, 102, "102");
+        assert_value_ok!(i8, -102, "-102");
+        assert_value_ok!(u8, 102, "102");
+        assert_value_ok!(i16, 256, "256");
+        assert_value_ok!(u64, u64::MAX, u64::MAX.to_string());
+        assert_value_ok!(u64, i64::MAX as u64 + 1, (i64::MAX as u64 + 1).to_string());
+        assert_value_ok!(f64, u64::MAX as f64, u64::MAX.to_string());
+        assert_value_ok!(u8, 255, "0xFF");
+        assert_value_ok!(i32, -16, "-0x10");
+        assert_value_ok!(u64, u64::MAX, "0xFFFFFFFFFFFFFFFF");
+        assert_value_ok!(crate::Value, crate::Value::Integer(0x1F), "0x1f");
+        assert_value_ok!(i64, 0, "+0");
+        assert_value_ok!(u8, 42, "+42");
+        assert_value_ok!(crate::Value, crate::Value::Integer(42), "+42");
+        assert_value_ok!(f64, 1.5, "+1.5");
+        assert_value_ok!(crate::Value, crate::Value::Float(1.5), "+1.5");
+
+        let err = Error::new(ErrorCode::ExpectedInteger, 1, 9, Some("foo".to_string()));
+        assert_value_err!(i64, err, "foo");
+
+        // A malformed hexadecimal literal is only an error where an integer is expected.
+        for hex in ["0x", "0xg", "0x1g"] {
+            let err = Error::new(ErrorCode::ExpectedInteger, 1, 9, Some(hex.to_string()));
+            assert_value_err!(u64, err, hex);
+            assert_value_ok!(String, hex.to_string(), hex);
+            assert_value_ok!(crate::Value, crate::Value::String(hex.to_string()), hex);
+        }
+
+        let value = from_str::<crate::Value>("value = { 0x = 1, 0xg = 2 }\nlist = [0xg]").unwrap();
+        assert_eq!(value["value"]["0x"], crate::Value::Integer(1));
+        assert_eq!(value["value"]["0xg"], crate::Value::Integer(2));
+        assert_eq!(value["list"][0], crate::Value::String(String::from("0xg")));
+        assert_eq!(crate::validate("a = 0x\nb = [0xg]"), Ok(()));
+    }
+
+    #[test]
+    fn roundtrip_u64() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            value: u64,
+            values: Vec<u64>,
+        }
+
+        let expected = Value {
+            value: u64::MAX,
+            values: vec![0, i64::MAX as u64, i64::MAX as u64 + 1, u64::MAX],
+        };
+
+        let sjson = crate::to_string(&expected).unwrap();
+        assert_eq!(
+            sjson,
+            "value = 18446744073709551615\nvalues = [\n  0\n  9223372036854775807\n  9223372036854775808\n  18446744073709551615\n]\n"
+        );
+
+        let actual: Value = from_str(&sjson).unwrap();
+        assert_eq!(actual, expected);
+
+        // Not silently truncated or turned into a float when the field is signed.
+        #[derive(Debug, serde::Deserialize)]
+        struct Signed {
+            #[allow(dead_code)]
+            value: i64,
+        }
+
+        let err = from_str::<Signed>("value = 18446744073709551615").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Custom);
+    }
+
+    #[test]
+    fn roundtrip_empty_top_level() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Empty {}
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Unit;
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            unit: Unit,
+        }
+
+        let sjson = crate::to_string(&Empty {}).unwrap();
+        assert_eq!(sjson, "");
+        assert_eq!(from_str::<Empty>(&sjson).unwrap(), Empty {});
+
+        let sjson = crate::to_string(&Unit).unwrap();
+        assert_eq!(sjson, "");
+        assert_eq!(from_str::<Unit>(&sjson).unwrap(), Unit);
+
+        for sjson in ["  \n", "// Nothing here\n", "{ }", "unused = 1"] {
+            assert_eq!(from_str::<Empty>(sjson).unwrap(), Empty {});
+            assert_eq!(from_str::<Unit>(sjson).unwrap(), Unit);
+        }
+
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_top_level_braces(true);
+        Unit.serialize(&mut ser).unwrap();
+        assert_eq!(buf, b"{ }\n");
+
+        // Nested, it is still a unit value.
+        let sjson = crate::to_string(&Value { unit: Unit }).unwrap();
+        assert_eq!(sjson, "unit = null\n");
+        assert_eq!(from_str::<Value>(&sjson).unwrap(), Value { unit: Unit });
+    }
+
+    #[test]
+    fn roundtrip_integer_keys() {
+        use std::collections::{BTreeMap, HashMap};
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            names: HashMap<u32, String>,
+            offsets: BTreeMap<i64, u64>,
+            flags: BTreeMap<bool, u8>,
+        }
+
+        let expected = Value {
+            names: HashMap::from([(7, String::from("seven")), (42, String::from("answer"))]),
+            offsets: BTreeMap::from([(i64::MIN, 0), (-1, 1), (i64::MAX, 2)]),
+            flags: BTreeMap::from([(false, 0), (true, 1)]),
+        };
+
+        let sjson = crate::to_string(&expected).unwrap();
+        assert!(sjson.contains(
+            "offsets = {\n  -9223372036854775808 = 0\n  -1 = 1\n  9223372036854775807 = 2\n}\n"
+        ));
+        assert!(sjson.contains("flags = {\n  false = 0\n  true = 1\n}\n"));
+
+        let actual: Value = from_str(&sjson).unwrap();
+        assert_eq!(actual, expected);
+
+        let actual: BTreeMap<u64, u8> = from_str("0x10 = 1, 18446744073709551615 = 2").unwrap();
+        assert_eq!(actual, BTreeMap::from([(16, 1), (u64::MAX, 2)]));
+
+        let err = from_str::<BTreeMap<u8, u8>>("foo = 1").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ExpectedInteger);
+    }
+
+    #[test]
+    fn deserialize_integer_overflow() {
+        let too_large = "18446744073709551616";
+        let too_small = "-9223372036854775809";
+
+        for digits in [too_large, too_small] {
+            let err = Error::new(ErrorCode::IntegerOverflow, 1, 9, Some(digits.to_string()));
+            assert_value_err!(u64, err, digits);
+            let err = Error::new(ErrorCode::IntegerOverflow, 1, 9, Some(digits.to_string()));
+            assert_value_err!(i64, err, digits);
+            let err = Error::new(ErrorCode::IntegerOverflow, 1, 9, Some(digits.to_string()));
+            assert_value_err!(crate::Value, err, digits);
+
+            // Floats still accept them, as well as anything with a fraction or exponent.
+            assert_value_ok!(f64, digits.parse::<f64>().unwrap(), digits);
+            let float = format!("{digits}.0");
+            assert_value_ok!(
+                crate::Value,
+                crate::Value::Float(digits.parse().unwrap()),
+                float
+            );
+        }
+
+        let hex = "0x10000000000000000";
+        let err = Error::new(ErrorCode::IntegerOverflow, 1, 9, Some(hex.to_string()));
+        assert_value_err!(u64, err, hex);
+        let err = Error::new(ErrorCode::IntegerOverflow, 1, 9, Some(hex.to_string()));
+        assert_value_err!(crate::Value, err, hex);
+
+        // Hexadecimal floats start like a hexadecimal integer, but are not one.
+        let mut de = Deserializer::from_str("value = 0x1p3").with_hex_floats();
+        let value = crate::Value::deserialize(&mut de).unwrap();
+        assert_eq!(value["value"], crate::Value::Float(8.0));
+
+        let sjson = "values = [\n  1\n  // A comment\n  99999999999999999999\n]";
+        let err = from_str::<std::collections::BTreeMap<String, Vec<u64>>>(sjson).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::IntegerOverflow);
+        assert_eq!((err.line(), err.column()), (4, 3));
+        assert_eq!(err.fragment(), Some("99999999999999999999\n]"));
+        assert_eq!(
+            err.to_string(),
+            "integer is out of range for i64 and u64 in `values[1]` at line 4 column 3"
+        );
+    }
+
+    #[test]
+    fn deserialize_float() {
+        assert_value_ok!(f64, 0.0, "0");
+        assert_value_ok!(f64, 0.0, "0.0");
+        assert_value_ok!(f64, -1.0, "-1");
+        assert_value_ok!(f64, -1.0, "-1.0");
+        assert_value_ok!(f64, f64::MAX, f64::MAX.to_string());
+        assert_value_ok!(f64, f64::MIN, f64::MIN.to_string());
+        assert_value_ok!(f64, 0.5, ".5");
+        assert_value_ok!(f64, -0.5, "-.5");
+        assert_value_ok!(f64, 5.0, "5.");
+        assert_value_ok!(f32, 5.0, "5.");
+        assert_value_ok!(crate::Value, crate::Value::Float(0.5), ".5");
+        assert_value_ok!(crate::Value, crate::Value::Float(5.0), "5.");
+
+        // Without any digits, it's not a number.
+        assert_value_ok!(crate::Value, crate::Value::String(String::from(".")), ".");
+        let err = Error::new(ErrorCode::ExpectedFloat, 1, 9, Some(".".to_string()));
+        assert_value_err!(f64, err, ".");
+    }
+
+    #[test]
+    fn deserialize_non_finite_float() {
+        assert_value_ok!(f64, f64::INFINITY, "inf");
+        assert_value_ok!(f64, f64::INFINITY, "+inf");
+        assert_value_ok!(f64, f64::NEG_INFINITY, "-inf");
+        assert_value_ok!(f32, f32::INFINITY, "inf");
+        assert_value_ok!(f32, f32::NEG_INFINITY, "-inf");
+
+        // Quoted, a spelling stays a string.
+        for value in ["\"inf\"", "\"nan\""] {
+            let err = Error::new(ErrorCode::ExpectedFloat, 1, 9, Some(value.to_string()));
+            assert_value_err!(f64, err, value);
+            let string = crate::Value::String(value.trim_matches('"').to_string());
+            assert_value_ok!(crate::Value, string, value);
+        }
+
+        // Only the configured spellings are recognized, in any case.
+        for value in ["NaN", "INF", "Infinity", "infinity"] {
+            let err = Error::new(ErrorCode::ExpectedFloat, 1, 9, Some(value.to_string()));
+            assert_value_err!(f64, err, value);
+            assert_value_ok!(crate::Value, crate::Value::String(value.to_string()), value);
+        }
+
+        let spellings = NonFiniteSpellings::new("NaN", "Infinity", "-Infinity");
+        let mut de = Deserializer::from_str("value = Infinity\nother = inf")
+            .with_non_finite_spellings(spellings);
+        let value = crate::Value::deserialize(&mut de).unwrap();
+        assert_eq!(value["value"], crate::Value::Float(f64::INFINITY));
+        assert_eq!(value["other"], crate::Value::String(String::from("inf")));
+    }
+
+    #[test]
+    fn deserialize_char() {
+        assert_value_ok!(char, 'a', "a");
+        assert_value_ok!(char, 'a', "\"a\"");
+        assert_value_ok!(char, ' ', "\" \"");
+        assert_value_ok!(char, 'ü', "ü");
+        assert_value_ok!(char, '\n', "\"\\n\"");
+        assert_value_ok!(char, '😀', "\"\\ud83d\\ude00\"");
+
+        for value in ["\"\"", "ab", "\"ab\"", "[a]"] {
+            let err = Error::new(ErrorCode::ExpectedChar, 1, 9, Some(value.to_string()));
+            assert_value_err!(char, err, value);
+        }
+    }
+
+    #[test]
+    fn deserialize_f32_range() {
+        assert_value_ok!(f32, f32::MAX, f32::MAX.to_string());
+        assert_value_ok!(f32, f32::MIN, f32::MIN.to_string());
+        // Rounds to `f32::MAX`, rather than overflowing.
+        assert_value_ok!(f32, f32::MAX, "3.4028235e38");
+        // Too small values lose precision, like any other float.
+        assert_value_ok!(f32, 0.0, "1e-50");
+        assert_value_ok!(f64, 1e40, "1e40");
+
+        for digits in ["1e40", "-1e40", "3.5e38"] {
+            let err = Error::new(ErrorCode::FloatOverflow, 1, 9, Some(digits.to_string()));
+            assert_value_err!(f32, err, digits);
+        }
+
+        let sjson = "values = [\n  1.5\n  1e40\n]";
+        let err = from_str::<std::collections::BTreeMap<String, Vec<f32>>>(sjson).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FloatOverflow);
+        assert_eq!((err.line(), err.column()), (3, 3));
+    }
+
+    #[test]
+    fn roundtrip_non_finite_float_spellings() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct Value {
+            value: f64,
+        }
+
+        let spellings = NonFiniteSpellings::new("1.#QNAN", "1.#INF", "-1.#INF")
+            .accept_infinity("+1.#INF")
+            .accept_nan("-1.#IND");
+
+        let tests = [
+            (f64::NAN, "1.#QNAN"),
+            (f64::INFINITY, "1.#INF"),
+            (f64::NEG_INFINITY, "-1.#INF"),
+        ];
+        for (value, spelling) in tests {
+            let mut buf = Vec::new();
+            let mut ser = Serializer::new(&mut buf)
+                .with_non_finite_mode(NonFiniteMode::String(spellings.clone()));
+            Value { value }.serialize(&mut ser).unwrap();
+
+            let sjson = String::from_utf8(buf).unwrap();
+            assert_eq!(sjson, format!("value = {spelling}\n"));
+
+            let mut de =
+                Deserializer::from_str(&sjson).with_non_finite_spellings(spellings.clone());
+            let actual = Value::deserialize(&mut de).unwrap();
+            de.end().unwrap();
+
+            if value.is_nan() {
+                assert!(actual.value.is_nan());
+            } else {
+                assert_eq!(actual.value, value);
+            }
+        }
+
+        let tests = [("+1.#INF", f64::INFINITY), ("-1.#IND", f64::NAN)];
+        for (spelling, value) in tests {
+            let sjson = format!("value = {spelling}");
+            let mut de =
+                Deserializer::from_str(&sjson).with_non_finite_spellings(spellings.clone());
+            let actual = Value::deserialize(&mut de).unwrap();
+
+            if value.is_nan() {
+                assert!(actual.value.is_nan());
+            } else {
+                assert_eq!(actual.value, value);
+            }
+        }
+
+        // Strings that are spelled like a non-finite float are quoted, so they stay strings.
+        let value = crate::Value::Array(vec![
+            crate::Value::String(String::from("1.#INF")),
+            crate::Value::String(String::from("inf")),
+        ]);
+        let value = crate::Value::Object(crate::Map::from_iter([(String::from("value"), value)]));
+
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf)
+            .with_non_finite_mode(NonFiniteMode::String(spellings.clone()));
+        value.serialize(&mut ser).unwrap();
+        let sjson = String::from_utf8(buf).unwrap();
+        assert_eq!(sjson, "value = [\n  \"1.#INF\"\n  inf\n]\n");
+
+        let mut de = Deserializer::from_str(&sjson).with_non_finite_spellings(spellings);
+        assert_eq!(crate::Value::deserialize(&mut de).unwrap(), value);
+
+        let sjson = crate::to_string(&value).unwrap();
+        assert_eq!(sjson, "value = [\n  1.#INF\n  \"inf\"\n]\n");
+        assert_eq!(from_str::<crate::Value>(&sjson).unwrap(), value);
+    }
+
+    #[test]
+    fn roundtrip_hex_floats() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct Value {
+            value: f64,
+        }
+
+        let tests = [
+            (12.0, "0x1.8p+3"),
+            (-0.75, "-0x1.8p-1"),
+            (0.1, "0x1.999999999999ap-4"),
+            (0.0, "0x0p+0"),
+            (-0.0, "-0x0p+0"),
+            (f64::MAX, "0x1.fffffffffffffp+1023"),
+            (f64::MIN_POSITIVE, "0x1p-1022"),
+            (f64::from_bits(1), "0x0.0000000000001p-1022"),
+        ];
+        for (value, expected) in tests {
+            let mut buf = Vec::new();
+            let mut ser = Serializer::new(&mut buf).with_hex_floats();
+            Value { value }.serialize(&mut ser).unwrap();
+
+            let sjson = String::from_utf8(buf).unwrap();
+            assert_eq!(sjson, format!("value = {expected}\n"));
+
+            let mut de = Deserializer::from_str(&sjson).with_hex_floats();
+            let actual = Value::deserialize(&mut de).unwrap();
+            de.end().unwrap();
+            assert_eq!(actual.value.to_bits(), value.to_bits());
+        }
+
+        let mut de = Deserializer::from_str("value = 0x1.8p3").with_hex_floats();
+        assert_eq!(Value::deserialize(&mut de).unwrap().value, 12.0);
+
+        // Without opting in, hexadecimal floats are only strings.
+        assert!(from_str::<Value>("value = 0x1.8p3").is_err());
+        let value = from_str::<crate::Value>("value = 0x1.8p3").unwrap();
+        assert_eq!(
+            value["value"],
+            crate::Value::String(String::from("0x1.8p3"))
+        );
+
+        // With hexadecimal floats enabled, such a string needs quotes to stay a string.
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_hex_floats();
+        value.serialize(&mut ser).unwrap();
+        let sjson = String::from_utf8(buf).unwrap();
+        assert_eq!(sjson, "value = \"0x1.8p3\"\n");
+        let mut de = Deserializer::from_str(&sjson).with_hex_floats();
+        assert_eq!(crate::Value::deserialize(&mut de).unwrap(), value);
+    }
+
+    #[test]
+    fn deserialize_unquoted_tab() {
+        let err = Error::new(ErrorCode::UnquotedTab, 1, 12, Some("\tbar".to_string()));
+        assert_value_err!(String, err, "foo\tbar");
+
+        assert_value_ok!(String, String::from("foo\tbar"), "\"foo\tbar\"");
+        assert_value_ok!(String, String::from("foo"), "foo\t");
+        assert_value_ok!(String, String::from("foo"), "foo\t\t// comment");
+        assert_value_ok!(Vec<String>, vec![String::from("foo")], "[foo\t]");
+    }
+
+    #[test]
+    fn deserialize_escape_sequences() {
+        assert_value_ok!(String, String::from("foo\nbar"), r#""foo\nbar""#);
+        assert_value_ok!(String, String::from("foo\tbar\r"), r#""foo\tbar\r""#);
+        assert_value_ok!(
+            String,
+            String::from("\"foo\" \\ bar"),
+            r#""\"foo\" \\ bar""#
+        );
+        assert_value_ok!(String, String::from("\\n"), r#""\\n""#);
+
+        // Other backslashes are kept, and unquoted and literal strings are not unescaped.
+        assert_value_ok!(String, String::from("\\d+\\w"), r#""\d+\w""#);
+        assert_value_ok!(String, String::from("foo\\nbar"), r#"foo\nbar"#);
+        assert_value_ok!(String, String::from("foo\\nbar"), r#""""foo\nbar""""#);
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            value: String,
+            list: Vec<String>,
+        }
+
+        let value = Value {
+            value: String::from("line 1\n\t\"line\" 2\r\n\\path\\n"),
+            list: vec![String::from("\t"), String::from("a \"b\"")],
+        };
+        let sjson = to_string(&value).unwrap();
+        assert_eq!(from_str::<Value>(&sjson).unwrap(), value);
+
+        // Strings without escape sequences are still borrowed from the input.
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Borrowed<'a> {
+            value: &'a str,
+        }
+
+        assert_eq!(
+            from_str::<Borrowed>(r#"value = "foo bar""#).unwrap(),
+            Borrowed { value: "foo bar" }
+        );
+        assert!(from_str::<Borrowed>(r#"value = "foo\nbar""#).is_err());
+    }
+
+    #[test]
+    fn deserialize_literal_string() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            script: String,
+            after: u64,
+        }
+
+        let sjson = "script = \"\"\"\nprint(\"a\\tb\") // not a comment\n\n\"\"\"\nafter = 1";
+        let expected = Data {
+            script: String::from("\nprint(\"a\\tb\") // not a comment\n\n"),
+            after: 1,
+        };
+        assert_eq!(from_str::<Data>(sjson).unwrap(), expected);
+
+        // The string ends at the first `"""`.
+        let sjson = "script = \"\"\"a \"\"\" b\"\"\"\nafter = 1";
+        assert!(from_str::<Data>(sjson).is_err());
+    }
+
+    #[test]
+    fn deserialize_unicode_escapes() {
+        assert_value_ok!(String, String::from("A"), r#""\u0041""#);
+        assert_value_ok!(String, String::from("Grüße"), r#""Gr\u00fc\u00DFe""#);
+        assert_value_ok!(String, String::from("a😀b"), r#""a\uD83D\uDE00b""#);
+        assert_value_ok!(String, String::from("\u{ffff}"), r#""\uffff""#);
+        // Unquoted strings are taken as they are.
+        assert_value_ok!(String, String::from("\\u0041"), r#"\u0041"#);
+
+        let tests = [
+            r#""\u""#,
+            r#""\u004""#,
+            r#""\u00g1""#,
+            r#""\uD83D""#,
+            r#""\uD83Dabc""#,
+            r#""\uD83D\u0041""#,
+            r#""\uDE00""#,
+            r#""\uDE00\uD83D""#,
+        ];
+
+        for json in tests {
+            let err = Error::new(
+                ErrorCode::InvalidUnicodeEscape,
+                1,
+                8,
+                Some(format!(" {json}")),
+            );
+            assert_value_err!(String, err, json);
+        }
+    }
+
+    #[test]
+    fn deserialize_trailing_content() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Value {
+            value: u64,
+            name: String,
+        }
+
+        let expected = Value {
+            value: 1,
+            name: String::from("foo"),
+        };
+
+        let tests = [
+            "value = 1\nname = foo",
+            "value = 1\nname = foo\n",
+            "value = 1\nname = foo  ",
+            "value = 1\nname = foo\t\t",
+            "value = 1\nname = foo \t \n\t ",
+            "value = 1\nname = foo // comment",
+            "value = 1\nname = foo\n// comment\n",
+            "value = 1\nname = foo /* comment */",
+            "value = 1\nname = foo\n/* comment\n */  \n",
+            "value = 1\nname = foo\n\n\n",
+            "value = 1\r\nname = foo\r\n\r\n",
+        ];
+        for sjson in tests {
+            assert_eq!(
+                from_str::<Value>(sjson).as_ref(),
+                Ok(&expected),
+                "{sjson:?}"
+            );
+        }
+
+        // The same, with a number as the last value.
+        let tests = [
+            "name = foo\nvalue = 1",
+            "name = foo\nvalue = 1\t",
+            "name = foo\nvalue = 1 // comment",
+            "name = foo\nvalue = 1 /* comment */\n\n",
+        ];
+        for sjson in tests {
+            assert_eq!(
+                from_str::<Value>(sjson).as_ref(),
+                Ok(&expected),
+                "{sjson:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize_trailing_comments() {
+        assert_value_ok!(u64, 1, "1 // foo");
+        assert_value_ok!(u64, 1, "1\n// foo\n");
+        assert_value_ok!(u64, 1, "1\n/* foo */");
+
+        let err = Error::new(
+            ErrorCode::UnterminatedComment,
+            2,
+            1,
+            Some("/* foo".to_string()),
+        );
+        // The comment comes after the value, so the error isn't part of it.
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Value {
+            value: u64,
+        }
+        assert_eq!(from_str::<Value>("value = 1\n/* foo"), Err(err));
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Entries {
+            a: u64,
+            b: String,
+        }
+        let err = from_str::<std::collections::BTreeMap<String, u64>>("a = 1 /* oops").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnterminatedComment);
+        assert_eq!((err.line(), err.column()), (1, 7));
+        assert_eq!(err.fragment(), Some("/* oops"));
+        let err = from_str::<Entries>("a = 1\nb = \"äö\" /* oops */ /* oops").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnterminatedComment);
+        assert_eq!((err.line(), err.column()), (2, 21));
+
+        // Reported at the `/*` that opened the comment.
+        let err = Error::new(
+            ErrorCode::UnterminatedComment,
+            1,
+            9,
+            Some("/* foo\nbar = 2".to_string()),
+        );
+        assert_value_err!(u64, err, "/* foo\nbar = 2");
+    }
+
+    #[test]
+    fn deserialize_entries_with_comments() {
+        #[derive(Clone, Debug, serde::Deserialize, PartialEq)]
+        struct Window {
+            width: u64,
+            title: String,
+        }
+
+        #[derive(Clone, Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            name: String,
+            size: f64,
+            tags: Vec<String>,
+            window: Window,
+        }
+
+        let expected = Data {
+            name: String::from("main"),
+            size: 1.5,
+            tags: vec![String::from("a"), String::from("b")],
+            window: Window {
+                width: 2,
+                title: String::from("foo bar"),
+            },
+        };
+
+        let tests = [
+            // Trailing line comments.
+            "name = main // the name\nsize = 1.5 // in meters\ntags = [a // first\n  b // second\n] // tags\nwindow = {\n  width = 2 // pixels\n  title = \"foo bar\" // quoted\n} // done",
+            // Trailing block comments.
+            "name = main /* the name */\nsize = 1.5 /* a */ /* b */\ntags = [a /* first */, b ]\nwindow = { width = 2 /* pixels */, title = \"foo bar\" /* quoted */ }",
+            // Blank lines between entries.
+            "name = main\n\nsize = 1.5\n\n\ntags = [\n  a\n\n  b\n]\n\nwindow = {\n\n  width = 2\n\n  title = \"foo bar\"\n\n}\n\n",
+            // Leading block comments.
+            "/* the name */ name = main\n/* size */\nsize = 1.5\ntags = [\n  /* first */ a\n  /* second */ b\n]\nwindow = {\n  /* pixels */ width = 2\n  /* quoted */ title = \"foo bar\"\n}",
+            // All of the above, with CRLF and extra spaces.
+            "// header\r\n\r\nname = main   // the name\r\n\r\n/* size */ size = 1.5 /* a */ // b\r\ntags = [a  ,  b  ]\r\nwindow = {  width = 2  ,  title = \"foo bar\"  }\t// done\r\n",
+        ];
+
+        for json in tests {
+            let actual = from_str::<Data>(json);
+            assert_eq!(actual, Ok(expected.clone()), "{json:?}");
+        }
+    }
+
+    #[test]
+    fn display_error_with_source() {
+        #[derive(Debug, Deserialize)]
+        struct Data {
+            #[allow(dead_code)]
+            list: Vec<u64>,
+        }
+
+        let sjson = "// comment\nlist = [1,foo]\n";
+        let err = from_str::<Data>(sjson).unwrap_err();
+        let expected = "\
+2 | list = [1,foo]
+  |           ^
+expected an integer value in `list[1]`";
+        assert_eq!(err.display_with_source(sjson), expected);
+
+        let sjson = "\tlist = [1,foo]";
+        let err = from_str::<Data>(sjson).unwrap_err();
+        assert_eq!(
+            err.display_with_source(sjson),
+            "1 | \tlist = [1,foo]\n  | \t          ^\nexpected an integer value in `list[1]`"
+        );
+
+        let err = Error::new(ErrorCode::ExpectedValue, 0, 0, None);
+        assert_eq!(err.display_with_source(sjson), "expected a value");
+    }
+
+    #[test]
+    fn deserialize_vec() {
+        assert_value_ok!(Vec<u64>, vec![1, 2, 3], "[1, 2, 3]");
+        assert_value_ok!(
+            Vec<u64>,
+            vec![1, 2, 3],
+            "\
+[
+    1
+    2
+    3
+]"
+        );
+    }
+
+    #[test]
+    fn deserialize_crlf_separators() {
+        // `\r\n` separates elements like `\n`, wherever it appears between them.
+        for json in [
+            "[\r\n  1\r\n  2\r\n  3\r\n]",
+            "[1 \r\n 2\t\r\n3]",
+            "[1 // one\r\n 2 /* two */\r\n 3\r\n]",
+            "[1,\r\n2,\r\n3,\r\n]",
+            "[\r\n\r\n1\r\n\r\n2\r\n\r\n3\r\n\r\n]",
+        ] {
+            assert_value_ok!(Vec<u64>, vec![1, 2, 3], json);
+            assert_eq!(crate::validate(&format!("value = {}", json)), Ok(()));
+        }
+
+        assert_value_ok!(
+            Vec<String>,
+            vec![String::from("a"), String::from("b c"), String::from("d")],
+            "[\r\n  a\r\n  \"b c\"\r\n  \"\"\"d\"\"\"\r\n]"
+        );
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Size {
+            width: u64,
+            height: u64,
+        }
+        assert_value_ok!(
+            Size,
+            Size {
+                width: 1,
+                height: 2
+            },
+            "{\r\n  width = 1\r\n  height = 2\r\n}"
+        );
+
+        let mut de = Deserializer::from_str("value = [1\r\n2\r\n3]")
+            .with_array_separators(&[ArraySeparator::Newline]);
+        let actual = std::collections::BTreeMap::<String, Vec<u64>>::deserialize(&mut de);
+        assert_eq!(actual.unwrap()["value"], vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn deserialize_indexed_array() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            list: Vec<String>,
+            nested: Vec<Vec<u64>>,
+            after: u64,
+        }
+
+        let sjson = r#"
+list = { [1] = "a", [2] = "b", [3] = "c" }
+nested = {
+    [2] = { [1] = 3 }
+    [1] = [1, 2]
+}
+after = 1
+"#;
+        let expected = Data {
+            list: vec![String::from("a"), String::from("b"), String::from("c")],
+            nested: vec![vec![1, 2], vec![3]],
+            after: 1,
+        };
+
+        let mut de = Deserializer::from_str(sjson).with_indexed_arrays();
+        assert_eq!(Data::deserialize(&mut de), Ok(expected));
+        assert_eq!(de.end(), Ok(()));
+
+        assert!(from_str::<Data>(sjson).is_err());
+    }
+
+    #[test]
+    fn deserialize_indexed_array_order() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            list: Vec<u64>,
+        }
+
+        let tests = [
+            ("{ [3] = 30, [1] = 10, [2] = 20 }", vec![10, 20, 30]),
+            ("{ [1] = 10, [5] = 50, [3] = 30 }", vec![10, 30, 50]),
+            ("{ [2] = 20, [2] = 21 }", vec![20, 21]),
+            ("{}", vec![]),
+        ];
+
+        for (list, expected) in tests {
+            let sjson = format!("list = {list}");
+            let mut de = Deserializer::from_str(&sjson).with_indexed_arrays();
+            assert_eq!(Data::deserialize(&mut de), Ok(Data { list: expected }));
+        }
+
+        let sjson = "list = { [1] = 10, foo = 20 }";
+        let mut de = Deserializer::from_str(sjson).with_indexed_arrays();
+        let err = Error::new(
+            ErrorCode::ExpectedArrayIndex,
+            1,
+            20,
+            Some("foo = 20 }".to_string()),
+        );
+        assert_eq!(Data::deserialize(&mut de), Err(err.in_key("list")));
+    }
+
+    #[test]
+    fn deserialize_enum() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        enum Animal {
+            Mouse,
+            Dog { name: String },
+            Cat(u64),
+        }
+
+        assert_value_ok!(Animal, Animal::Mouse, "Mouse");
+        assert_value_ok!(Animal, Animal::Cat(9), "{ Cat = 9 }");
+        assert_value_ok!(
+            Animal,
+            Animal::Dog {
+                name: String::from("Buddy")
+            },
+            "{ Dog = { name = Buddy }}"
+        );
+    }
+
+    #[test]
+    fn deserialize_projection() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            name: String,
+            version: u64,
+            tags: Vec<u64>,
+        }
+
+        let mut sjson = String::new();
+        for i in 0..100 {
+            sjson.push_str(&format!(
+                "skipped_{i} = {{ list = [1, 2, {{ a = \"}}]\" }}] }}\n"
+            ));
+            if i == 50 {
+                sjson.push_str("name = foo\nversion = 3\ntags = [1, 2]\n");
+            }
+        }
+
+        let expected = Data {
+            name: String::from("foo"),
+            version: 3,
+            tags: vec![1, 2],
+        };
+
+        let actual: Data = from_str_projection(&sjson, &["name", "version", "tags"]).unwrap();
+        assert_eq!(actual, expected);
+
+        // Only top-level keys are projected.
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Outer {
+            inner: std::collections::BTreeMap<String, u64>,
+        }
+
+        let actual: Outer =
+            from_str_projection("skip = 1\ninner = { a = 1, b = 2 }", &["inner"]).unwrap();
+        assert_eq!(actual.inner.len(), 2);
+
+        // Skipped values still need to be well-formed.
+        assert!(from_str_projection::<Data>("skip = [1, 2", &["name"]).is_err());
+        assert!(from_str_projection::<Data>("skip = ]", &["name"]).is_err());
+    }
+
+    #[test]
+    fn roundtrip_bytes_encodings() {
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct Value {
+            #[serde(with = "serde_bytes")]
+            value: Vec<u8>,
+        }
+
+        let value = Value {
+            value: vec![0, 1, 127, 255],
+        };
+
+        let tests = [
+            (
+                BytesEncoding::Array,
+                "value = [\n  0\n  1\n  127\n  255\n]\n",
+            ),
+            (BytesEncoding::Base64, "value = \"AAF//w==\"\n"),
+        ];
+        for (encoding, expected) in tests {
+            let mut buf = Vec::new();
+            let mut ser = Serializer::new(&mut buf).with_bytes_encoding(encoding);
+            value.serialize(&mut ser).unwrap();
+
+            let sjson = String::from_utf8(buf).unwrap();
+            assert_eq!(sjson, expected);
+
+            let mut de = Deserializer::from_str(&sjson).with_bytes_encoding(encoding);
+            assert_eq!(Value::deserialize(&mut de).unwrap(), value);
+            de.end().unwrap();
+        }
+
+        // Plain byte sequences read from either representation.
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Plain {
+            value: Vec<u8>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct Array {
+            value: [u8; 4],
+        }
+
+        for encoding in [BytesEncoding::Array, BytesEncoding::Base64] {
+            let array = Array {
+                value: [0, 1, 127, 255],
+            };
+
+            let mut buf = Vec::new();
+            let mut ser = Serializer::new(&mut buf).with_bytes_encoding(encoding);
+            array.serialize(&mut ser).unwrap();
+
+            let sjson = String::from_utf8(buf).unwrap();
+            let mut de = Deserializer::from_str(&sjson).with_bytes_encoding(encoding);
+            assert_eq!(Array::deserialize(&mut de).unwrap(), array);
+        }
+
+        for sjson in ["value = [0, 1, 127, 255]", "value = \"AAF//w==\""] {
+            let mut de = Deserializer::from_str(sjson).with_bytes_encoding(BytesEncoding::Base64);
+            let actual = Plain::deserialize(&mut de).unwrap();
+            assert_eq!(actual.value, value.value);
+        }
+
+        let mut de =
+            Deserializer::from_str("value = \"AA=F\"").with_bytes_encoding(BytesEncoding::Base64);
+        let err = Error::new(
+            ErrorCode::InvalidBase64,
+            1,
+            8,
+            Some(" \"AA=F\"".to_string()),
+        );
+        assert_eq!(Plain::deserialize(&mut de), Err(err.in_key("value")));
+
+        // Without base64 enabled, only arrays are accepted.
+        assert!(from_str::<Value>("value = \"AAF//w==\"").is_err());
+    }
+
+    #[test]
+    fn roundtrip_bytes_default() {
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct Value {
+            #[serde(with = "serde_bytes")]
+            data: Vec<u8>,
+            #[serde(with = "serde_bytes")]
+            empty: Vec<u8>,
+            buf: serde_bytes::ByteBuf,
+        }
+
+        let value = Value {
+            data: vec![12, 255, 0],
+            empty: Vec::new(),
+            buf: serde_bytes::ByteBuf::from(b"{ a = 1 }".to_vec()),
+        };
+
+        let sjson = to_string(&value).unwrap();
+        assert!(sjson.starts_with("data = [\n  12\n  255\n  0\n]\nempty = []\n"));
+        assert_eq!(from_str::<Value>(&sjson), Ok(value));
+
+        let value = Value {
+            data: vec![1, 2],
+            empty: Vec::new(),
+            buf: serde_bytes::ByteBuf::from(vec![3]),
+        };
+
+        let sjson = to_string_compact(&value).unwrap();
+        assert_eq!(sjson, "data = [1, 2], empty = [], buf = [3]");
+        assert_eq!(from_str::<Value>(&sjson), Ok(value));
+    }
+
+    #[test]
+    fn roundtrip_top_level_tagged_enum() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Window {
+            width: u64,
+            title: String,
+        }
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        #[serde(tag = "t", content = "c")]
+        enum Adjacent {
+            Empty,
+            Count(u64),
+            Pair(u64, String),
+            Window(Window),
+            Struct { width: u64, tags: Vec<String> },
+        }
+
+        let tests = [
+            (Adjacent::Empty, "t = Empty\n"),
+            (Adjacent::Count(3), "t = Count\nc = 3\n"),
+            (
+                Adjacent::Pair(1, String::from("foo")),
+                "t = Pair\nc = [\n  1\n  foo\n]\n",
+            ),
+            (
+                Adjacent::Window(Window {
+                    width: 2,
+                    title: String::from("main"),
+                }),
+                "t = Window\nc = {\n  width = 2\n  title = main\n}\n",
+            ),
+            (
+                Adjacent::Struct {
+                    width: 3,
+                    tags: vec![String::from("a")],
+                },
+                "t = Struct\nc = {\n  width = 3\n  tags = [\n    a\n  ]\n}\n",
+            ),
+        ];
+
+        for (value, expected) in tests {
+            let sjson = to_string(&value).unwrap();
+            assert_eq!(sjson, expected);
+            assert_eq!(from_str::<Adjacent>(&sjson).unwrap(), value);
+        }
+
+        // The content may come first.
+        let sjson = "c = 3\nt = Count\n";
+        assert_eq!(from_str::<Adjacent>(sjson).unwrap(), Adjacent::Count(3));
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        #[serde(tag = "t")]
+        enum Internal {
+            Empty,
+            Window(Window),
+            Struct { width: u64, tags: Vec<String> },
+        }
+
+        let tests = [
+            (Internal::Empty, "t = Empty\n"),
+            (
+                Internal::Window(Window {
+                    width: 2,
+                    title: String::from("main"),
+                }),
+                "t = Window\nwidth = 2\ntitle = main\n",
+            ),
+            (
+                Internal::Struct {
+                    width: 3,
+                    tags: vec![String::from("a")],
+                },
+                "t = Struct\nwidth = 3\ntags = [\n  a\n]\n",
+            ),
+        ];
+
+        for (value, expected) in tests {
+            let sjson = to_string(&value).unwrap();
+            assert_eq!(sjson, expected);
+            assert_eq!(from_str::<Internal>(&sjson).unwrap(), value);
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn roundtrip_chrono() {
+        use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            utc: DateTime<Utc>,
+            offset: DateTime<FixedOffset>,
+            date: NaiveDate,
+            date_time: NaiveDateTime,
+            time: NaiveTime,
+            #[serde(with = "crate::unix_timestamp")]
+            timestamp: DateTime<Utc>,
+        }
+
+        let utc = Utc.with_ymd_and_hms(2024, 3, 21, 12, 30, 5).unwrap();
+        let tests = [
+            (
+                Value {
+                    utc,
+                    offset: FixedOffset::east_opt(2 * 3600)
+                        .unwrap()
+                        .with_ymd_and_hms(2024, 3, 21, 14, 30, 5)
+                        .unwrap(),
+                    date: NaiveDate::from_ymd_opt(2024, 3, 21).unwrap(),
+                    date_time: utc.naive_utc(),
+                    time: NaiveTime::from_hms_opt(12, 30, 5).unwrap(),
+                    timestamp: utc,
+                },
+                "\
+utc = \"2024-03-21T12:30:05Z\"
+offset = \"2024-03-21T14:30:05+02:00\"
+date = 2024-03-21
+date_time = \"2024-03-21T12:30:05\"
+time = \"12:30:05\"
+timestamp = 1711024205
+",
+            ),
+            (
+                Value {
+                    utc: utc + chrono::Duration::milliseconds(250),
+                    offset: FixedOffset::west_opt(5 * 3600 + 1800)
+                        .unwrap()
+                        .with_ymd_and_hms(1969, 12, 31, 18, 29, 59)
+                        .unwrap(),
+                    date: NaiveDate::from_ymd_opt(-44, 3, 15).unwrap(),
+                    date_time: utc.naive_utc() + chrono::Duration::nanoseconds(1),
+                    time: NaiveTime::from_hms_micro_opt(0, 0, 0, 1).unwrap(),
+                    timestamp: Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 59).unwrap(),
+                },
+                "\
+utc = \"2024-03-21T12:30:05.250Z\"
+offset = \"1969-12-31T18:29:59-05:30\"
+date = -0044-03-15
+date_time = \"2024-03-21T12:30:05.000000001\"
+time = \"00:00:00.000001\"
+timestamp = -1
+",
+            ),
+        ];
+
+        for (value, expected) in tests {
+            let sjson = to_string(&value).unwrap();
+            assert_eq!(sjson, expected);
+            assert_ok!(Value, value, &sjson);
+        }
+
+        // Other RFC 3339 spellings are accepted as well.
+        let time = "2024-03-21 12:30:05+00:00";
+        assert_value_ok!(DateTime<Utc>, utc, format!("\"{time}\""));
+        assert_value_ok!(DateTime<Utc>, utc, "\"2024-03-21T13:30:05+01:00\"");
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Timestamp {
+            #[serde(with = "crate::unix_timestamp")]
+            _value: DateTime<Utc>,
+        }
+
+        assert!(from_str::<Timestamp>(&format!("_value = {}", i64::MAX)).is_err());
+    }
+
+    #[test]
+    fn roundtrip_unit_field_modes() {
+        #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            #[serde(default)]
+            marker: (),
+            option: Option<u64>,
+            count: u64,
+        }
+
+        let value = Value {
+            marker: (),
+            option: None,
+            count: 3,
+        };
+
+        let tests = [
+            (
+                UnitFieldMode::Null,
+                "marker = null\noption = null\ncount = 3\n",
+            ),
+            (UnitFieldMode::Omit, "count = 3\n"),
+            (UnitFieldMode::BareKey, "marker\noption\ncount = 3\n"),
+        ];
+        for (mode, expected) in tests {
+            let mut buf = Vec::new();
+            let mut ser = Serializer::new(&mut buf).with_unit_field_mode(mode);
+            value.serialize(&mut ser).unwrap();
+
+            let sjson = String::from_utf8(buf).unwrap();
+            assert_eq!(sjson, expected);
+
+            let mut de = Deserializer::from_str(&sjson).with_bare_keys();
+            assert_eq!(Value::deserialize(&mut de).unwrap(), value);
+            de.end().unwrap();
+        }
+
+        // Without opting in, a bare key is still an error.
+        assert!(from_str::<Value>("marker\noption\ncount = 3\n").is_err());
+    }
+
+    #[test]
+    fn roundtrip_renamed_enum() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        #[serde(rename_all = "snake_case")]
+        enum Animal {
+            FieldMouse,
+            GuideDog { dog_name: String },
+            HouseCat(u64),
+            OldBird(u64, String),
+        }
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            value: Animal,
+            after: u64,
+        }
+
+        let tests = [
+            (Animal::FieldMouse, "value = field_mouse\n"),
+            (
+                Animal::GuideDog {
+                    dog_name: String::from("Buddy"),
+                },
+                "value = {\n  guide_dog = {\n    dog_name = Buddy\n  }\n}\n",
+            ),
+            (Animal::HouseCat(9), "value = { house_cat = 9 }\n"),
+            (
+                Animal::OldBird(3, String::from("Polly")),
+                "value = {\n  old_bird = [\n    3\n    Polly\n  ]\n}\n",
+            ),
+        ];
+
+        for (value, sjson) in tests {
+            let value = Value { value, after: 1 };
+            let expected = format!("{sjson}after = 1\n");
+
+            let actual = crate::to_string(&value).unwrap();
+            assert_eq!(actual, expected);
+            assert_eq!(from_str::<Value>(&actual).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn roundtrip_newtype_variant() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Circle {
+            radius: u64,
+            center: Vec<i64>,
+        }
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        enum Shape {
+            Circle(Circle),
+            Polygon(Vec<Vec<i64>>),
+        }
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Scene {
+            main: Shape,
+            others: Vec<Shape>,
+            after: u64,
+        }
+
+        let value = Scene {
+            main: Shape::Circle(Circle {
+                radius: 3,
+                center: vec![1, -1],
+            }),
+            others: vec![
+                Shape::Polygon(vec![vec![0, 0], vec![]]),
+                Shape::Circle(Circle {
+                    radius: 1,
+                    center: vec![],
+                }),
+            ],
+            after: 1,
+        };
+
+        let expected = "\
+main = { Circle = {
+  radius = 3
+  center = [
+    1
+    -1
+  ]
+} }
+others = [
+  { Polygon = [
+    [
+      0
+      0
+    ]
+    []
+  ] }
+  { Circle = {
+    radius = 1
+    center = []
+  } }
+]
+after = 1
+";
+        let actual = crate::to_string(&value).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(from_str::<Scene>(&actual).unwrap(), value);
+
+        // The closing delimiters line up with the opening ones, too.
+        let expected = "\
+main = { Circle = {
+                    radius = 3
+                    center = [
+                               1
+                               -1
+                             ]
+                  } }
+others = [
+           { Polygon = [
+                         [
+                           0
+                           0
+                         ]
+                         []
+                       ] }
+           { Circle = {
+                        radius = 1
+                        center = []
+                      } }
+         ]
+after = 1
+";
+        let mut actual = Vec::new();
+        let mut ser = Serializer::new(&mut actual).with_indent_mode(IndentMode::KeyRelative);
+        value.serialize(&mut ser).unwrap();
+        let actual = String::from_utf8(actual).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(from_str::<Scene>(&actual).unwrap(), value);
+    }
+
+    #[test]
+    fn roundtrip_enum_map() {
+        use std::collections::HashMap;
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        enum Shape {
+            Circle(f64),
+            Square(f64),
+            Rect { width: f64, height: f64 },
+            Point,
+        }
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Scene {
+            shapes: HashMap<String, Shape>,
+            after: u64,
+        }
+
+        let expected = Scene {
+            shapes: HashMap::from([
+                (String::from("foo"), Shape::Circle(1.0)),
+                (String::from("bar"), Shape::Square(2.5)),
+                (
+                    String::from("baz"),
+                    Shape::Rect {
+                        width: 1.0,
+                        height: 2.0,
+                    },
+                ),
+                (String::from("qux"), Shape::Point),
+            ]),
+            after: 1,
+        };
+
+        let tests = [
+            "shapes = {\n  foo = { Circle = 1.0 }\n  bar = { Square = 2.5 }\n  baz = { Rect = { width = 1.0, height = 2.0 } }\n  qux = Point\n}\nafter = 1",
+            "shapes = { foo = { Circle = 1.0 }, bar = { Square = 2.5 }, baz = { Rect = { width = 1.0, height = 2.0 } }, qux = Point }\nafter = 1",
+            "shapes = {\n  foo = {\n    Circle = 1.0\n  }\n  bar = { Square = 2.5 } // a comment\n  baz = {\n    Rect = {\n      width = 1.0\n      height = 2.0\n    }\n  }\n  qux = Point\n}\nafter = 1\n",
+        ];
+
+        for json in tests {
+            assert_eq!(from_str::<Scene>(json).unwrap(), expected, "{json}");
+        }
+
+        let actual = crate::to_string(&expected).unwrap();
+        assert_eq!(from_str::<Scene>(&actual).unwrap(), expected);
+
+        // The map can be the document itself, too.
+        let actual = crate::to_string(&expected.shapes).unwrap();
+        assert_eq!(
+            from_str::<HashMap<String, Shape>>(&actual).unwrap(),
+            expected.shapes
+        );
+
+        let err = from_str::<Scene>(
+            "shapes = {\n  foo = { Circle = 1.0 }\n  bar = { Oval = 2.0 }\n}\nafter = 1",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown variant `Oval`, expected one of `Circle`, `Square`, `Rect`, `Point` in `shapes.bar` at line 3 column 11"
+        );
+    }
+
+    #[test]
+    fn roundtrip_result() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Value {
+            value: Result<u64, String>,
+            after: u64,
+        }
+
+        let tests = [
+            (Ok(3), "value = { Ok = 3 }\n"),
+            (
+                Err(String::from("failed to load")),
+                "value = { Err = \"failed to load\" }\n",
+            ),
+        ];
+
+        for (value, sjson) in tests {
+            let value = Value { value, after: 1 };
+            let expected = format!("{sjson}after = 1\n");
+
+            let actual = crate::to_string(&value).unwrap();
+            assert_eq!(actual, expected);
+            assert_eq!(from_str::<Value>(&actual).unwrap(), value);
+        }
+
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Cache {
+            outcomes: Vec<Result<(), String>>,
+        }
+
+        let value = Cache {
+            outcomes: vec![Ok(()), Err(String::from("timeout"))],
+        };
+        let expected = "outcomes = [\n  { Ok = null }\n  { Err = timeout }\n]\n";
+        let actual = crate::to_string(&value).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(from_str::<Cache>(&actual).unwrap(), value);
+
+        let err = from_str::<Value>("value = { Maybe = 3 }\nafter = 1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown variant `Maybe`, expected `Ok` or `Err` in `value` at line 1 column 11"
+        );
+    }
+
+    // Checks the example from
+    // https://help.autodesk.com/view/Stingray/ENU/?guid=__stingray_help_managing_content_sjson_html
+    #[test]
+    fn deserialize_stingray_example() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Win32Settings {
+            query_performance_counter_affinity_mask: u64,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Settings {
+            boot_script: String,
+            console_port: u16,
+            win32: Win32Settings,
+            render_config: PathBuf,
+        }
+
+        let expected = Settings {
+            boot_script: String::from("boot"),
+            console_port: 14030,
+            win32: Win32Settings {
+                query_performance_counter_affinity_mask: 0,
+            },
+            render_config: PathBuf::from("core/rendering/renderer"),
+        };
+
+        let json = r#"
+// The script that should be started when the application runs.
+boot_script = "boot"
+
+// The port on which the console server runs.
+console_port = 14030
+
+// Settings for the win32 platform
+win32 = {
+    /* Sets the affinity mask for
+       QueryPerformanceCounter() */
+    query_performance_counter_affinity_mask = 0
+}
+
+render_config = "core/rendering/renderer"
+"#;
+
+        assert_ok!(Settings, expected, json);
+    }
+
+    #[test]
+    fn deserialize_stingray_example_spans() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Win32Settings {
+            query_performance_counter_affinity_mask: u64,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Settings {
+            boot_script: String,
+            console_port: u16,
+            win32: Win32Settings,
+            render_config: PathBuf,
+        }
+
+        let json = r#"
+// The script that should be started when the application runs.
+boot_script = "boot"
+
+// The port on which the console server runs.
+console_port = 14030
+
+// Settings for the win32 platform
+win32 = {
+    /* Sets the affinity mask for
+       QueryPerformanceCounter() */
+    query_performance_counter_affinity_mask = 0
+}
+
+render_config = "core/rendering/renderer"
+"#;
+
+        let (_, spans) = from_str_with_spans::<Settings>(json).unwrap();
+
+        let expected = [
+            ("boot_script", "\"boot\"", 3, 15),
+            ("console_port", "14030", 6, 16),
+            (
+                "win32",
+                &json[json.find('{').unwrap()..json.find('}').unwrap() + 1],
+                9,
+                9,
+            ),
+            ("win32.query_performance_counter_affinity_mask", "0", 12, 47),
+            ("render_config", "\"core/rendering/renderer\"", 15, 17),
+        ];
+
+        assert_eq!(spans.len(), expected.len());
+        for (path, text, line, column) in expected {
+            let span = &spans[path];
+            assert_eq!(&json[span.range.clone()], text, "{path}");
+            assert_eq!(span.line, line, "{path}");
+            assert_eq!(span.column, column, "{path}");
+        }
+    }
+
+    #[test]
+    fn deserialize_stingray_example_doc_comments() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Win32Settings {
+            query_performance_counter_affinity_mask: u64,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Settings {
+            boot_script: String,
+            console_port: u16,
+            win32: Win32Settings,
+            render_config: PathBuf,
+        }
+
+        let json = r#"
+// The script that should be started when the application runs.
+boot_script = "boot"
+
+// The port on which the console server runs.
+console_port = 14030
+
+// Settings for the win32 platform
+win32 = {
+    /* Sets the affinity mask for
+       QueryPerformanceCounter() */
+    query_performance_counter_affinity_mask = 0
+}
+
+render_config = "core/rendering/renderer"
+"#;
+
+        let (_, comments) = from_str_with_doc_comments::<Settings>(json).unwrap();
+
+        assert_eq!(
+            comments["boot_script"],
+            "The script that should be started when the application runs."
+        );
+        assert_eq!(
+            comments["console_port"],
+            "The port on which the console server runs."
+        );
+        assert_eq!(comments["win32"], "Settings for the win32 platform");
+        assert_eq!(comments.len(), 3);
+    }
+
+    #[test]
+    fn deserialize_doc_comment_blocks() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: u64,
+            bar: u64,
+        }
+
+        let json = "// Header\n\n// First line\n//Second line\nfoo = 1\n/* bar */\n\nbar = 2\n";
+        let (_, comments) = from_str_with_doc_comments::<Data>(json).unwrap();
+        assert_eq!(comments["foo"], "First line\nSecond line");
+        assert_eq!(comments.len(), 1);
+    }
+
+    #[test]
+    fn deserialize_comments() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: u64,
+            bar: Vec<u64>,
+            baz: String,
+        }
+
+        let json = "// Header\nfoo = 1 // one\nbar = [2, /* two */ 3]\nbaz = \"// not a comment\"\n// Footer\n";
+        let mut de = Deserializer::from_str(json).with_comments();
+        let data = Data::deserialize(&mut de).unwrap();
+        assert_eq!(data.baz, "// not a comment");
+        assert_eq!(
+            de.comments(),
+            [
+                (0, String::from("// Header")),
+                (18, String::from("// one")),
+                (35, String::from("/* two */")),
+            ]
+        );
+
+        de.end().unwrap();
+        assert_eq!(de.comments().len(), 4);
+        assert_eq!(de.comments()[3], (73, String::from("// Footer")));
+
+        let mut de = Deserializer::from_str(json);
+        Data::deserialize(&mut de).unwrap();
+        de.end().unwrap();
+        assert!(de.comments().is_empty());
+    }
+
+    #[test]
+    fn deserialize_until_terminator() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: u64,
+            bar: Vec<u64>,
+        }
+
+        let expected = Data {
+            foo: 1,
+            bar: vec![2, 3],
+        };
+
+        let document = "---\nfoo = 1\nbar = [2, 3]\n---\n# Heading\n\nfoo = 4\n";
+        let start = "---\n".len();
+        let (data, offset) = from_str_until::<Data>(&document[start..], "---").unwrap();
+        assert_eq!(data, expected);
+        assert_eq!(&document[start + offset..], "---\n# Heading\n\nfoo = 4\n");
+
+        // Comments and blank lines before the fence are part of the block.
+        let json = "foo = 1\r\nbar = [2, 3] // trailing\r\n\r\n---";
+        let (data, offset) = from_str_until::<Data>(json, "---").unwrap();
+        assert_eq!(data, expected);
+        assert_eq!(offset, json.len() - 3);
+
+        // The terminator is only matched in place of a top-level key.
+        let json = "foo = 1\nbar = [2, 3]\n";
+        let (data, offset) = from_str_until::<Data>(json, "3]").unwrap();
+        assert_eq!(data, expected);
+        assert_eq!(offset, json.len());
+
+        let json = "foo = 1\nbar = [2, 3]\nnot a fence";
+        assert!(from_str_until::<Data>(json, "---").is_err());
+    }
+
+    #[test]
+    fn deserialize_multi() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Entry {
+            level: String,
+            count: u32,
+        }
+
+        let entry = |level: &str, count| Entry {
+            level: level.to_string(),
+            count,
+        };
+
+        let input = "---\nlevel = info\ncount = 1\n---\n{ level = warn, count = 2 }\r\n---\r\n// No entry\n  ---  \nlevel = error\ncount = 3\n---\n";
+        assert_eq!(
+            from_str_multi::<Entry>(input, "---").unwrap(),
+            [entry("info", 1), entry("warn", 2), entry("error", 3)]
+        );
+
+        assert!(from_str_multi::<Entry>("", "---").unwrap().is_empty());
+
+        // Only whole lines separate documents.
+        let input = "level = \"---\"\ncount = 1 // ---\n";
+        assert_eq!(
+            from_str_multi::<Entry>(input, "---").unwrap(),
+            [entry("---", 1)]
+        );
+
+        // Errors are located in the whole input.
+        let input = "level = info\ncount = 1\n---\nlevel = warn\ncount = many\n";
+        let err = from_str_multi::<Entry>(input, "---").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ExpectedInteger);
+        assert_eq!((err.line(), err.column()), (5, 9));
+        assert_eq!(err.path(), Some("count"));
+
+        let input = "level = info\ncount = 1\n---\nlevel = warn\ncount = 2\nextra\n";
+        let err = from_str_multi::<Entry>(input, "---").unwrap_err();
+        assert_eq!((err.line(), err.column()), (6, 6));
+    }
+
+    #[test]
+    fn deserialize_stream() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Entry {
+            level: String,
+            count: u32,
+        }
+
+        let input = r#"
+{ level = info, count = 1 }
+{level = warn
+ count = 2}
+
+// A comment between entries.
+{ level = "error", count = 3 } /* and after */
+"#;
+        let entries = Deserializer::from_str(input)
+            .into_iter::<Entry>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    level: String::from("info"),
+                    count: 1
+                },
+                Entry {
+                    level: String::from("warn"),
+                    count: 2
+                },
+                Entry {
+                    level: String::from("error"),
+                    count: 3
+                },
+            ]
+        );
+
+        let mut empty = Deserializer::from_str("  // Nothing here\n").into_iter::<Entry>();
+        assert!(empty.next().is_none());
+
+        // Errors are reported in the offending value, and end the stream.
+        let input = "{ level = info, count = 1 }\n{ level = warn }\n{ level = error, count = 3 }";
+        let mut stream = Deserializer::from_str(input).into_iter::<Entry>();
+        assert!(stream.next().unwrap().is_ok());
+        let err = stream.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Custom);
+        assert_eq!((err.line(), err.column()), (2, 1));
+        assert!(stream.next().is_none());
+
+        let input = "{ level = info, count = 1 }\n{ level = warn, count = many }";
+        let err = Deserializer::from_str(input)
+            .into_iter::<Entry>()
+            .nth(1)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ExpectedInteger);
+        assert_eq!(err.line(), 2);
+
+        let err = Deserializer::from_str("{ level = info, count = 1 } /* open")
+            .into_iter::<Entry>()
+            .nth(1)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnterminatedComment);
+    }
+
+    #[test]
+    fn deserialize_from_reader() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: String,
+            bar: Vec<u64>,
+        }
+
+        let expected = Data {
+            foo: String::from("bär"),
+            bar: vec![1, 2],
+        };
+
+        let json = "foo = bär\nbar = [1, 2]\n";
+        assert_eq!(from_reader::<_, Data>(json.as_bytes()).unwrap(), expected);
+
+        let err = from_reader::<_, Data>(&b"foo = b\xe4r\nbar = [1, 2]\n"[..]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "input is not valid UTF-8: invalid utf-8 sequence of 1 bytes from index 7 at line 1 column 8"
+        );
+
+        // Errors are the same as for the string itself.
+        let json = "foo = bär\nbar = [1, 2]\n}";
+        let err = from_reader::<_, Data>(json.as_bytes()).unwrap_err();
+        assert_eq!(err, from_str::<Data>(json).unwrap_err());
+    }
+
+    #[test]
+    fn deserialize_from_slice() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data<'a> {
+            foo: &'a str,
+            bar: Vec<u64>,
+        }
+
+        let json = "foo = bär\nbar = [1, 2]\n".as_bytes().to_vec();
+        let data = from_slice::<Data>(&json).unwrap();
+        assert_eq!(
+            data,
+            Data {
+                foo: "bär",
+                bar: vec![1, 2]
+            }
+        );
+
+        let json = b"foo = \"b r\"\nbar = []";
+        assert_eq!(from_slice::<Data>(json).unwrap().foo, "b r");
+
+        let tests: [(&[u8], _); 4] = [
+            (b"\xff", "line 1 column 1"),
+            (b"foo = b\xe4r", "line 1 column 8"),
+            // The column counts characters, not bytes.
+            (b"foo = b\xc3\xa4r\nbar = [\xff]", "line 2 column 8"),
+            (b"foo = bar\n\n\xc0", "line 3 column 1"),
+        ];
+
+        for (json, location) in tests {
+            let err = from_slice::<Data>(json).unwrap_err();
+            assert!(err.to_string().ends_with(location), "{err}");
+        }
+    }
+
+    #[test]
+    fn deserialize_with_len() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: u64,
+            bar: Vec<u64>,
+        }
+
+        let expected = Data {
+            foo: 1,
+            bar: vec![2, 3],
+        };
+
+        let json = "foo = 1\nbar = [2, 3]";
+        assert_eq!(
+            from_str_with_len::<Data>(json).unwrap(),
+            (expected, json.len())
+        );
+
+        let tests = [
+            ("foo = 1\nbar = [2, 3]\n", "\n"),
+            ("foo = 1\nbar = [2, 3] // trailing\n", " // trailing\n"),
+            ("foo = 1\nbar = [2, 3]\n}\nrest = 4", "\n}\nrest = 4"),
+            ("foo = 1, bar = [2, 3]}{ foo = 4 }", "}{ foo = 4 }"),
+        ];
+
+        for (json, rest) in tests {
+            let (data, len) = from_str_with_len::<Data>(json).unwrap();
+            assert_eq!(data.bar, vec![2, 3]);
+            assert_eq!(&json[len..], rest);
+        }
+
+        let json = "foo = 1\nbar = [2, 3]\nnot valid";
+        assert!(from_str_with_len::<Data>(json).is_err());
+    }
+
+    #[test]
+    fn deserialize_top_level_braces() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: u64,
+            bar: Vec<u64>,
+        }
+
+        let expected = Data {
+            foo: 1,
+            bar: vec![2, 3],
+        };
+
+        let tests = [
+            "foo = 1\nbar = [2, 3]\n",
+            "{\n  foo = 1\n  bar = [2, 3]\n}\n",
+            "// Braced\n{ foo = 1, bar = [2, 3] } // done",
+        ];
+
+        for json in tests {
+            assert_eq!(from_str::<Data>(json).unwrap(), expected);
+        }
+
+        let err = from_str::<Data>("{ foo = 1, bar = [] } baz").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TrailingCharacters);
+
+        let err = from_str::<Data>("{ foo = 1, bar = []").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ExpectedMapEnd);
+    }
+
+    #[test]
+    fn deserialize_partial() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Header {
+            name: String,
+            size: u64,
+        }
+
+        let json = "name = data, size = 3\n}\u{0}\u{1}\u{2}";
+        let (header, rest) = from_str_partial::<Header>(json).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                name: String::from("data"),
+                size: 3,
+            }
+        );
+        assert_eq!(rest, "\n}\u{0}\u{1}\u{2}");
+
+        let (_, rest) = from_str_partial::<Header>("name = data, size = 3").unwrap();
+        assert_eq!(rest, "");
+
+        let mut de = Deserializer::from_str("[1, 2] rest");
+        let value = Vec::<u64>::deserialize(&mut de).unwrap();
+        assert_eq!(value, vec![1, 2]);
+        assert_eq!(de.remaining(), " rest");
+        assert!(de.end().is_err());
+    }
+
+    #[test]
+    fn deserialize_unicode_whitespace() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: String,
+            bar: Vec<u64>,
+        }
+
+        let expected = Data {
+            foo: String::from("baz"),
+            bar: vec![1, 2],
+        };
+
+        let json = "foo\u{a0}=\u{a0}baz\u{2009}\nbar = [\u{3000}1,\u{a0}2\u{85}]\u{2003}\n";
+        let mut de = Deserializer::from_str(json).with_unicode_whitespace();
+        let data = Data::deserialize(&mut de).unwrap();
+        de.end().unwrap();
+        assert_eq!(data, expected);
+
+        // U+FEFF is not whitespace, so it is still part of a value.
+        let json = "foo = baz\nbar = [1, 2\u{feff}]\n";
+        let mut de = Deserializer::from_str(json).with_unicode_whitespace();
+        assert!(Data::deserialize(&mut de).is_err());
+
+        // Without the option, the non-breaking space becomes part of the string.
+        let json = "foo = baz\u{a0}\nbar = [1, 2]";
+        let data: Data = from_str(json).unwrap();
+        assert_eq!(data.foo, "baz\u{a0}");
+        assert!(from_str::<Data>("foo\u{a0}= baz\nbar = [1, 2]").is_err());
+    }
+
+    #[test]
+    fn deserialize_assignments() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Inner {
+            baz: String,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            foo: u64,
+            bar: Vec<Inner>,
+        }
+
+        let expected = Data {
+            foo: 1,
+            bar: vec![Inner {
+                baz: String::from("qux"),
+            }],
+        };
+
+        let tests = [
+            (Assignment::Equals, "foo = 1\nbar = [{ baz = qux }]"),
+            (Assignment::Colon, "foo: 1\nbar:[{ baz :qux }]"),
+            (Assignment::FatArrow, "foo => 1\nbar=>[{ baz=>qux }]"),
+            (Assignment::Arrow, "foo -> 1\nbar -> [{ baz ->qux }]"),
+        ];
+
+        for (assignment, json) in tests {
+            let mut de = Deserializer::from_str(json).with_assignments(&[assignment]);
+            assert_eq!(Data::deserialize(&mut de).unwrap(), expected, "{json}");
+            de.end().unwrap();
+        }
+
+        let assignments = [
+            Assignment::Equals,
+            Assignment::Colon,
+            Assignment::FatArrow,
+            Assignment::Arrow,
+        ];
+        let json = "foo => 1\nbar: [{ baz -> qux }]";
+        let mut de = Deserializer::from_str(json).with_assignments(&assignments);
+        assert_eq!(Data::deserialize(&mut de).unwrap(), expected);
+
+        // Only `=` is accepted by default, and it is no longer accepted once replaced.
+        assert!(from_str::<Data>("foo: 1\nbar = []").is_err());
+        let mut de =
+            Deserializer::from_str("foo = 1\nbar: []").with_assignments(&[Assignment::Colon]);
+        let err = Error::new(
+            ErrorCode::ExpectedMapEquals,
+            1,
+            4,
+            Some(String::from(" = 1\nbar: []")),
+        );
+        assert_eq!(Data::deserialize(&mut de), Err(err));
+    }
+
+    #[test]
+    fn deserialize_array_separators() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Names {
+            names: Vec<String>,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            numbers: Vec<u64>,
+            names: Vec<String>,
+            nested: Vec<Vec<f64>>,
+        }
+
+        let expected = Data {
+            numbers: vec![1, 2, 3],
+            names: vec![String::from("foo"), String::from("bar baz")],
+            nested: vec![vec![1.5], vec![2.5, 3.5]],
+        };
+
+        let semicolon = [ArraySeparator::Semicolon];
+        let all = [
+            ArraySeparator::Comma,
+            ArraySeparator::Newline,
+            ArraySeparator::Semicolon,
+        ];
+
+        let tests = [
+            (
+                &semicolon[..],
+                "numbers = [1; 2; 3]\nnames = [foo ; \"bar baz\"]\nnested = [[1.5]; [2.5; 3.5]]",
+            ),
+            (
+                &semicolon[..],
+                "numbers = [1 ;2 /* two */ ;3;]\nnames = [ foo ;\"bar baz\" ]\nnested = [[1.5];[2.5;3.5]]",
+            ),
+            (
+                &all[..],
+                "numbers = [1; 2, 3]\nnames = [\n  foo ;\n  \"bar baz\"\n]\nnested = [\n  [1.5]\r\n  [2.5, 3.5];\n]",
+            ),
+        ];
+
+        for (separators, json) in tests {
+            let mut de = Deserializer::from_str(json).with_array_separators(separators);
+            assert_eq!(Data::deserialize(&mut de).unwrap(), expected, "{json}");
+            de.end().unwrap();
+        }
+
+        // Semicolons are part of unquoted strings, so they need to be separated by whitespace.
+        let mut de = Deserializer::from_str("names = [foo;bar ]").with_array_separators(&all);
+        assert_eq!(
+            Names::deserialize(&mut de).unwrap().names,
+            [String::from("foo;bar")]
+        );
+
+        // Only commas and line breaks are accepted by default, and no longer once replaced.
+        assert!(from_str::<Data>("numbers = [1; 2]\nnames = []\nnested = []").is_err());
+        let mut de = Deserializer::from_str("numbers = [1, 2]\nnames = []\nnested = []")
+            .with_array_separators(&semicolon);
+        let err = Error::new(
+            ErrorCode::ExpectedArraySeparator,
+            1,
+            13,
+            Some(String::from(", 2]\nnames = []\nnested = []")),
+        )
+        .in_key("numbers");
+        assert_eq!(Data::deserialize(&mut de), Err(err));
+
+        // Objects are not affected.
+        let mut de = Deserializer::from_str("numbers = []; names = []\nnested = []")
+            .with_array_separators(&semicolon);
+        assert!(Data::deserialize(&mut de).is_err());
+    }
+
+    #[test]
+    fn deserialize_max_string_length() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            v: String,
+        }
+
+        let ok = [
+            "v = abcd",
+            "v = \"ab d\"",
+            "v = \"a\\n\"",
+            "v = \"\"\"ab\nc\"\"\"",
+        ];
+        for json in ok {
+            let mut de = Deserializer::from_str(json).with_max_string_length(4);
+            Data::deserialize(&mut de).unwrap_or_else(|err| panic!("{json}: {err}"));
+        }
+
+        let long = "a".repeat(100_000);
+        let too_long = [
+            String::from("v = abcde"),
+            String::from("v = \"ab de\""),
+            String::from("v = \"a\\n\\n\""),
+            String::from("v = \"\"\"ab\ncde\"\"\""),
+            // The limit is hit before the missing end of the string.
+            format!("v = \"{long}"),
+            format!("v = {long}"),
+        ];
+        for json in too_long {
+            let mut de = Deserializer::from_str(&json).with_max_string_length(4);
+            let err = Data::deserialize(&mut de).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "string is longer than 4 bytes in `v` at line 1 column 4",
+                "{json:.20}"
+            );
+        }
+
+        // Keys are limited, too.
+        let mut de = Deserializer::from_str("abcde = 1").with_max_string_length(4);
+        let err = BTreeMap::<String, u64>::deserialize(&mut de).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "string is longer than 4 bytes at line 1 column 1"
+        );
+
+        // So are strings that are only skipped.
+        let mut de = Deserializer::from_str("v = a\nother = [abcde]")
+            .with_max_string_length(4)
+            .with_projection(&["v"]);
+        assert!(Data::deserialize(&mut de).is_err());
+
+        // Without a limit, strings can be of any length.
+        let json = format!("v = {long}");
+        assert_eq!(from_str::<Data>(&json).unwrap().v, long);
+    }
+
+    #[test]
+    fn deserialize_empty_document() {
+        #[derive(Debug, Default, serde::Deserialize, PartialEq)]
+        #[serde(default)]
+        struct Defaults {
+            foo: u64,
+            bar: String,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Optional {
+            foo: Option<u64>,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Required {
+            foo: u64,
+            bar: Option<u64>,
+        }
+
+        let tests = [
+            "",
+            "  \n\t\r\n",
+            "// foo\n/* bar */\n",
+            "\n\n  /* foo\n */ // bar",
+        ];
+
+        for json in tests {
+            assert_eq!(from_str::<Defaults>(json), Ok(Defaults::default()));
+            assert_eq!(from_str::<Optional>(json), Ok(Optional { foo: None }));
+
+            let err = Error::new(
+                ErrorCode::EmptyDocument(Box::new(ErrorCode::Message(String::from(
+                    "missing field `foo`",
+                )))),
+                1,
+                1,
+                Some(String::new()),
+            );
+            assert_eq!(from_str::<Required>(json), Err(err));
+        }
+
+        let err = from_str::<Required>("\n// foo\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing field `foo` in an empty document at line 1 column 1"
+        );
+
+        // A document with keys reports the missing field at its first key.
+        let err = Error::new(
+            ErrorCode::Message(String::from("missing field `foo`")),
+            2,
+            1,
+            Some(String::from("bar = 1")),
+        );
+        assert_eq!(from_str::<Required>("// foo\nbar = 1"), Err(err));
+    }
+
+    #[test]
+    fn deserialize_flatten() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Size {
+            width: u64,
+            height: u64,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Window {
+            name: String,
+            id: u64,
+            #[serde(flatten)]
+            size: Size,
+            #[serde(flatten)]
+            rest: BTreeMap<String, String>,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            window: Window,
+        }
+
+        // Everything not matched by a named field ends up in the catch-all.
+        let json =
+            "name = main\nwidth = 1\ntitle = \"Main Window\"\nheight = 2\nid = 3\nicon = app.png";
+        let expected = Window {
+            name: String::from("main"),
+            id: 3,
+            size: Size {
+                width: 1,
+                height: 2,
+            },
+            rest: BTreeMap::from([
+                (String::from("icon"), String::from("app.png")),
+                (String::from("title"), String::from("Main Window")),
+            ]),
+        };
+        assert_ok!(Window, expected, json);
+
+        // Flattened values are buffered by serde, so errors within them can only be placed
+        // at the start of the object they are in.
+        let json = "window = {\n  name = main\n  id = 3\n  width = foo\n  height = 2\n}";
+        let err = Error::new(
+            ErrorCode::Message(String::from("invalid type: string \"foo\", expected u64")),
+            1,
+            10,
+            Some(String::from(
+                "{\n  name = main\n  id = 3\n  width = foo\n  height = 2\n}",
+            )),
+        );
+        assert_eq!(from_str::<Data>(json), Err(err.in_key("window")));
+
+        let json =
+            "window = {\n  name = main\n  id = 3\n  width = 1\n  height = 2\n  title = [1]\n}";
+        let err = from_str::<Data>(json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid type: sequence, expected a string in `window` at line 1 column 10"
+        );
+
+        // Named fields are not buffered, and keep their exact location.
+        let json = "window = {\n  name = main\n  id = foo\n  width = 1\n  height = 2\n}";
+        let err = from_str::<Data>(json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected an integer value in `window.id` at line 3 column 8"
+        );
+    }
+
+    #[test]
+    fn deserialize_flatten_nested() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Position {
+            x: f32,
+            y: f32,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Layout {
+            depth: u8,
+            #[serde(flatten)]
+            extra: BTreeMap<String, crate::Value>,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Widget {
+            visible: bool,
+            tags: Vec<String>,
+            position: Position,
+            note: Option<String>,
+            #[serde(flatten)]
+            layout: Layout,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        #[serde(tag = "kind")]
+        enum Shape {
+            Circle { radius: f64 },
+            Square { side: u32 },
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Item {
+            name: String,
+            #[serde(flatten)]
+            widget: Widget,
+            #[serde(flatten)]
+            shape: Shape,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Items {
+            items: Vec<Item>,
+        }
+
+        // Flattened fields may themselves flatten, and hold any kind of value.
+        let sjson = r#"
+items = [
+  {
+    name = a
+    visible = true
+    tags = [x, y]
+    position = { x = 1.5, y = 2 }
+    note = null
+    depth = 4
+    kind = Circle
+    radius = 0.5
+    mask = 0xFF
+    children = { b = [1, 2] }
+  }
+]
+"#;
+        let actual = from_str::<Items>(sjson).unwrap();
+        let expected = Item {
+            name: String::from("a"),
+            widget: Widget {
+                visible: true,
+                tags: vec![String::from("x"), String::from("y")],
+                position: Position { x: 1.5, y: 2.0 },
+                note: None,
+                layout: Layout {
+                    depth: 4,
+                    extra: BTreeMap::from([
+                        (
+                            String::from("children"),
+                            from_str::<crate::Value>("b = [1, 2]").unwrap(),
+                        ),
+                        (
+                            String::from("kind"),
+                            crate::Value::String(String::from("Circle")),
+                        ),
+                        (String::from("mask"), crate::Value::Integer(255)),
+                        (String::from("radius"), crate::Value::Float(0.5)),
+                    ]),
+                },
+            },
+            shape: Shape::Circle { radius: 0.5 },
+        };
+        assert_eq!(actual.items, vec![expected]);
 
-            let json = format!("value = {}", $json);
-            let actual = from_str::<Value>(&json);
-            assert_eq!(actual, Err($expected));
-        }};
+        // Keys and strings are buffered as borrowed, so they can be read as `&str`.
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Borrowed<'a> {
+            name: &'a str,
+            #[serde(borrow, flatten)]
+            rest: BTreeMap<&'a str, &'a str>,
+        }
+
+        let actual = from_str::<Borrowed>("name = a\ncolor = red\nsize = large").unwrap();
+        assert_eq!(
+            actual,
+            Borrowed {
+                name: "a",
+                rest: BTreeMap::from([("color", "red"), ("size", "large")]),
+            }
+        );
     }
 
     #[test]
-    fn deserialize_null() {
-        assert_value_ok!((), "null");
+    fn deserialize_visitor_error_location() {
+        #[derive(Debug, serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Window {
+            _width: u64,
+        }
 
-        let err = Error::new(ErrorCode::ExpectedNull, 1, 8, Some(" foo".to_string()));
-        assert_value_err!((), err, "foo");
-    }
+        #[derive(Debug, serde::Deserialize)]
+        struct Data {
+            _windows: Vec<Window>,
+        }
 
-    #[test]
-    fn deserialize_bool() {
-        assert_value_ok!(bool, true, "true");
-        assert_value_ok!(bool, false, "false");
+        let json = "_windows = [\n  { _width = 1 }\n  { _height = 2 }\n]";
+        let err = Error::new(
+            ErrorCode::Message(String::from("unknown field `_height`, expected `_width`")),
+            3,
+            5,
+            Some(String::from("_height = 2 }\n]")),
+        )
+        .in_index(1)
+        .in_key("_windows");
+        assert_eq!(from_str::<Data>(json).unwrap_err(), err);
 
-        let err = Error::new(ErrorCode::ExpectedBoolean, 1, 8, Some(" foo".to_string()));
-        assert_value_err!(bool, err, "foo");
+        let json = "_windows = [\n  { _width = 1 }\n  {}\n]";
+        let err = from_str::<Data>(json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing field `_width` in `_windows[1]` at line 3 column 3"
+        );
+
+        // Values that a visitor rejects are placed at the value.
+        let json = "_windows = [\n  { _width = 1 }\n  { _width = -1 }\n]";
+        let err = from_str::<Data>(json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value: integer `-1`, expected u64 in `_windows[1]._width` at line 3 column 14"
+        );
     }
 
     #[test]
-    fn deserialize_integer() {
-        assert_value_ok!(i64, 0, "0");
-        assert_value_ok!(i64, -1, "-1");
-        assert_value_ok!(i64, i64::MAX, i64::MAX.to_string());
-        assert_value_ok!(i64, i64::MIN, i64::MIN.to_string());
+    fn deserialize_error_path() {
+        use std::collections::BTreeMap;
 
-        assert_value_ok!(i8, 0, "0");
-        assert_value_ok!(i8, 102, "102");
-        assert_value_ok!(i8, -102, "-102");
-        assert_value_ok!(u8, 102, "102");
-        assert_value_ok!(i16, 256, "256");
+        #[derive(Debug, serde::Deserialize)]
+        struct Counter {
+            _mask: u64,
+        }
 
-        let err = Error::new(ErrorCode::ExpectedInteger, 1, 8, Some(" foo".to_string()));
-        assert_value_err!(i64, err, "foo");
-    }
+        #[derive(Debug, serde::Deserialize)]
+        struct Win32 {
+            _counters: Vec<Counter>,
+        }
 
-    #[test]
-    fn deserialize_float() {
-        assert_value_ok!(f64, 0.0, "0");
-        assert_value_ok!(f64, 0.0, "0.0");
-        assert_value_ok!(f64, -1.0, "-1");
-        assert_value_ok!(f64, -1.0, "-1.0");
-        assert_value_ok!(f64, f64::MAX, f64::MAX.to_string());
-        assert_value_ok!(f64, f64::MIN, f64::MIN.to_string());
-    }
+        #[derive(Debug, serde::Deserialize)]
+        struct Settings {
+            _win32: Win32,
+            #[serde(default)]
+            _grid: Vec<Vec<u64>>,
+            #[serde(default)]
+            _names: BTreeMap<String, u64>,
+        }
 
-    #[test]
-    fn deserialize_vec() {
-        assert_value_ok!(Vec<u64>, vec![1, 2, 3], "[1, 2, 3]");
-        assert_value_ok!(
-            Vec<u64>,
-            vec![1, 2, 3],
-            "\
-[
-    1
-    2
-    3
-]"
+        let json = "_win32 = {\n  _counters = [\n    { _mask = 1 }\n    { _mask = 2 }\n    { _mask = foo }\n  ]\n}";
+        let err = from_str::<Settings>(json).unwrap_err();
+        assert_eq!(err.path(), Some("_win32._counters[2]._mask"));
+        assert_eq!(
+            err.to_string(),
+            "expected an integer value in `_win32._counters[2]._mask` at line 5 column 15"
         );
-    }
 
-    #[test]
-    fn deserialize_enum() {
-        #[derive(Debug, serde::Deserialize, PartialEq)]
-        enum Animal {
-            Mouse,
-            Dog { name: String },
-            Cat(u64),
+        let tests = [
+            (
+                "_win32 = { _counters = [] }\n_grid = [\n  [1]\n  [2, foo]\n]",
+                Some("_grid[1][1]"),
+            ),
+            (
+                "_win32 = { _counters = [] }\n_names = { foo = 1, bar = baz }",
+                Some("_names.bar"),
+            ),
+            ("_win32 = { _counters = [{}] }", Some("_win32._counters[0]")),
+            ("_win32 = { _counters = 1 }", Some("_win32._counters")),
+            ("_grid = []", None),
+            ("_win32 = { _counters = [] }\n}", None),
+        ];
+
+        for (json, path) in tests {
+            let err = from_str::<Settings>(json).unwrap_err();
+            assert_eq!(err.path(), path, "{json:?}");
         }
 
-        assert_value_ok!(Animal, Animal::Mouse, "Mouse");
-        assert_value_ok!(Animal, Animal::Cat(9), "{ Cat = 9 }");
-        assert_value_ok!(
-            Animal,
-            Animal::Dog {
-                name: String::from("Buddy")
-            },
-            "{ Dog = { name = Buddy }}"
-        );
+        let json = "_win32 = { _counters = [] }\n_grid = { [2] = [1], [1] = [ foo ] }";
+        let mut de = Deserializer::from_str(json).with_indexed_arrays();
+        let err = Settings::deserialize(&mut de).unwrap_err();
+        // Indices are positions in the resulting array, not the ones written in the object.
+        assert_eq!(err.path(), Some("_grid[0][0]"));
     }
 
-    // Checks the example from
-    // https://help.autodesk.com/view/Stingray/ENU/?guid=__stingray_help_managing_content_sjson_html
     #[test]
-    fn deserialize_stingray_example() {
+    fn deserialize_array_spans() {
         #[derive(Debug, serde::Deserialize, PartialEq)]
-        struct Win32Settings {
-            query_performance_counter_affinity_mask: u64,
+        struct Item {
+            name: String,
         }
 
         #[derive(Debug, serde::Deserialize, PartialEq)]
-        struct Settings {
-            boot_script: String,
-            console_port: u16,
-            win32: Win32Settings,
-            render_config: PathBuf,
+        struct Data {
+            items: Vec<Item>,
         }
 
-        let expected = Settings {
-            boot_script: String::from("boot"),
-            console_port: 14030,
-            win32: Win32Settings {
-                query_performance_counter_affinity_mask: 0,
-            },
-            render_config: PathBuf::from("core/rendering/renderer"),
-        };
-
-        let json = r#"
-// The script that should be started when the application runs.
-boot_script = "boot"
-
-// The port on which the console server runs.
-console_port = 14030
-
-// Settings for the win32 platform
-win32 = {
-    /* Sets the affinity mask for
-       QueryPerformanceCounter() */
-    query_performance_counter_affinity_mask = 0
-}
-
-render_config = "core/rendering/renderer"
-"#;
+        let json = "items = [\n    { name = foo }\n    { name = bar }\n]";
+        let (_, spans) = from_str_with_spans::<Data>(json).unwrap();
 
-        assert_ok!(Settings, expected, json);
+        assert_eq!(&json[spans["items.0"].range.clone()], "{ name = foo }");
+        assert_eq!(&json[spans["items.1.name"].range.clone()], "bar");
+        assert_eq!(spans["items.1.name"].line, 3);
     }
 
     #[test]
@@ -747,6 +4727,187 @@ render_config = "core/rendering/renderer"
         assert_eq!(actual, Err(err));
     }
 
+    #[test]
+    fn deserialize_duplicate_keys() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Inner {
+            a: u8,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Data {
+            a: u8,
+            inner: Inner,
+            list: Vec<Inner>,
+        }
+
+        let deserialize = |sjson| {
+            let mut de = Deserializer::from_str(sjson).with_deny_duplicate_keys();
+            Data::deserialize(&mut de)
+        };
+
+        // The same key in different objects is fine.
+        let sjson = "a = 1\ninner = { a = 2 }\nlist = [{ a = 3 }, { a = 4 }]";
+        assert!(deserialize(sjson).is_ok());
+
+        let sjson = "a = 1\ninner = { a = 2 }\nlist = []\na = 1";
+        let err = deserialize(sjson).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(err.to_string(), "duplicate key `a` at line 4 column 1");
+
+        let sjson = "a = 1\ninner = { a = 2, \"a\" = 3 }\nlist = []";
+        let err = deserialize(sjson).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "duplicate key `a` in `inner` at line 2 column 18"
+        );
+
+        let sjson = "a = 1\ninner = { a = 2 }\nlist = [{ a = 3, a = 4 }]";
+        assert_eq!(
+            deserialize(sjson).unwrap_err().to_string(),
+            "duplicate key `a` in `list[0]` at line 3 column 18"
+        );
+
+        // Without the option, it depends on the type.
+        let sjson = "a = 1\ninner = { a = 2 }\nlist = []\na = 1";
+        assert!(from_str::<Data>(sjson).is_err());
+        let value = from_str::<crate::Value>("a = 1\nb = 2\na = 3").unwrap();
+        assert_eq!(value["a"].as_i64(), Some(3));
+    }
+
+    #[test]
+    fn error_kind() {
+        #[derive(Debug, Deserialize, Serialize)]
+        #[serde(deny_unknown_fields)]
+        struct Data {
+            value: u8,
+        }
+
+        let tests = [
+            ("value = 1 }", ErrorKind::TrailingCharacters),
+            ("value = foo", ErrorKind::ExpectedInteger),
+            ("value = 1\nextra = 2", ErrorKind::Custom),
+            // The empty document only changes the message of a missing field.
+            ("", ErrorKind::Custom),
+            ("value = 1 /* foo", ErrorKind::UnterminatedComment),
+            ("value = [1, 2]", ErrorKind::ExpectedInteger),
+        ];
+        for (sjson, kind) in tests {
+            let err = from_str::<Data>(sjson).unwrap_err();
+            assert_eq!(err.kind(), kind, "{sjson}: {err}");
+        }
+
+        assert_eq!(
+            from_str::<(u8, u8)>("a = 1").unwrap_err().kind(),
+            ErrorKind::TopLevelSequence
+        );
+        assert_eq!(
+            crate::to_slice(&mut [0; 4], &Data { value: 1 }).map_err(|err| err.kind()),
+            Err(ErrorKind::BufferFull)
+        );
+    }
+
+    #[test]
+    fn error_location() {
+        #[derive(Debug, Deserialize)]
+        struct Data {
+            #[allow(dead_code)]
+            value: Vec<u8>,
+        }
+
+        let err = from_str::<Data>("\nvalue = [\n  1\n  föö\n]").unwrap_err();
+        assert_eq!(err.line(), 4);
+        assert_eq!(err.column(), 3);
+        assert_eq!(err.fragment(), Some("föö\n]"));
+
+        // Tokens that were read, or peeked at, before the mismatch was found are pointed at, too.
+        let err = from_str::<Data>("value =   { a = 1 }").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 11));
+        assert_eq!(err.fragment(), Some("{ a = 1 }"));
+
+        let err = from_str::<Data>("value = [1,   true]").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 15));
+        assert_eq!(err.fragment(), Some("true]"));
+
+        let err = from_str::<crate::Value>("value =   ]").unwrap_err();
+        assert_eq!((err.line(), err.column()), (1, 11));
+
+        let err = to_string(&f64::NAN).unwrap_err();
+        assert_eq!(err.line(), 0);
+        assert_eq!(err.column(), 0);
+        assert_eq!(err.fragment(), None);
+    }
+
+    #[test]
+    fn deserialize_top_level_sequence() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Pair(u8, u8);
+
+        let json = "a = 1\nb = 2";
+        let err = Error::new(ErrorCode::TopLevelSequence, 1, 1, Some(json.to_string()));
+        assert_eq!(from_str::<Pair>(json).unwrap_err(), err);
+        assert_eq!(from_str::<(u8, u8)>(json).unwrap_err(), err);
+        assert_eq!(from_str::<Vec<u8>>(json).unwrap_err(), err);
+        assert_eq!(
+            err.to_string(),
+            "the top-level document is key-value pairs, it cannot be deserialized into a sequence or tuple at line 1 column 1"
+        );
+
+        // A document that is a single array can be read into a sequence.
+        assert_eq!(from_str::<(u8, u8)>("[1, 2]"), Ok((1, 2)));
+        assert_eq!(
+            from_str::<Pair>("// pair\n[\n  1\n  2\n]\n"),
+            Ok(Pair(1, 2))
+        );
+        assert_eq!(from_str::<Vec<u8>>("[]"), Ok(vec![]));
+        assert!(from_str::<serde::de::IgnoredAny>("[1, [2], { a = 3 }]").is_ok());
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Record {
+            name: String,
+            size: u32,
+        }
+
+        let json = "[\n  { name = \"foo\", size = 1 }\n  { name = \"bar\", size = 2 }\n]\n";
+        let expected = vec![
+            Record {
+                name: String::from("foo"),
+                size: 1,
+            },
+            Record {
+                name: String::from("bar"),
+                size: 2,
+            },
+        ];
+        assert_eq!(from_str::<Vec<Record>>(json), Ok(expected));
+
+        let err = Error::new(
+            ErrorCode::TrailingCharacters,
+            1,
+            7,
+            Some(" [3]".to_string()),
+        );
+        assert_eq!(from_str::<Vec<u8>>("[1, 2] [3]"), Err(err));
+
+        // Scalars are still rejected at the top level.
+        let json = "[1, 2]";
+        let err = Error::new(
+            ErrorCode::ExpectedTopLevelObject,
+            1,
+            1,
+            Some(json.to_string()),
+        );
+        assert_eq!(from_str::<u8>(json), Err(err));
+
+        // Nested tuple structs are arrays as usual.
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Data {
+            pair: Pair,
+        }
+
+        assert_ok!(Data, Data { pair: Pair(1, 2) }, "pair = [1, 2]");
+    }
+
     #[test]
     fn deserialize_array() {
         #[derive(Debug, Default, serde::Deserialize, PartialEq)]
@@ -766,7 +4927,267 @@ array = [
         assert_ok!(Data, expected, sjson);
     }
 
+    #[test]
+    fn peek_is_collection() {
+        let tests = [
+            ("[1, 2]", Some(CollectionKind::Array)),
+            ("\n  // comment\n[{ foo = 1 }]", Some(CollectionKind::Array)),
+            ("{ foo = [1] }", Some(CollectionKind::Object)),
+            ("{}", Some(CollectionKind::Object)),
+            ("{ [1] = foo }", Some(CollectionKind::Object)),
+            ("1", None),
+            ("1.5", None),
+            ("foo", None),
+            ("\"[1]\"", None),
+            ("null", None),
+            ("true", None),
+            ("", None),
+        ];
+
+        for (json, expected) in tests {
+            let mut de = Deserializer::from_str(json);
+            assert_eq!(de.peek_is_collection().unwrap(), expected, "{json}");
+            // Nothing was consumed.
+            assert_eq!(de.peek_is_collection().unwrap(), expected, "{json}");
+            assert_eq!(de.end().is_ok(), json.is_empty(), "{json}");
+        }
+
+        let mut de = Deserializer::from_str("{ [1] = foo }").with_indexed_arrays();
+        assert_eq!(
+            de.peek_is_collection().unwrap(),
+            Some(CollectionKind::Array)
+        );
+
+        let mut de = Deserializer::from_str("/* foo");
+        assert!(de.peek_is_collection().is_err());
+    }
+
+    #[test]
+    fn read_tagged() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Color {
+            r: u8,
+            g: u8,
+            b: u8,
+        }
+
+        let json = "type = vec3\nvalue = [1, 2, 3]";
+        let mut de = Deserializer::from_str(json);
+        let (tag, value) = de.read_tagged("type", "value").unwrap();
+        de.end().unwrap();
+        assert_eq!(tag, "vec3");
+        assert_eq!(value.get(), "[1, 2, 3]");
+        assert_eq!(value.offset(), 20);
+        assert_eq!(value.deserialize::<[u64; 3]>().unwrap(), [1, 2, 3]);
+
+        // The tag may come last, and other keys are skipped.
+        let json = "value = {\n  r = 255, g = 0\n  b = 0\n}\nname = foo\ntype = color";
+        let mut de = Deserializer::from_str(json);
+        let (tag, value) = de.read_tagged("type", "value").unwrap();
+        de.end().unwrap();
+        assert_eq!(tag, "color");
+        assert_eq!(value.get(), "{\n  r = 255, g = 0\n  b = 0\n}");
+        assert_eq!(
+            value.deserialize::<Color>().unwrap(),
+            Color { r: 255, g: 0, b: 0 }
+        );
+
+        // Nested objects, read from a value captured before.
+        let json = "type = list\nvalue = {\n  type = id\n  value = \"foo bar\"\n}";
+        let (_, value) = Deserializer::from_str(json)
+            .read_tagged("type", "value")
+            .unwrap();
+        let mut de = Deserializer::from_raw(&value);
+        let (tag, value) = de.read_tagged("type", "value").unwrap();
+        de.end().unwrap();
+        assert_eq!(tag, "id");
+        assert_eq!(value.deserialize::<String>().unwrap(), "foo bar");
+
+        // Options are enabled on the deserializer for the raw value.
+        let json = "type = color\nvalue = { r: 1, g: 2, b: 3 }";
+        let (_, value) = Deserializer::from_str(json)
+            .with_assignments(&[Assignment::Equals, Assignment::Colon])
+            .read_tagged("type", "value")
+            .unwrap();
+        assert!(value.deserialize::<Color>().is_err());
+        let mut de = Deserializer::from_raw(&value).with_assignments(&[Assignment::Colon]);
+        assert_eq!(
+            Color::deserialize(&mut de).unwrap(),
+            Color { r: 1, g: 2, b: 3 }
+        );
+
+        // Errors point into the original document.
+        let json = "type = vec3\nvalue = [\n  1, 2\n  foo\n]";
+        let (_, value) = Deserializer::from_str(json)
+            .read_tagged("type", "value")
+            .unwrap();
+        let err = value.deserialize::<[u64; 3]>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected an integer value in `[2]` at line 4 column 3"
+        );
+
+        let err = Deserializer::from_str("value = 1")
+            .read_tagged("type", "value")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "missing field `type` at line 1 column 1");
+
+        let err = Deserializer::from_str("foo = 1\ntype = vec3")
+            .read_tagged("type", "value")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "missing field `value` at line 1 column 1");
+
+        let mut de = Deserializer::from_str("type = vec3\nvalue = [1, 2");
+        assert!(de.read_tagged("type", "value").is_err());
+    }
+
+    #[test]
+    fn deserialize_empty_collections() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Empty {}
+
+        for json in ["[]", "[ ]", "[\n]", "[\n  // nothing\n]"] {
+            assert_value_ok!(Vec<u64>, json);
+            assert_value_ok!(Empty, json.replace('[', "{").replace(']', "}"));
+            assert_value_ok!(
+                BTreeMap<String, u64>,
+                json.replace('[', "{").replace(']', "}")
+            );
+        }
+
+        #[derive(Debug, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Data {
+            map: BTreeMap<String, Vec<u64>>,
+            list: Vec<Vec<u64>>,
+            empty: Empty,
+            objects: Vec<Empty>,
+        }
+
+        let expected = Data {
+            map: BTreeMap::from([(String::from("foo"), Vec::new())]),
+            list: vec![Vec::new(), Vec::new()],
+            empty: Empty {},
+            objects: vec![Empty {}],
+        };
+
+        // Without any whitespace, the delimiters directly follow each other.
+        assert_ok!(
+            Data,
+            expected,
+            "map={foo=[]}\nlist=[[],[]]\nempty={}\nobjects=[{}]"
+        );
+        assert_ok!(
+            Data,
+            expected,
+            "map = { foo = [] }, list = [[], []], empty = {}, objects = [{}]"
+        );
+
+        // As written by the serializer.
+        let sjson = crate::to_string(&expected).unwrap();
+        assert_ok!(Data, expected, &sjson);
+        let sjson = crate::to_string(&Data::default()).unwrap();
+        assert_ok!(Data, Data::default(), &sjson);
+
+        assert_ok!(BTreeMap<String, u64>, BTreeMap::new(), "");
+        assert_ok!(BTreeMap<String, u64>, BTreeMap::new(), "\n// nothing\n");
+    }
+
     // Regression test for #1 (https://git.sclu1034.dev/lucas/serde_sjson/issues/1)
+    #[test]
+    fn deserialize_absent_and_null_options() {
+        #[derive(Debug, Default, serde::Deserialize, PartialEq)]
+        struct Config {
+            name: String,
+            #[serde(default)]
+            version: Option<String>,
+            // Missing `Option` fields are `None` even without `#[serde(default)]`.
+            license: Option<String>,
+        }
+
+        let expected = Config {
+            name: String::from("foo"),
+            version: None,
+            license: None,
+        };
+        assert_ok!(Config, expected, "name = foo");
+        assert_ok!(
+            Config,
+            expected,
+            "name = foo\nversion = null\nlicense = null"
+        );
+
+        let expected = Config {
+            name: String::from("foo"),
+            version: Some(String::from("0.1.0")),
+            license: Some(String::from("null")),
+        };
+        assert_ok!(
+            Config,
+            expected,
+            "name = foo\nversion = \"0.1.0\"\nlicense = \"null\""
+        );
+    }
+
+    #[test]
+    fn roundtrip_double_option() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+        struct Config {
+            name: String,
+            #[serde(
+                default,
+                with = "crate::double_option",
+                skip_serializing_if = "Option::is_none"
+            )]
+            version: Option<Option<String>>,
+            #[serde(default, with = "crate::double_option")]
+            size: Option<Option<u64>>,
+        }
+
+        let tests = [
+            (None, None, "name = foo\nsize = null\n"),
+            (
+                Some(None),
+                None,
+                "name = foo\nversion = null\nsize = null\n",
+            ),
+            (
+                Some(Some(String::from("0.1.0"))),
+                Some(Some(3)),
+                "name = foo\nversion = 0.1.0\nsize = 3\n",
+            ),
+        ];
+
+        for (version, size, sjson) in tests {
+            let config = Config {
+                name: String::from("foo"),
+                version,
+                size,
+            };
+            assert_eq!(to_string(&config).unwrap(), sjson);
+        }
+
+        // Without skipping, an absent value is written as `null`, and reads back as explicit.
+        let config = from_str::<Config>("name = foo\nsize = null").unwrap();
+        assert_eq!(config.size, Some(None));
+        let config = from_str::<Config>("name = foo").unwrap();
+        assert_eq!(config.size, None);
+
+        let expected = Config {
+            name: String::from("foo"),
+            version: Some(None),
+            size: Some(Some(3)),
+        };
+        assert_ok!(Config, expected, "name = foo\nversion = null\nsize = 3");
+
+        let err = from_str::<Config>("name = foo\nsize = bar").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected an integer value in `size` at line 2 column 8"
+        );
+    }
+
     #[test]
     fn deserialize_dtmt_config() {
         #[derive(Debug, Default, serde::Deserialize, PartialEq)]
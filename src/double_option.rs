@@ -0,0 +1,65 @@
+//! Tells an absent field apart from one that is explicitly `null`, using an
+//! `Option<Option<T>>`.
+//!
+//! A plain `Option<T>` field is `None` both when the key is missing and when its value
+//! is `null`. With `#[serde(default, with = "serde_sjson::double_option")]`, a missing
+//! key is `None`, a `null` value is `Some(None)`, and any other value is `Some(Some(_))`.
+//!
+//! Add `skip_serializing_if = "Option::is_none"` to leave the key out again when
+//! serializing `None`.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Config {
+//!     #[serde(
+//!         default,
+//!         with = "serde_sjson::double_option",
+//!         skip_serializing_if = "Option::is_none"
+//!     )]
+//!     version: Option<Option<String>>,
+//! }
+//!
+//! let config: Config = serde_sjson::from_str("").unwrap();
+//! assert_eq!(config.version, None);
+//!
+//! let config: Config = serde_sjson::from_str("version = null").unwrap();
+//! assert_eq!(config.version, Some(None));
+//!
+//! let config: Config = serde_sjson::from_str("version = \"0.1.0\"").unwrap();
+//! assert_eq!(config.version, Some(Some(String::from("0.1.0"))));
+//!
+//! let s = serde_sjson::to_string(&Config { version: Some(None) }).unwrap();
+//! assert_eq!(s, "version = null\n");
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes `Some(None)` as `null`, and `Some(Some(value))` as the value.
+///
+/// `None` is serialized as `null` as well, unless the field is skipped.
+/// `Some(None)` is written as a unit, so that it is kept by
+/// [`Serializer::with_skip_none`](crate::Serializer::with_skip_none).
+pub fn serialize<T, S>(value: &Option<Option<T>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    match value {
+        Some(Some(value)) => serializer.serialize_some(value),
+        Some(None) => serializer.serialize_unit(),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Deserializes a value that is present, wrapping `null` as `Some(None)`.
+///
+/// Absent values are only handled through `#[serde(default)]`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
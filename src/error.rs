@@ -19,16 +19,24 @@ struct ErrorImpl {
     column: usize,
     fragment: Option<String>,
     token: Option<Token>,
+    // The keys and array indices leading to the value, like `win32.counters[2].mask`.
+    path: String,
 }
 
 #[derive(PartialEq)]
 pub(crate) enum ErrorCode {
     // Generic error built from a message or different error
     Message(String),
+    // The error of the underlying reader or writer, kept as the source.
+    Io(IoError),
+    BufferFull,
+    DuplicateKey(String),
     ExpectedArray,
     ExpectedArrayEnd,
+    ExpectedArrayIndex,
     ExpectedArraySeparator,
     ExpectedBoolean,
+    ExpectedChar,
     ExpectedEnum,
     ExpectedFloat,
     ExpectedInteger,
@@ -40,20 +48,170 @@ pub(crate) enum ErrorCode {
     ExpectedString,
     ExpectedTopLevelObject,
     ExpectedValue,
+    FloatOverflow,
+    IntegerOverflow,
+    InvalidBase64,
+    InvalidMapKey,
+    InvalidUnicodeEscape,
+    MapKeyWithoutValue,
+    StringTooLong(usize),
+    TopLevelSequence,
     TrailingCharacters,
+    UnquotedTab,
+    UnterminatedComment,
     NonFiniteFloat,
+    // An error for a document without any keys, like a missing field.
+    EmptyDocument(Box<ErrorCode>),
+}
+
+/// The category of an [`Error`], as returned by [`Error::kind`].
+///
+/// More kinds may be added in the future.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A custom error, like one from a `Serialize` or `Deserialize` implementation,
+    /// or a syntax error without a more specific kind.
+    Custom,
+    /// Reading the input or writing the output failed.
+    ///
+    /// The `io::Error` is available from [`std::error::Error::source`].
+    Io,
+    /// A fixed-size output buffer ran out of space.
+    BufferFull,
+    /// An object contained the same key more than once.
+    ///
+    /// This is only checked with [`Deserializer::with_deny_duplicate_keys`].
+    ///
+    /// [`Deserializer::with_deny_duplicate_keys`]: crate::Deserializer::with_deny_duplicate_keys
+    DuplicateKey,
+    /// An array was expected.
+    ExpectedArray,
+    /// The closing `]` of an array was expected.
+    ExpectedArrayEnd,
+    /// An index like `[1]` was expected.
+    ExpectedArrayIndex,
+    /// A separator between array elements was expected.
+    ExpectedArraySeparator,
+    /// A boolean was expected.
+    ExpectedBoolean,
+    /// A string of exactly one character was expected.
+    ExpectedChar,
+    /// A unit variant or an object with a single variant was expected.
+    ExpectedEnum,
+    /// A floating point number was expected.
+    ExpectedFloat,
+    /// An integer was expected.
+    ExpectedInteger,
+    /// An object was expected.
+    ExpectedMap,
+    /// The closing `}` of an object was expected.
+    ExpectedMapEnd,
+    /// The `=` between a key and its value was expected.
+    ExpectedMapEquals,
+    /// A separator between object entries was expected.
+    ExpectedMapSeparator,
+    /// `null` was expected.
+    ExpectedNull,
+    /// A string was expected.
+    ExpectedString,
+    /// The document must be an object, but a different value was requested.
+    ExpectedTopLevelObject,
+    /// Any value was expected.
+    ExpectedValue,
+    /// A floating point number was too large for `f32`.
+    FloatOverflow,
+    /// An integer was too large for `i64` and `u64`.
+    IntegerOverflow,
+    /// A string was not valid base64.
+    InvalidBase64,
+    /// A map key cannot be written.
+    InvalidMapKey,
+    /// A `\u` escape sequence was malformed.
+    InvalidUnicodeEscape,
+    /// A map key was written without a value.
+    MapKeyWithoutValue,
+    /// A string exceeded the maximum length.
+    StringTooLong,
+    /// A document of key-value pairs was read into a sequence.
+    TopLevelSequence,
+    /// There was more input after the value.
+    TrailingCharacters,
+    /// An unquoted string was followed by a tab.
+    UnquotedTab,
+    /// A block comment was never closed.
+    UnterminatedComment,
+    /// A floating point number was infinite or NaN.
+    NonFiniteFloat,
+}
+
+// An `io::Error` that can be compared, so that `Error` can stay `PartialEq`.
+#[derive(Debug)]
+pub(crate) struct IoError(io::Error);
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.kind() == other.0.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl ErrorCode {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ErrorCode::Message(_) => ErrorKind::Custom,
+            ErrorCode::Io(_) => ErrorKind::Io,
+            ErrorCode::BufferFull => ErrorKind::BufferFull,
+            ErrorCode::DuplicateKey(_) => ErrorKind::DuplicateKey,
+            ErrorCode::ExpectedArray => ErrorKind::ExpectedArray,
+            ErrorCode::ExpectedArrayEnd => ErrorKind::ExpectedArrayEnd,
+            ErrorCode::ExpectedArrayIndex => ErrorKind::ExpectedArrayIndex,
+            ErrorCode::ExpectedArraySeparator => ErrorKind::ExpectedArraySeparator,
+            ErrorCode::ExpectedBoolean => ErrorKind::ExpectedBoolean,
+            ErrorCode::ExpectedChar => ErrorKind::ExpectedChar,
+            ErrorCode::ExpectedEnum => ErrorKind::ExpectedEnum,
+            ErrorCode::ExpectedFloat => ErrorKind::ExpectedFloat,
+            ErrorCode::ExpectedInteger => ErrorKind::ExpectedInteger,
+            ErrorCode::ExpectedMap => ErrorKind::ExpectedMap,
+            ErrorCode::ExpectedMapEnd => ErrorKind::ExpectedMapEnd,
+            ErrorCode::ExpectedMapEquals => ErrorKind::ExpectedMapEquals,
+            ErrorCode::ExpectedMapSeparator => ErrorKind::ExpectedMapSeparator,
+            ErrorCode::ExpectedNull => ErrorKind::ExpectedNull,
+            ErrorCode::ExpectedString => ErrorKind::ExpectedString,
+            ErrorCode::ExpectedTopLevelObject => ErrorKind::ExpectedTopLevelObject,
+            ErrorCode::ExpectedValue => ErrorKind::ExpectedValue,
+            ErrorCode::FloatOverflow => ErrorKind::FloatOverflow,
+            ErrorCode::IntegerOverflow => ErrorKind::IntegerOverflow,
+            ErrorCode::InvalidBase64 => ErrorKind::InvalidBase64,
+            ErrorCode::InvalidMapKey => ErrorKind::InvalidMapKey,
+            ErrorCode::InvalidUnicodeEscape => ErrorKind::InvalidUnicodeEscape,
+            ErrorCode::MapKeyWithoutValue => ErrorKind::MapKeyWithoutValue,
+            ErrorCode::StringTooLong(_) => ErrorKind::StringTooLong,
+            ErrorCode::TopLevelSequence => ErrorKind::TopLevelSequence,
+            ErrorCode::TrailingCharacters => ErrorKind::TrailingCharacters,
+            ErrorCode::UnquotedTab => ErrorKind::UnquotedTab,
+            ErrorCode::UnterminatedComment => ErrorKind::UnterminatedComment,
+            ErrorCode::NonFiniteFloat => ErrorKind::NonFiniteFloat,
+            // Being in an empty document only changes the message.
+            ErrorCode::EmptyDocument(code) => code.kind(),
+        }
+    }
 }
 
 impl fmt::Display for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ErrorCode::Message(msg) => f.write_str(msg),
+            ErrorCode::Io(err) => fmt::Display::fmt(&err.0, f),
+            ErrorCode::BufferFull => f.write_str("output buffer is full"),
+            ErrorCode::DuplicateKey(key) => write!(f, "duplicate key `{}`", key),
             ErrorCode::ExpectedArray => f.write_str("expected an array value"),
             ErrorCode::ExpectedArrayEnd => f.write_str("expected an array end delimiter"),
+            ErrorCode::ExpectedArrayIndex => f.write_str("expected an array index like '[1]'"),
             ErrorCode::ExpectedArraySeparator => {
                 f.write_str("expected comma or newline between array entries")
             }
             ErrorCode::ExpectedBoolean => f.write_str("expected a boolean value"),
+            ErrorCode::ExpectedChar => f.write_str("expected a single character"),
             ErrorCode::ExpectedEnum => f.write_str("expected string or object"),
             ErrorCode::ExpectedFloat => f.write_str("expected floating point number"),
             ErrorCode::ExpectedInteger => f.write_str("expected an integer value"),
@@ -67,23 +225,46 @@ impl fmt::Display for ErrorCode {
             ErrorCode::ExpectedString => f.write_str("expected a string value"),
             ErrorCode::ExpectedTopLevelObject => f.write_str("expected object at the top level"),
             ErrorCode::ExpectedValue => f.write_str("expected a value"),
+            ErrorCode::FloatOverflow => f.write_str("floating point number is out of range for f32"),
+            ErrorCode::IntegerOverflow => f.write_str("integer is out of range for i64 and u64"),
+            ErrorCode::InvalidBase64 => f.write_str("expected a valid base64 string"),
+            ErrorCode::InvalidMapKey => {
+                f.write_str("map key must not contain line breaks or structural characters")
+            }
+            ErrorCode::InvalidUnicodeEscape => f.write_str(
+                "invalid unicode escape, expected `\\u` and four hex digits, with surrogates in pairs",
+            ),
+            ErrorCode::MapKeyWithoutValue => f.write_str("map key without a value"),
+            ErrorCode::StringTooLong(max) => write!(f, "string is longer than {} bytes", max),
+            ErrorCode::TopLevelSequence => f.write_str(
+                "the top-level document is key-value pairs, it cannot be deserialized into a sequence or tuple",
+            ),
             ErrorCode::TrailingCharacters => f.write_str("unexpected trailing characters"),
-            ErrorCode::NonFiniteFloat => f.write_str("got infinite floating point number"),
+            ErrorCode::UnquotedTab => f.write_str(
+                "unquoted string is followed by a tab, use quotes for strings containing tabs",
+            ),
+            ErrorCode::UnterminatedComment => f.write_str("block comment is never closed"),
+            ErrorCode::NonFiniteFloat => {
+                f.write_str("got a floating point number that is NaN or infinite")
+            }
+            ErrorCode::EmptyDocument(code) => write!(f, "{} in an empty document", code),
         }
     }
 }
 
 impl fmt::Display for ErrorImpl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.line == 0 {
-            fmt::Display::fmt(&self.code, f)
-        } else {
-            write!(
-                f,
-                "{} at line {} column {}",
-                self.code, self.line, self.column
-            )
+        fmt::Display::fmt(&self.code, f)?;
+
+        if !self.path.is_empty() {
+            write!(f, " in `{}`", self.path)?;
+        }
+
+        if self.line != 0 {
+            write!(f, " at line {} column {}", self.line, self.column)?;
         }
+
+        Ok(())
     }
 }
 
@@ -97,12 +278,13 @@ impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Error({:?}, line: {}, column: {}, fragment: {:?}, token: {:?})",
+            "Error({:?}, line: {}, column: {}, fragment: {:?}, token: {:?}, path: {:?})",
             self.inner.code.to_string(),
             self.inner.line,
             self.inner.column,
             self.inner.fragment,
             self.inner.token,
+            self.inner.path,
         )
     }
 }
@@ -118,6 +300,7 @@ impl serde::de::Error for Error {
             column: 0,
             fragment: None,
             token: None,
+            path: String::new(),
         });
         Self { inner }
     }
@@ -134,14 +317,111 @@ impl serde::ser::Error for Error {
             column: 0,
             fragment: None,
             token: None,
+            path: String::new(),
         });
         Self { inner }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.inner.code {
+            ErrorCode::Io(err) => Some(&err.0),
+            _ => None,
+        }
+    }
+}
 
 impl Error {
+    /// Renders the error along with the offending line of the source, with a caret
+    /// pointing at the column where the error occurred:
+    ///
+    /// ```text
+    /// 1 | value = [1,foo]
+    ///   |            ^
+    /// expected an integer value
+    /// ```
+    ///
+    /// `source` must be the input that produced the error. If the error has no location,
+    /// or the location is not part of `source`, only the message is rendered.
+    pub fn display_with_source(&self, source: &str) -> String {
+        let mut message = self.inner.code.to_string();
+        if let Some(path) = self.path() {
+            message.push_str(&format!(" in `{}`", path));
+        }
+
+        let Some(line) = (self.inner.line as usize)
+            .checked_sub(1)
+            .and_then(|index| source.lines().nth(index))
+        else {
+            return message;
+        };
+
+        let number = self.inner.line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        // Keep tabs, so that the caret lines up regardless of tab width.
+        let indent: String = line
+            .chars()
+            .take(self.inner.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!("{number} | {line}\n{gutter} | {indent}^\n{message}")
+    }
+
+    /// Returns the category of the error, to handle different failures programmatically.
+    ///
+    /// ```
+    /// use serde_sjson::ErrorKind;
+    ///
+    /// let err = serde_sjson::from_str::<u8>("value = 1").unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::ExpectedTopLevelObject);
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        self.inner.code.kind()
+    }
+
+    /// Returns the line the error occurred at, starting at 1.
+    ///
+    /// This is 0 for errors without a location, like those from serializing.
+    pub fn line(&self) -> u32 {
+        self.inner.line
+    }
+
+    /// Returns the column the error occurred at, counted in characters and starting at 1.
+    ///
+    /// This is 0 for errors without a location, like those from serializing.
+    pub fn column(&self) -> usize {
+        self.inner.column
+    }
+
+    /// Returns the remaining input, starting where the error occurred.
+    ///
+    /// ```
+    /// let err = serde_sjson::from_str::<serde_sjson::Value>("value = [1 2]").unwrap_err();
+    ///
+    /// assert_eq!(err.line(), 1);
+    /// assert_eq!(err.column(), 11);
+    /// assert_eq!(err.fragment(), Some(" 2]"));
+    /// ```
+    pub fn fragment(&self) -> Option<&str> {
+        self.inner.fragment.as_deref()
+    }
+
+    /// Returns the keys and array indices leading to the value that caused the error,
+    /// like `win32.counters[2].mask`.
+    ///
+    /// This is `None` for errors outside of any value, like in the top-level object itself,
+    /// and for errors from serializing.
+    pub fn path(&self) -> Option<&str> {
+        if self.inner.path.is_empty() {
+            None
+        } else {
+            Some(&self.inner.path)
+        }
+    }
+
     pub(crate) fn new(code: ErrorCode, line: u32, column: usize, fragment: Option<String>) -> Self {
         Self {
             inner: Box::new(ErrorImpl {
@@ -150,9 +430,75 @@ impl Error {
                 column,
                 fragment,
                 token: None,
+                path: String::new(),
             }),
         }
     }
+
+    // Prepends the key of the object value the error occurred in to its path.
+    // Errors pass through every enclosing value on their way out, so the path is built
+    // from the innermost value outwards.
+    pub(crate) fn in_key(mut self, key: &str) -> Self {
+        let path = &mut self.inner.path;
+
+        if !path.is_empty() && !path.starts_with('[') {
+            path.insert(0, '.');
+        }
+        path.insert_str(0, key);
+
+        self
+    }
+
+    // Prepends the index of the array element the error occurred in to its path.
+    pub(crate) fn in_index(mut self, index: usize) -> Self {
+        let path = &mut self.inner.path;
+
+        if !path.is_empty() && !path.starts_with('[') {
+            path.insert(0, '.');
+        }
+        path.insert_str(0, &format!("[{}]", index));
+
+        self
+    }
+
+    // Errors from a visitor, like an unknown field, don't know where they happened.
+    // This places them at the value being visited, unless they already have a location.
+    pub(crate) fn or_located_at(mut self, line: u32, column: usize, fragment: &str) -> Self {
+        if self.inner.line == 0 {
+            self.inner.line = line;
+            self.inner.column = column;
+            self.inner.fragment = Some(fragment.to_string());
+        }
+
+        self
+    }
+
+    // For a document that is effectively empty, the start of it is the best we can do.
+    pub(crate) fn in_empty_document(self, line: u32, column: usize) -> Self {
+        if self.inner.line != 0 {
+            return self;
+        }
+
+        Self::new(
+            ErrorCode::EmptyDocument(Box::new(self.inner.code)),
+            line,
+            column,
+            Some(String::new()),
+        )
+    }
+
+    // A `&mut [u8]` reports a `WriteZero` error once it is full. Only for such a buffer
+    // is that known to mean running out of space, other writers keep their error as it is.
+    pub(crate) fn or_buffer_full(mut self) -> Self {
+        if let ErrorCode::Io(IoError(err)) = &self.inner.code {
+            if err.kind() == io::ErrorKind::WriteZero {
+                self.inner.code = ErrorCode::BufferFull;
+            }
+        }
+
+        self
+    }
+
     pub(crate) fn with_token(
         code: ErrorCode,
         line: u32,
@@ -167,6 +513,7 @@ impl Error {
                 column,
                 fragment,
                 token: Some(token),
+                path: String::new(),
             }),
         }
     }
@@ -174,6 +521,6 @@ impl Error {
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
-        Self::new(ErrorCode::Message(format!("{}", err)), 0, 0, None)
+        Self::new(ErrorCode::Io(IoError(err)), 0, 0, None)
     }
 }
@@ -0,0 +1,197 @@
+/// The spellings used for non-finite floating point numbers.
+///
+/// Each kind of non-finite value has one canonical spelling, which the serializer emits,
+/// and any number of additional spellings that the deserializer accepts.
+///
+/// The default spellings are `nan`, `inf` and `-inf`, with `+inf` also accepted
+/// for positive infinity.
+///
+/// ```
+/// use serde_sjson::NonFiniteSpellings;
+///
+/// // MSVC-style spellings, as used by some engines.
+/// let spellings = NonFiniteSpellings::new("1.#QNAN", "1.#INF", "-1.#INF")
+///     .accept_nan("nan")
+///     .accept_infinity("inf")
+///     .accept_neg_infinity("-inf");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonFiniteSpellings {
+    // The first entry of each list is the canonical spelling.
+    nan: Vec<String>,
+    infinity: Vec<String>,
+    neg_infinity: Vec<String>,
+}
+
+impl NonFiniteSpellings {
+    /// Creates a new set of spellings with the given canonical spellings.
+    pub fn new(
+        nan: impl Into<String>,
+        infinity: impl Into<String>,
+        neg_infinity: impl Into<String>,
+    ) -> Self {
+        Self {
+            nan: vec![nan.into()],
+            infinity: vec![infinity.into()],
+            neg_infinity: vec![neg_infinity.into()],
+        }
+    }
+
+    /// Adds a spelling that is accepted for NaN when deserializing.
+    pub fn accept_nan(mut self, spelling: impl Into<String>) -> Self {
+        self.nan.push(spelling.into());
+        self
+    }
+
+    /// Adds a spelling that is accepted for positive infinity when deserializing.
+    pub fn accept_infinity(mut self, spelling: impl Into<String>) -> Self {
+        self.infinity.push(spelling.into());
+        self
+    }
+
+    /// Adds a spelling that is accepted for negative infinity when deserializing.
+    pub fn accept_neg_infinity(mut self, spelling: impl Into<String>) -> Self {
+        self.neg_infinity.push(spelling.into());
+        self
+    }
+
+    /// Returns the canonical spelling for a non-finite value.
+    pub(crate) fn spell(&self, v: f64) -> &str {
+        debug_assert!(!v.is_finite());
+
+        if v.is_nan() {
+            &self.nan[0]
+        } else if v.is_sign_positive() {
+            &self.infinity[0]
+        } else {
+            &self.neg_infinity[0]
+        }
+    }
+
+    /// Returns the non-finite value for an accepted spelling.
+    pub(crate) fn parse(&self, s: &str) -> Option<f64> {
+        if self.nan.iter().any(|val| val == s) {
+            Some(f64::NAN)
+        } else if self.infinity.iter().any(|val| val == s) {
+            Some(f64::INFINITY)
+        } else if self.neg_infinity.iter().any(|val| val == s) {
+            Some(f64::NEG_INFINITY)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for NonFiniteSpellings {
+    fn default() -> Self {
+        Self::new("nan", "inf", "-inf").accept_infinity("+inf")
+    }
+}
+
+/// Formats a finite float as a hexadecimal literal, like `0x1.8p+3`.
+///
+/// The output has exactly the bits of the value, so it reads back to the identical float.
+pub(crate) fn format_hex(v: f64) -> String {
+    debug_assert!(v.is_finite());
+
+    let bits = v.to_bits();
+    let sign = if v.is_sign_negative() { "-" } else { "" };
+    let biased = ((bits >> 52) & 0x7ff) as i32;
+    let mantissa = bits & ((1 << 52) - 1);
+
+    if biased == 0 && mantissa == 0 {
+        return format!("{sign}0x0p+0");
+    }
+
+    // Subnormals have no implicit leading one, and a fixed exponent.
+    let (lead, exp) = if biased == 0 {
+        (0, -1022)
+    } else {
+        (1, biased - 1023)
+    };
+
+    let fraction = format!("{:013x}", mantissa);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        format!("{sign}0x{lead}p{exp:+}")
+    } else {
+        format!("{sign}0x{lead}.{fraction}p{exp:+}")
+    }
+}
+
+/// Formats a finite float with the given number of significant digits.
+///
+/// Like `printf`'s `%g`, an exponent is used when it is less than -4, or at least
+/// the number of digits. Unlike `%g`, trailing zeros are kept.
+pub(crate) fn format_significant(v: f64, digits: u8) -> String {
+    debug_assert!(v.is_finite());
+
+    let digits = usize::from(digits.max(1));
+    let scientific = format!("{:.*e}", digits - 1, v);
+    let Some((mantissa, exp)) = scientific.split_once('e') else {
+        return scientific;
+    };
+    let exp: i32 = exp.parse().unwrap_or_default();
+
+    if exp < -4 || exp >= digits as i32 {
+        format!("{mantissa}e{exp}")
+    } else {
+        // Rounding to the same digit as the scientific form keeps the carry, e.g. `9.99` to `10`.
+        format!("{:.*}", (digits as i32 - 1 - exp) as usize, v)
+    }
+}
+
+/// Parses a hexadecimal float literal, like `0x1.8p3`.
+///
+/// Literals with more significant digits than fit into 64 bits are truncated.
+pub(crate) fn parse_hex(s: &str) -> Option<f64> {
+    let (negative, s) = match s.as_bytes().first()? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    let s = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+    let (digits, exp) = s.split_once(['p', 'P'])?;
+    let exp: i32 = exp.parse().ok()?;
+
+    let (int, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() {
+        return None;
+    }
+
+    let mut mantissa: u64 = 0;
+    let mut shift = exp;
+    for (i, c) in int.chars().chain(fraction.chars()).enumerate() {
+        let digit = c.to_digit(16)?;
+
+        if mantissa >> 60 == 0 {
+            mantissa = (mantissa << 4) | u64::from(digit);
+            if i >= int.len() {
+                shift -= 4;
+            }
+        } else if i < int.len() {
+            // Digits that don't fit anymore still count towards the magnitude.
+            shift += 4;
+        }
+    }
+
+    let value = scale(mantissa as f64, shift);
+    Some(if negative { -value } else { value })
+}
+
+// Multiplies by a power of two. The steps keep each factor representable.
+fn scale(mut value: f64, mut exp: i32) -> f64 {
+    while exp > 1000 {
+        value *= 2f64.powi(1000);
+        exp -= 1000;
+    }
+
+    while exp < -1000 {
+        value *= 2f64.powi(-1000);
+        exp += 1000;
+    }
+
+    value * 2f64.powi(exp)
+}
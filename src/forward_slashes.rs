@@ -0,0 +1,65 @@
+//! Serializes a path with forward slashes as separators, instead of escaped backslashes.
+//!
+//! Windows paths are written as `"scripts\\mods\\test"` by default. Some engines only accept
+//! forward slashes in paths, and reject escaped backslashes. With
+//! `#[serde(with = "serde_sjson::forward_slashes")]`, every backslash in the field is replaced
+//! by a forward slash before it is serialized. Other fields are not affected, so strings that
+//! need their backslashes keep them.
+//!
+//! The field can be any type that is a path, like `PathBuf` or `String`. It is deserialized
+//! as usual.
+//!
+//! ```
+//! use std::path::PathBuf;
+//!
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Mod {
+//!     #[serde(with = "serde_sjson::forward_slashes")]
+//!     script: PathBuf,
+//!     description: String,
+//! }
+//!
+//! let value = Mod {
+//!     script: PathBuf::from("scripts\\mods\\test\\new"),
+//!     description: String::from("Uses \\n for line breaks"),
+//! };
+//!
+//! let s = serde_sjson::to_string(&value).unwrap();
+//! assert_eq!(
+//!     s,
+//!     "script = scripts/mods/test/new\ndescription = \"Uses \\\\n for line breaks\"\n"
+//! );
+//! ```
+
+use std::path::Path;
+
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serializes a path, with backslashes replaced by forward slashes.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<Path>,
+    S: Serializer,
+{
+    let path = value.as_ref();
+    let Some(path) = path.to_str() else {
+        return Err(S::Error::custom(format!(
+            "path {} is not valid UTF-8",
+            path.display()
+        )));
+    };
+
+    serializer.serialize_str(&path.replace('\\', "/"))
+}
+
+/// Deserializes a path as it is written.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer)
+}
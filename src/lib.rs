@@ -65,12 +65,66 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Enums
+//!
+//! Unit variants are written as a plain string. Other variants, including `Ok` and `Err`
+//! of a `Result`, are an object with the variant name as the only key:
+//!
+//! ```text
+//! status = ready
+//! result = { Ok = 3 }
+//! error = { Err = "failed to load" }
+//! ```
+//!
+//! # Strings
+//!
+//! Strings can be written in three ways. Unquoted strings end at whitespace, `=`, `,`,
+//! or a bracket or brace, and at `:` when it is accepted as an assignment.
+//! Quoted strings resolve the escape sequences `\n`, `\t`, `\r`, `\"`, `\\` and `\uXXXX`.
+//! Triple-quoted strings may span lines, and their contents are taken verbatim, without
+//! any escape sequences. They end at the first `"""`, so they cannot contain one:
+//!
+//! ```text
+//! name = Marc
+//! greeting = "Hello,\n\"world\""
+//! script = """
+//! print("Hello, world")
+//! """
+//! ```
+//!
+//! # Features
+//!
+//! - `chrono`: Adds the `unix_timestamp` module, to write `chrono::DateTime<Utc>` as an
+//!   integer timestamp instead of an RFC 3339 string.
 
 mod de;
+pub mod double_option;
 mod error;
+mod float;
+pub mod forward_slashes;
 mod parser;
 mod ser;
+mod tokenizer;
+#[cfg(feature = "chrono")]
+pub mod unix_timestamp;
+mod validate;
+mod value;
 
-pub use de::{from_str, Deserializer};
-pub use error::{Error, Result};
-pub use ser::{to_string, to_vec, to_writer, Serializer};
+pub use de::{
+    from_reader, from_slice, from_str, from_str_multi, from_str_partial, from_str_projection,
+    from_str_until, from_str_with_doc_comments, from_str_with_len, from_str_with_spans,
+    ArraySeparator, Assignment, CollectionKind, Deserializer, RawValue, SourceSpan,
+    StreamDeserializer,
+};
+pub use error::{Error, ErrorKind, Result};
+pub use float::NonFiniteSpellings;
+pub use ser::{
+    serialized_size, to_slice, to_string, to_string_canonical, to_string_compact,
+    to_string_documented, to_string_pretty, to_vec, to_writer, to_writer_flushed, to_writer_pretty,
+    BytesEncoding, FloatFormat, Indent, IndentMode, NonFiniteMode, SerializeOptions, Serializer,
+    UnitFieldMode,
+};
+pub use tokenizer::{Token, Tokenizer};
+pub use validate::validate;
+pub use value::{Map, Value};
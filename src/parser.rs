@@ -1,14 +1,35 @@
+use std::borrow::Cow;
+
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_until};
-use nom::character::complete::{char, digit1, none_of, not_line_ending, one_of};
-use nom::combinator::{cut, eof, map, map_res, opt, recognize, value};
-use nom::multi::many1_count;
-use nom::number::complete::double;
+use nom::bytes::complete::{tag, tag_no_case, take_until, take_while};
+use nom::character::complete::{
+    char, digit0, digit1, hex_digit0, hex_digit1, not_line_ending, one_of, satisfy,
+};
+use nom::combinator::{cut, eof, map, map_opt, opt, peek, recognize, value};
+use nom::multi::{many0_count, many1_count};
 use nom::sequence::{delimited, preceded, terminated, tuple};
 use nom::{IResult, Slice};
 use nom_locate::LocatedSpan;
 
-pub(crate) type Span<'a> = LocatedSpan<&'a str>;
+use crate::float::{self, NonFiniteSpellings};
+
+/// Settings that change how the input is parsed.
+///
+/// They are carried along by the span, so that the parsers can stay plain functions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ParseOptions {
+    /// Whether Unicode whitespace is treated like ASCII whitespace.
+    pub unicode_whitespace: bool,
+    /// The maximum length of a string in bytes, before escape sequences are resolved.
+    pub max_string_length: Option<usize>,
+    /// Whether `:` may separate a key from its value, so that it ends unquoted strings.
+    pub colon_assignment: bool,
+    /// Whether hexadecimal float literals, like `0x1.8p3`, are read as floats.
+    pub hex_floats: bool,
+}
+
+/// The input to the parsers.
+pub(crate) type Span<'a> = LocatedSpan<&'a str, ParseOptions>;
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Token {
@@ -19,6 +40,8 @@ pub(crate) enum Token {
     Equals,
     Float(f64),
     Integer(i64),
+    // Only used for integers above `i64::MAX`.
+    UnsignedInteger(u64),
     Null,
     ObjectEnd,
     ObjectStart,
@@ -26,58 +49,211 @@ pub(crate) enum Token {
     String(String),
 }
 
-fn horizontal_whitespace(input: Span) -> IResult<Span, char> {
-    one_of(" \t")(input)
+/// A token, along with the input starting at it.
+///
+/// Errors about the token can be reported at its own line and column from this,
+/// rather than at wherever the input was before any whitespace or comments.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SpannedToken<'a> {
+    pub token: Token,
+    pub start: Span<'a>,
+}
+
+impl SpannedToken<'_> {
+    pub fn line(&self) -> u32 {
+        self.start.location_line()
+    }
+
+    pub fn column(&self) -> usize {
+        self.start.get_utf8_column()
+    }
+}
+
+fn is_whitespace(c: char, unicode: bool) -> bool {
+    matches!(c, ' ' | '\n' | '\r' | '\t') || (unicode && c.is_whitespace())
+}
+
+fn horizontal_whitespace(input: Span<'_>) -> IResult<Span<'_>, char> {
+    let unicode = input.extra.unicode_whitespace;
+    satisfy(move |c| c != '\n' && c != '\r' && is_whitespace(c, unicode))(input)
 }
 
-fn whitespace(input: Span) -> IResult<Span, char> {
-    one_of(" \n\r\t")(input)
+fn whitespace(input: Span<'_>) -> IResult<Span<'_>, char> {
+    let unicode = input.extra.unicode_whitespace;
+    satisfy(move |c| is_whitespace(c, unicode))(input)
 }
 
-fn null(input: Span) -> IResult<Span, ()> {
+// Checks that a number, `null` or a boolean ends here. Otherwise, like in `1foo` or `2024-03-21`,
+// it is only the start of an unquoted string.
+fn delimiter(input: Span<'_>) -> IResult<Span<'_>, ()> {
+    let colon = input.extra.colon_assignment;
+    let structural = satisfy(move |c| {
+        matches!(c, ',' | ';' | '=' | '"' | '{' | '}' | '[' | ']') || (colon && c == ':')
+    });
+
+    peek(alt((
+        value((), eof),
+        value((), whitespace),
+        value((), structural),
+        value((), tag("//")),
+        value((), tag("/*")),
+    )))(input)
+}
+
+fn null(input: Span<'_>) -> IResult<Span<'_>, ()> {
     value((), tag("null"))(input)
 }
 
-fn separator(input: Span) -> IResult<Span, &str> {
+fn separator(input: Span<'_>) -> IResult<Span<'_>, &str> {
     map(alt((tag(","), tag("\n"), tag("\r\n"))), |val: Span| {
         *val.fragment()
     })(input)
 }
 
-fn bool(input: Span) -> IResult<Span, bool> {
+fn bool(input: Span<'_>) -> IResult<Span<'_>, bool> {
     alt((value(true, tag("true")), value(false, tag("false"))))(input)
 }
 
-fn integer(input: Span) -> IResult<Span, i64> {
-    map_res(recognize(tuple((opt(char('-')), digit1))), |val: Span| {
-        val.fragment().parse::<i64>()
-    })(input)
+// A decimal or `0x` prefixed hexadecimal integer, with an optional sign. Without digits
+// after the prefix, only the `0` is matched, so callers have to check what follows.
+fn integer_literal(input: Span<'_>) -> IResult<Span<'_>, Span<'_>> {
+    recognize(tuple((
+        opt(one_of("+-")),
+        alt((preceded(tag_no_case("0x"), hex_digit1), digit1)),
+    )))(input)
 }
 
-fn float(input: Span) -> IResult<Span, f64> {
-    double(input)
+// Splits an integer literal into whether it is negative, its magnitude and the radix.
+fn integer_magnitude(val: &str) -> Option<(bool, u64)> {
+    let (negative, val) = match val.as_bytes().first()? {
+        b'-' => (true, &val[1..]),
+        b'+' => (false, &val[1..]),
+        _ => (false, val),
+    };
+    let magnitude = match val.get(..2) {
+        Some("0x" | "0X") => u64::from_str_radix(&val[2..], 16),
+        _ => val.parse::<u64>(),
+    };
+
+    magnitude.ok().map(|magnitude| (negative, magnitude))
 }
 
-fn identifier(input: Span) -> IResult<Span, &str> {
-    map(recognize(many1_count(none_of("\" \t\n=:"))), |val: Span| {
-        *val.fragment()
+fn integer(input: Span<'_>) -> IResult<Span<'_>, i64> {
+    map_opt(integer_literal, |val: Span| {
+        let (negative, magnitude) = integer_magnitude(val.fragment())?;
+        if negative {
+            i64::try_from(-i128::from(magnitude)).ok()
+        } else {
+            i64::try_from(magnitude).ok()
+        }
     })(input)
 }
 
-fn literal_string(input: Span) -> IResult<Span, &str> {
-    map(
-        delimited(tag("\"\"\""), take_until("\"\"\""), tag("\"\"\"")),
-        |val: Span| *val.fragment(),
+// Only reached for integers that `integer` rejects, so anything it matches is above `i64::MAX`.
+fn unsigned_integer(input: Span<'_>) -> IResult<Span<'_>, u64> {
+    map_opt(integer_literal, |val: Span| {
+        match integer_magnitude(val.fragment())? {
+            (false, magnitude) => Some(magnitude),
+            (true, _) => None,
+        }
+    })(input)
+}
+
+// A decimal float: an optional sign, digits with an optional `.` and fraction, and an
+// optional exponent. Either side of the `.` may be empty, as in `.5` or `5.`, but not both.
+// Integers are valid floats as well. Unlike nom's `double`, this doesn't accept spellings
+// like `NaN` or `inf`, so that only the configured `NonFiniteSpellings` are recognized.
+fn float(input: Span<'_>) -> IResult<Span<'_>, f64> {
+    let mantissa = alt((
+        recognize(tuple((digit1, opt(preceded(char('.'), digit0))))),
+        recognize(preceded(char('.'), digit1)),
+    ));
+    let exponent = tuple((one_of("eE"), opt(one_of("+-")), digit1));
+    let literal: IResult<Span<'_>, Span<'_>> =
+        recognize(tuple((opt(one_of("+-")), mantissa, opt(exponent))))(input);
+
+    match literal.map(|(rest, val)| (rest, val.fragment().parse::<f64>())) {
+        Ok((rest, Ok(val))) => Ok((rest, val)),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Float,
+        ))),
+    }
+}
+
+fn hex_float(input: Span<'_>) -> IResult<Span<'_>, f64> {
+    if !input.extra.hex_floats {
+        let err = nom::error::Error::new(input, nom::error::ErrorKind::Float);
+        return Err(nom::Err::Error(err));
+    }
+
+    // The binary exponent is required, so that hexadecimal integers aren't mistaken for floats.
+    map_opt(
+        recognize(tuple((
+            opt(one_of("+-")),
+            tag_no_case("0x"),
+            hex_digit1,
+            opt(preceded(char('.'), hex_digit0)),
+            one_of("pP"),
+            opt(one_of("+-")),
+            digit1,
+        ))),
+        |val: Span| float::parse_hex(val.fragment()),
     )(input)
 }
 
-fn string_content(input: Span) -> IResult<Span, &str> {
+// Fails fatally for a string that is longer than allowed, so that no other kind
+// of string is tried instead.
+fn string_too_long<T>(input: Span<'_>) -> IResult<Span<'_>, T> {
+    let err = nom::error::Error {
+        input,
+        code: nom::error::ErrorKind::TooLarge,
+    };
+    Err(nom::Err::Failure(err))
+}
+
+fn exceeds_max_length(input: Span<'_>, len: usize) -> bool {
+    input.extra.max_string_length.is_some_and(|max| len > max)
+}
+
+fn identifier(input: Span<'_>) -> IResult<Span<'_>, &str> {
+    let unicode = input.extra.unicode_whitespace;
+    let colon = input.extra.colon_assignment;
+    let (rest, val) = recognize(many1_count(satisfy(move |c| {
+        !(matches!(c, '"' | '=' | ',' | '{' | '}' | '[' | ']')
+            || (colon && c == ':')
+            || is_whitespace(c, unicode))
+    })))(input)?;
+
+    if exceeds_max_length(input, val.len()) {
+        return string_too_long(input);
+    }
+
+    Ok((rest, *val.fragment()))
+}
+
+// A `"""` delimited string, whose contents are taken verbatim. Like in the reference
+// implementation, there is no way to escape a `"""` inside it.
+fn literal_string(input: Span<'_>) -> IResult<Span<'_>, &str> {
+    let (rest, val) = delimited(tag("\"\"\""), take_until("\"\"\""), tag("\"\"\""))(input)?;
+
+    if exceeds_max_length(input, val.len()) {
+        return string_too_long(input);
+    }
+
+    Ok((rest, *val.fragment()))
+}
+
+fn string_content(input: Span<'_>) -> IResult<Span<'_>, &str> {
     let buf = input.fragment();
     let mut escaped = false;
-    let mut i = 0;
 
     for (j, ch) in buf.char_indices() {
-        i = j;
+        // Stop as soon as the limit is reached, rather than scanning to the end first.
+        if exceeds_max_length(input, j) {
+            return string_too_long(input);
+        }
+
         match ch {
             '\\' if !escaped => {
                 escaped = true;
@@ -97,39 +273,119 @@ fn string_content(input: Span) -> IResult<Span, &str> {
     }
 
     let err = nom::error::Error {
-        input: input.slice((i + 1)..),
+        input: input.slice(buf.len()..),
         code: nom::error::ErrorKind::Char,
     };
     Err(nom::Err::Failure(err))
 }
 
-fn delimited_string(input: Span) -> IResult<Span, &str> {
+fn delimited_string(input: Span<'_>) -> IResult<Span<'_>, &str> {
     preceded(char('"'), cut(terminated(string_content, char('"'))))(input)
 }
 
-fn string(input: Span) -> IResult<Span, &str> {
-    alt((identifier, literal_string, delimited_string))(input)
+// Resolves the escape sequences in delimited strings, or returns `None` for an invalid
+// `\u` escape. A backslash before any other character is kept as it is.
+fn unescape(val: &str) -> Option<Cow<'_, str>> {
+    if !val.contains('\\') {
+        return Some(Cow::Borrowed(val));
+    }
+
+    let mut unescaped = String::with_capacity(val.len());
+    let mut chars = val.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('u') => unescaped.push(unicode_escape(&mut chars)?),
+            Some(c @ ('"' | '\\')) => unescaped.push(c),
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    Some(Cow::Owned(unescaped))
+}
+
+// Decodes the code point of a `\u` escape, after the `\u`. Characters outside the
+// Basic Multilingual Plane are written as a UTF-16 surrogate pair, like `\uD83D\uDE00`.
+fn unicode_escape(chars: &mut std::str::Chars<'_>) -> Option<char> {
+    let code = match hex4(chars)? {
+        high @ 0xD800..=0xDBFF => {
+            if chars.next()? != '\\' || chars.next()? != 'u' {
+                return None;
+            }
+
+            match hex4(chars)? {
+                low @ 0xDC00..=0xDFFF => 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00),
+                _ => return None,
+            }
+        }
+        code => code,
+    };
+
+    // This rejects a low surrogate without a high one.
+    char::from_u32(code)
+}
+
+fn hex4(chars: &mut std::str::Chars<'_>) -> Option<u32> {
+    (0..4).try_fold(0, |code, _| Some(code * 16 + chars.next()?.to_digit(16)?))
 }
 
-fn line_comment(input: Span) -> IResult<Span, &str> {
+fn unescaped_string(input: Span<'_>) -> IResult<Span<'_>, Cow<'_, str>> {
+    let (rest, val) = delimited_string(input)?;
+
+    match unescape(val) {
+        Some(val) => Ok((rest, val)),
+        None => {
+            let err = nom::error::Error {
+                input,
+                code: nom::error::ErrorKind::EscapedTransform,
+            };
+            Err(nom::Err::Failure(err))
+        }
+    }
+}
+
+// Unquoted and literal strings are taken as they are, only delimited strings are unescaped.
+fn string(input: Span<'_>) -> IResult<Span<'_>, Cow<'_, str>> {
+    alt((
+        map(identifier, Cow::Borrowed),
+        map(literal_string, Cow::Borrowed),
+        unescaped_string,
+    ))(input)
+}
+
+fn line_comment(input: Span<'_>) -> IResult<Span<'_>, &str> {
     map(
         preceded(tag("//"), alt((not_line_ending, eof))),
         |val: Span| *val.fragment(),
     )(input)
 }
 
-fn block_comment(input: Span) -> IResult<Span, &str> {
+fn block_comment(input: Span<'_>) -> IResult<Span<'_>, &str> {
+    // Once a comment has been opened, a missing end is fatal. Otherwise the opening `/*`
+    // would be parsed as part of an unquoted string instead.
     map(
-        delimited(tag("/*"), take_until("*/"), tag("*/")),
+        preceded(tag("/*"), cut(terminated(take_until("*/"), tag("*/")))),
         |val: Span| *val.fragment(),
     )(input)
 }
 
-fn comment(input: Span) -> IResult<Span, &str> {
+fn comment(input: Span<'_>) -> IResult<Span<'_>, &str> {
     alt((line_comment, block_comment))(input)
 }
 
-fn optional(input: Span) -> IResult<Span, ()> {
+fn optional(input: Span<'_>) -> IResult<Span<'_>, ()> {
     let whitespace = value((), whitespace);
     let comment = value((), comment);
     let empty = value((), tag(""));
@@ -138,66 +394,214 @@ fn optional(input: Span) -> IResult<Span, ()> {
     alt((content, empty))(input)
 }
 
-pub(crate) fn parse_next_token(input: Span) -> IResult<Span, Token> {
-    preceded(
-        opt(optional),
-        alt((
-            // Order is important here.
-            // Certain valid strings like "null", "true" or "false" need to be
-            // matched to their special value.
-            // Integer-like numbers need to be matched to that, but are valid floats, too.
-            value(Token::Eof, eof),
-            value(Token::Separator, separator),
-            value(Token::ObjectStart, tag("{")),
-            value(Token::ObjectEnd, tag("}")),
-            value(Token::ArrayStart, tag("[")),
-            value(Token::ArrayEnd, tag("]")),
-            value(Token::Equals, tag("=")),
-            value(Token::Null, null),
-            map(bool, Token::Boolean),
-            map(integer, Token::Integer),
-            map(float, Token::Float),
-            map(string, |val| Token::String(val.to_string())),
-        )),
-    )(input)
+pub(crate) fn parse_next_token(input: Span<'_>) -> IResult<Span<'_>, SpannedToken<'_>> {
+    let (start, _) = opt(optional)(input)?;
+    let (rest, token) = alt((
+        // Order is important here.
+        // Certain valid strings like "null", "true" or "false" need to be
+        // matched to their special value.
+        // Integer-like numbers need to be matched to that, but are valid floats, too.
+        value(Token::Eof, eof),
+        value(Token::Separator, separator),
+        value(Token::ObjectStart, tag("{")),
+        value(Token::ObjectEnd, tag("}")),
+        value(Token::ArrayStart, tag("[")),
+        value(Token::ArrayEnd, tag("]")),
+        value(Token::Equals, tag("=")),
+        value(Token::Null, terminated(null, delimiter)),
+        map(terminated(bool, delimiter), Token::Boolean),
+        // Hexadecimal floats start like an integer `0`, so they have to be matched first.
+        map(terminated(hex_float, delimiter), Token::Float),
+        // An integer followed by a fraction or exponent is the start of a float.
+        map(terminated(integer, delimiter), Token::Integer),
+        map(
+            terminated(unsigned_integer, delimiter),
+            Token::UnsignedInteger,
+        ),
+        map(terminated(float, delimiter), Token::Float),
+        map(string, |val| Token::String(val.into_owned())),
+    ))(start)?;
+
+    Ok((rest, SpannedToken { token, start }))
+}
+
+/// Checks whether a string would be read back as the same string if written unquoted,
+/// rather than as a different value, like `null`, `true` or `1.5`, or as several tokens.
+pub(crate) fn is_plain_string(
+    val: &str,
+    options: ParseOptions,
+    non_finite: &NonFiniteSpellings,
+) -> bool {
+    if non_finite.parse(val).is_some() {
+        return false;
+    }
+
+    matches!(
+        parse_next_token(Span::new_extra(val, options)),
+        Ok((rest, SpannedToken { token: Token::String(string), .. }))
+            if rest.fragment().is_empty() && string == val
+    )
+}
+
+/// Checks whether parsing failed because a block comment is never closed.
+pub(crate) fn is_unterminated_comment(err: &nom::Err<nom::error::Error<Span<'_>>>) -> bool {
+    // `block_comment` is the only parser that fails fatally in `take_until`.
+    matches!(err, nom::Err::Failure(err) if err.code == nom::error::ErrorKind::TakeUntil)
+}
+
+/// Returns `input` from the `/*` that opened a block comment, if parsing failed
+/// because the comment is never closed.
+pub(crate) fn unterminated_comment<'a>(
+    input: Span<'a>,
+    err: &nom::Err<nom::error::Error<Span<'_>>>,
+) -> Option<Span<'a>> {
+    let nom::Err::Failure(failure) = err else {
+        return None;
+    };
+    if !is_unterminated_comment(err) {
+        return None;
+    }
+
+    // `take_until` fails right after the `/*`.
+    let start = (failure.input.location_offset() - "/*".len()).checked_sub(input.location_offset());
+    Some(start.map_or(input, |start| input.slice(start..)))
+}
+
+/// Checks whether parsing failed because of a malformed `\u` escape sequence.
+pub(crate) fn is_invalid_unicode_escape(err: &nom::Err<nom::error::Error<Span<'_>>>) -> bool {
+    matches!(err, nom::Err::Failure(err) if err.code == nom::error::ErrorKind::EscapedTransform)
+}
+
+/// Checks whether parsing failed because a string is longer than allowed.
+pub(crate) fn is_string_too_long(err: &nom::Err<nom::error::Error<Span<'_>>>) -> bool {
+    matches!(err, nom::Err::Failure(err) if err.code == nom::error::ErrorKind::TooLarge)
+}
+
+/// Returns the block of comments directly before the next token.
+///
+/// A blank line ends a block, so comments that are separated from the next token
+/// by one are not included.
+pub(crate) fn parse_leading_comments(input: Span<'_>) -> Vec<&str> {
+    let mut comments = Vec::new();
+    let mut input = input;
+
+    loop {
+        let Ok((rest, space)) = take_while::<_, _, nom::error::Error<_>>(|c| {
+            is_whitespace(c, input.extra.unicode_whitespace)
+        })(input) else {
+            break;
+        };
+
+        if space.fragment().matches('\n').count() > 1 {
+            comments.clear();
+        }
+
+        match comment(rest) {
+            Ok((rest, text)) => {
+                comments.push(text);
+                input = rest;
+            }
+            Err(_) => break,
+        }
+    }
+
+    comments
+}
+
+/// Returns every comment before the next token, with the byte offset it starts at.
+///
+/// The comments include their delimiters.
+pub(crate) fn parse_comments(input: Span<'_>) -> Vec<(usize, &str)> {
+    let mut comments = Vec::new();
+    let mut input = input;
+
+    loop {
+        let Ok((rest, _)) = take_while::<_, _, nom::error::Error<_>>(|c| {
+            is_whitespace(c, input.extra.unicode_whitespace)
+        })(input) else {
+            break;
+        };
+
+        match recognize(comment)(rest) {
+            Ok((next, text)) => {
+                comments.push((rest.location_offset(), *text.fragment()));
+                input = next;
+            }
+            Err(_) => break,
+        }
+    }
+
+    comments
 }
 
-pub(crate) fn parse_trailing_characters(input: Span) -> IResult<Span, ()> {
+pub(crate) fn parse_trailing_characters(input: Span<'_>) -> IResult<Span<'_>, ()> {
     value((), optional)(input)
 }
 
-pub(crate) fn parse_null(input: Span) -> IResult<Span, Token> {
+pub(crate) fn parse_null(input: Span<'_>) -> IResult<Span<'_>, Token> {
     preceded(optional, value(Token::Null, null))(input)
 }
 
-pub(crate) fn parse_separator(input: Span) -> IResult<Span, Token> {
-    preceded(
-        opt(horizontal_whitespace),
-        value(Token::Separator, separator),
-    )(input)
-}
+/// Parses the spaces and comments on the same line between a value and its separator.
+pub(crate) fn parse_horizontal_space(input: Span<'_>) -> IResult<Span<'_>, ()> {
+    let horizontal_whitespace = value((), horizontal_whitespace);
+    let comment = value((), comment);
 
-pub(crate) fn parse_bool(input: Span) -> IResult<Span, Token> {
-    preceded(optional, map(bool, Token::Boolean))(input)
+    value((), many0_count(alt((horizontal_whitespace, comment))))(input)
 }
 
-pub(crate) fn parse_integer(input: Span) -> IResult<Span, Token> {
-    preceded(optional, map(integer, Token::Integer))(input)
+/// Parses the separator after a value, which may be preceded by spaces and comments
+/// on the same line.
+pub(crate) fn parse_separator(input: Span<'_>) -> IResult<Span<'_>, Token> {
+    preceded(parse_horizontal_space, value(Token::Separator, separator))(input)
 }
 
-pub(crate) fn parse_float(input: Span) -> IResult<Span, Token> {
-    preceded(optional, map(float, Token::Float))(input)
+pub(crate) fn parse_bool(input: Span<'_>) -> IResult<Span<'_>, Token> {
+    preceded(optional, map(bool, Token::Boolean))(input)
 }
 
-pub(crate) fn parse_identifier(input: Span) -> IResult<Span, Token> {
+pub(crate) fn parse_integer(input: Span<'_>) -> IResult<Span<'_>, Token> {
+    // The delimiter rejects a malformed literal like `0x` or `0xg`, rather than
+    // reading the `0` and leaving the rest.
     preceded(
         optional,
-        map(identifier, |val| Token::String(val.to_string())),
+        terminated(
+            alt((
+                map(integer, Token::Integer),
+                map(unsigned_integer, Token::UnsignedInteger),
+            )),
+            delimiter,
+        ),
     )(input)
 }
 
-pub(crate) fn parse_string(input: Span) -> IResult<Span, Token> {
-    preceded(optional, map(string, |val| Token::String(val.to_string())))(input)
+/// Recognizes digits that denote an integer, regardless of whether they fit any integer type.
+///
+/// Digits that continue, like the fraction or exponent of a float, or the `p` exponent
+/// of a hexadecimal float, are not an integer.
+pub(crate) fn parse_integer_literal(input: Span<'_>) -> IResult<Span<'_>, Span<'_>> {
+    terminated(integer_literal, delimiter)(input)
+}
+
+pub(crate) fn parse_float(input: Span<'_>) -> IResult<Span<'_>, Token> {
+    preceded(optional, map(float, Token::Float))(input)
+}
+
+pub(crate) fn parse_hex_float(input: Span<'_>) -> IResult<Span<'_>, Token> {
+    preceded(optional, map(hex_float, Token::Float))(input)
+}
+
+pub(crate) fn parse_identifier(input: Span<'_>) -> IResult<Span<'_>, &str> {
+    preceded(optional, identifier)(input)
+}
+
+pub(crate) fn parse_string(input: Span<'_>) -> IResult<Span<'_>, Token> {
+    map(parse_str, |val| Token::String(val.into_owned()))(input)
+}
+
+/// Parses a string, borrowing it from the input unless escape sequences had to be resolved.
+pub(crate) fn parse_str(input: Span<'_>) -> IResult<Span<'_>, Cow<'_, str>> {
+    preceded(optional, string)(input)
 }
 
 #[cfg(test)]
@@ -230,8 +634,12 @@ mod test {
     }
 
     fn check_parse_result<S: AsRef<str>, T: AsRef<[Token]>>(input: S, tokens: T) {
+        check_parse_result_with(Span::from(input.as_ref()), tokens)
+    }
+
+    fn check_parse_result_with<T: AsRef<[Token]>>(input: Span<'_>, tokens: T) {
         let tokens = tokens.as_ref();
-        let mut remaining = Span::from(input.as_ref());
+        let mut remaining = input;
         let mut i = 0;
 
         loop {
@@ -242,10 +650,10 @@ mod test {
             let (span, token) =
                 super::parse_next_token(remaining).expect("failed to parse next token");
 
-            assert_eq!(Some(&token), tokens.get(i));
+            assert_eq!(Some(&token.token), tokens.get(i));
 
             remaining = span;
-            i = i + 1;
+            i += 1;
         }
 
         assert_eq!(
@@ -271,28 +679,211 @@ mod test {
         assert_ok!("\n\t// foo\n\t/* foo\n\tbar */\n", optional, "", ());
     }
 
+    #[test]
+    fn parse_unicode_whitespace() {
+        // By default, other whitespace is part of an unquoted string.
+        assert_ok!("foo\u{a0}bar", identifier, "", "foo\u{a0}bar");
+        assert_err!("\u{a0}", whitespace, ErrorKind::Satisfy);
+
+        let options = ParseOptions {
+            unicode_whitespace: true,
+            ..Default::default()
+        };
+        let input = Span::new_extra("\u{a0}\u{3000}foo\u{2003}= 1\u{202f}\nbar", options);
+        let (input, token) = parse_string(input).unwrap();
+        assert_eq!(token, Token::String(String::from("foo")));
+        let (input, token) = parse_next_token(input).unwrap();
+        assert_eq!(token.token, Token::Equals);
+        assert_eq!((token.line(), token.column()), (1, 7));
+        let (input, token) = super::parse_integer(input).unwrap();
+        assert_eq!(token, Token::Integer(1));
+        let (input, token) = parse_separator(input).unwrap();
+        assert_eq!(token, Token::Separator);
+        assert_eq!(*input.fragment(), "bar");
+    }
+
     #[test]
     fn parse_integer() {
         assert_ok!("3", integer, "", 3);
         assert_ok!("12345", integer, "", 12345);
         assert_ok!("-12345", integer, "", -12345);
         assert_ok!("12345   ", integer, "   ", 12345);
+        assert_ok!("+0", integer, "", 0);
+        assert_ok!("+42", integer, "", 42);
+        assert_ok!("+0x10", integer, "", 16);
 
         assert_err!("   12345", integer, ErrorKind::Digit);
 
         assert_ok!("    12345", parse_integer, "", Token::Integer(12345));
         assert_ok!("\n12345", parse_integer, "", Token::Integer(12345));
         assert_ok!("\t12345", parse_integer, "", Token::Integer(12345));
+        assert_ok!(
+            "18446744073709551615",
+            parse_integer,
+            "",
+            Token::UnsignedInteger(u64::MAX)
+        );
+        assert!(super::parse_integer(Span::from("18446744073709551616")).is_err());
+        assert_ok!(
+            "+18446744073709551615",
+            parse_integer,
+            "",
+            Token::UnsignedInteger(u64::MAX)
+        );
+        assert!(super::parse_integer(Span::from("+-1")).is_err());
     }
 
     #[test]
+    fn parse_hex_integer() {
+        assert_ok!("0x0", integer, "", 0);
+        assert_ok!("0xFF", integer, "", 255);
+        assert_ok!("0Xff", integer, "", 255);
+        assert_ok!("-0x10", integer, "", -16);
+        assert_ok!("0x7FFFFFFFFFFFFFFF", integer, "", i64::MAX);
+        assert_ok!("-0x8000000000000000", integer, "", i64::MIN);
+        assert_ok!("0xFF, 1", integer, ", 1", 255);
+
+        assert_ok!(
+            "0xFFFFFFFFFFFFFFFF",
+            parse_integer,
+            "",
+            Token::UnsignedInteger(u64::MAX)
+        );
+        assert!(super::parse_integer(Span::from("0x10000000000000000")).is_err());
+        assert!(super::parse_integer(Span::from("-0x8000000000000001")).is_err());
+
+        // Without digits after the prefix, it's not an integer, but still a valid string.
+        for input in ["0x", "0xg", "-0x", "0x1g"] {
+            assert!(super::parse_integer(Span::from(input)).is_err());
+            let (rest, token) = parse_next_token(Span::from(input)).unwrap();
+            assert_eq!(token.token, Token::String(String::from(input)));
+            assert!(rest.fragment().is_empty());
+        }
+
+        // Hexadecimal floats are only recognized when enabled, and then take precedence.
+        let (_, token) = parse_next_token(Span::from("0x1p3")).unwrap();
+        assert_eq!(token.token, Token::String(String::from("0x1p3")));
+
+        let options = ParseOptions {
+            hex_floats: true,
+            ..Default::default()
+        };
+        check_parse_result_with(
+            Span::new_extra("[0xFF, 0x1p3]", options),
+            [
+                Token::ArrayStart,
+                Token::Integer(255),
+                Token::Separator,
+                Token::Float(8.0),
+                Token::ArrayEnd,
+            ],
+        );
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
     fn parse_float() {
         assert_ok!("3", float, "", 3.0);
         assert_ok!("3.0", float, "", 3.0);
         assert_ok!("3.1415", float, "", 3.1415);
         assert_ok!("-123.456789", float, "", -123.456789);
+        assert_ok!("+1.5", float, "", 1.5);
+        assert_ok!(".5", float, "", 0.5);
+        assert_ok!("-.5", float, "", -0.5);
+        assert_ok!("5.", float, "", 5.0);
+        assert_ok!("5.e2", float, "", 500.0);
+        assert_ok!(".5e1", float, "", 5.0);
+        assert_ok!("5., 1", float, ", 1", 5.0);
+        assert_err!(".", float, ErrorKind::Float);
+        assert_err!("-.", float, ErrorKind::Float);
+        assert_err!(".e1", float, ErrorKind::Float);
         assert_err!("   1.23", float, ErrorKind::Float);
         assert_ok!("1.23   ", float, "   ", 1.23);
+
+        // Non-finite values only have the spellings configured in the deserializer.
+        for input in ["nan", "NaN", "inf", "-inf", "INF", "infinity", "Infinity"] {
+            assert_err!(input, float, ErrorKind::Float);
+        }
+        assert_ok!("1e", float, "e", 1.0);
+        assert_ok!("1e+", float, "e+", 1.0);
+    }
+
+    #[test]
+    fn parse_number_tokens() {
+        check_parse_result(
+            "[1.5, 2, -3e2, 4E-1, 5]",
+            [
+                Token::ArrayStart,
+                Token::Float(1.5),
+                Token::Separator,
+                Token::Integer(2),
+                Token::Separator,
+                Token::Float(-300.0),
+                Token::Separator,
+                Token::Float(0.4),
+                Token::Separator,
+                Token::Integer(5),
+                Token::ArrayEnd,
+            ],
+        );
+
+        // Only a delimiter ends a number, `null` or a boolean. Otherwise, it starts a string.
+        check_parse_result(
+            "[1foo, 1e 2024-03-21 0.1.0,true/* */nullable //\n1;]",
+            [
+                Token::ArrayStart,
+                Token::String(String::from("1foo")),
+                Token::Separator,
+                Token::String(String::from("1e")),
+                Token::String(String::from("2024-03-21")),
+                Token::String(String::from("0.1.0")),
+                Token::Separator,
+                Token::Boolean(true),
+                Token::String(String::from("nullable")),
+                Token::Integer(1),
+                Token::String(String::from(";")),
+                Token::ArrayEnd,
+            ],
+        );
+
+        // A trailing `.` makes an integer a float.
+        check_parse_result(
+            "[.5, 5., 5]",
+            [
+                Token::ArrayStart,
+                Token::Float(0.5),
+                Token::Separator,
+                Token::Float(5.0),
+                Token::Separator,
+                Token::Integer(5),
+                Token::ArrayEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn parse_hex_float() {
+        let options = ParseOptions {
+            hex_floats: true,
+            ..Default::default()
+        };
+        let parse = |input| {
+            hex_float(Span::new_extra(input, options))
+                .map(|(span, val)| (*span.fragment(), val))
+                .map_err(|err| err.map(|err| err.code))
+        };
+
+        assert_eq!(parse("0x1p0"), Ok(("", 1.0)));
+        assert_eq!(parse("0x1.8p3"), Ok(("", 12.0)));
+        assert_eq!(parse("0X1.8P+3"), Ok(("", 12.0)));
+        assert_eq!(parse("-0x1.8p-1"), Ok(("", -0.75)));
+        assert_eq!(parse("0x1.8p3 "), Ok((" ", 12.0)));
+
+        assert!(parse("0x18").is_err());
+        assert_eq!(parse("1.8p3"), Err(Err::Error(ErrorKind::Tag)));
+
+        // Without opting in, nothing is a hexadecimal float.
+        assert_err!("0x1p0", hex_float, ErrorKind::Float);
     }
 
     #[test]
@@ -307,9 +898,38 @@ mod test {
         assert_ok!("foo-bar", identifier, "", "foo-bar");
         assert_ok!("foo/bar", identifier, "", "foo/bar");
         assert_ok!("foo\"", identifier, "\"", "foo");
+        assert_ok!("foo\r\n", identifier, "\r\n", "foo");
+        assert_ok!("foo,bar", identifier, ",bar", "foo");
+        assert_ok!("foo}", identifier, "}", "foo");
+        assert_ok!("foo]", identifier, "]", "foo");
+        assert_ok!("foo{", identifier, "{", "foo");
+        assert_ok!("foo[1]", identifier, "[1]", "foo");
 
         assert_err!("\"foo", identifier, ErrorKind::Many1Count);
         assert_err!("\"foo\"", identifier, ErrorKind::Many1Count);
+        assert_err!(",foo", identifier, ErrorKind::Many1Count);
+    }
+
+    #[test]
+    fn parse_tight_collections() {
+        check_parse_result(
+            "{a=[b,c],d=e}",
+            [
+                Token::ObjectStart,
+                Token::String(String::from("a")),
+                Token::Equals,
+                Token::ArrayStart,
+                Token::String(String::from("b")),
+                Token::Separator,
+                Token::String(String::from("c")),
+                Token::ArrayEnd,
+                Token::Separator,
+                Token::String(String::from("d")),
+                Token::Equals,
+                Token::String(String::from("e")),
+                Token::ObjectEnd,
+            ],
+        );
     }
 
     #[test]
@@ -331,7 +951,18 @@ mod test {
             assert_eq!(
                 delimited_string(input),
                 Err(Err::Failure(Error::new(
-                    unsafe { Span::new_from_raw_offset(4, 1, "", ()) },
+                    unsafe { Span::new_from_raw_offset(4, 1, "", ParseOptions::default()) },
+                    ErrorKind::Char
+                )))
+            );
+        }
+
+        // The error is at the end of the input, even after a multi-byte character.
+        for (input, offset) in [("\"", 1), ("\"fooä", 6)] {
+            assert_eq!(
+                delimited_string(Span::from(input)),
+                Err(Err::Failure(Error::new(
+                    unsafe { Span::new_from_raw_offset(offset, 1, "", ParseOptions::default()) },
                     ErrorKind::Char
                 )))
             );
@@ -342,7 +973,7 @@ mod test {
             assert_eq!(
                 delimited_string(input),
                 Err(Err::Failure(Error::new(
-                    unsafe { Span::new_from_raw_offset(4, 1, "\nbar\"", ()) },
+                    unsafe { Span::new_from_raw_offset(4, 1, "\nbar\"", ParseOptions::default()) },
                     ErrorKind::Char
                 )))
             );
@@ -377,7 +1008,7 @@ Escape sequences, like \n and \t, are parsed literally.
             assert_eq!(
                 literal_string(input),
                 Err(Err::Error(Error::new(
-                    unsafe { Span::new_from_raw_offset(3, 1, "\"\"", ()) },
+                    unsafe { Span::new_from_raw_offset(3, 1, "\"\"", ParseOptions::default()) },
                     ErrorKind::TakeUntil
                 )))
             );
@@ -394,6 +1025,9 @@ Escape sequences, like \n and \t, are parsed literally.
     fn parse_block_comment() {
         assert_ok!("/* foo */", block_comment, "", " foo ");
         assert_ok!("/*\n\tfoo\nbar\n*/", block_comment, "", "\n\tfoo\nbar\n");
+
+        let err = block_comment(Span::from("/* foo")).unwrap_err();
+        assert!(is_unterminated_comment(&err));
     }
 
     // Regression test for #1 (https://git.sclu1034.dev/lucas/serde_sjson/issues/1)
@@ -434,9 +1068,42 @@ packages = [
     // Regression test for #2
     #[test]
     fn parse_windows_path() {
-        let text = "C:\\Users\\public\\test.txt";
+        // Backslashes that don't start an escape sequence are kept.
+        let text = "C:\\Users\\public\\data.txt";
         let sjson = format!(r#""{}""#, text);
         check_parse_result(sjson, [Token::String(String::from(text))]);
+
+        // `\t` is a tab, so the serializer writes escaped backslashes.
+        let sjson = r#""C:\\Users\\public\\test.txt""#;
+        check_parse_result(
+            sjson,
+            [Token::String(String::from("C:\\Users\\public\\test.txt"))],
+        );
+    }
+
+    #[test]
+    fn parse_separator_after_comments() {
+        let tests = [
+            (",", ""),
+            ("\n", ""),
+            ("\r\nfoo", "foo"),
+            ("  \t,", ""),
+            (" // foo\nbar", "bar"),
+            ("// foo\r\n", ""),
+            (" /* foo */ ,", ""),
+            ("/* foo */ /* bar */\n", ""),
+            ("/* foo\nbar */\n", ""),
+        ];
+
+        for (input, remain) in tests {
+            let (span, token) = parse_separator(Span::from(input)).unwrap();
+            assert_eq!(token, Token::Separator, "{input:?}");
+            assert_eq!(*span.fragment(), remain, "{input:?}");
+        }
+
+        assert!(parse_separator(Span::from(" // foo")).is_err());
+        assert!(parse_separator(Span::from(" /* foo */ bar")).is_err());
+        assert!(parse_separator(Span::from(" /* foo")).is_err());
     }
 
     // Regression test for #10
@@ -1,17 +1,256 @@
+use std::collections::HashMap;
 use std::io;
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use serde::ser::SerializeSeq as _;
 use serde::Serialize;
 
 use crate::error::{Error, ErrorCode, Result};
+use crate::float::{self, NonFiniteSpellings};
+use crate::parser;
+
+/// Determines how the serializer handles non-finite floating point numbers.
+///
+/// The mode applies to every `f32` and `f64`, whether it is a field, an array element,
+/// or inside an `Option`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum NonFiniteMode {
+    /// Fail with an [`ErrorKind::NonFiniteFloat`](crate::ErrorKind::NonFiniteFloat) error.
+    #[default]
+    Error,
+    /// Write `null`, like `serde_json` does.
+    ///
+    /// This loses the value. It reads back as `None` into an `Option<f64>`,
+    /// but fails to deserialize into an `f64`.
+    Null,
+    /// Write the canonical spelling of the value.
+    String(NonFiniteSpellings),
+}
+
+/// Determines how finite floating point numbers are formatted.
+///
+/// Only [`FloatFormat::Shortest`] is guaranteed to read back as the identical value.
+/// The other formats round, so they may not round-trip exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// Write the shortest representation that reads back as the same value.
+    #[default]
+    Shortest,
+    /// Write the given number of decimals, like `1.500`.
+    Fixed(u8),
+    /// Write the given number of significant digits, like `1.23` or `1.23e-7`.
+    ///
+    /// Very small or large values use an exponent, as `printf`'s `%g` does.
+    /// At least one digit is written.
+    Significant(u8),
+}
+
+/// Determines how byte arrays are represented.
+///
+/// Values serialized as bytes, e.g. through `serde_bytes`, and sequences of `u8`,
+/// like `Vec<u8>` or `[u8; N]`, are written the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BytesEncoding {
+    /// Write an array of integers.
+    #[default]
+    Array,
+    /// Write a base64 string, using the standard alphabet with padding.
+    ///
+    /// An empty sequence is still written as an empty array, since its element type is unknown.
+    Base64,
+    /// Write values serialized as bytes as they are. They must already be valid SJSON.
+    ///
+    /// Sequences of `u8` are written as arrays of integers.
+    Raw,
+}
+
+// The elements of an open sequence, while it is not yet known whether it only
+// contains bytes.
+#[derive(Default)]
+struct ByteSeq {
+    bytes: Vec<u8>,
+    elements: Vec<Vec<u8>>,
+    only_bytes: bool,
+}
+
+const SPACES: &[u8] = &[b' '; 32];
+const TABS: &[u8] = &[b'\t'; 32];
+
+/// Determines the characters that each level of nesting is indented with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    /// Indent by the given number of spaces.
+    Spaces(usize),
+    /// Indent by one tab.
+    ///
+    /// With [`IndentMode::KeyRelative`], contents are lined up with spaces, and are
+    /// indented by one column relative to the opening delimiter.
+    Tab,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self::Spaces(2)
+    }
+}
+
+impl Indent {
+    // The number of columns a level of nesting is indented by.
+    fn width(self) -> usize {
+        match self {
+            Self::Spaces(n) => n,
+            Self::Tab => 1,
+        }
+    }
+}
+
+/// Determines how the contents of nested objects and arrays are indented.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IndentMode {
+    /// Indent by the configured [`Indent`] per level of nesting.
+    #[default]
+    Fixed,
+    /// Indent by the configured [`Indent`] relative to the opening delimiter, which
+    /// lines up the closing delimiter with the opening one:
+    ///
+    /// ```text
+    /// settings = {
+    ///              width = 1
+    ///            }
+    /// ```
+    KeyRelative,
+}
+
+/// Determines how struct fields with a unit value, like `()` or `None`, are serialized.
+///
+/// Just like `serialize_unit` itself, this does not distinguish between `()` and `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitFieldMode {
+    /// Write the field as `value = null`.
+    #[default]
+    Null,
+    /// Leave the field out entirely.
+    ///
+    /// An `Option` field reads back as `None`, but a `()` field needs `#[serde(default)]`
+    /// to be deserialized again.
+    Omit,
+    /// Write only the key, as `value`.
+    ///
+    /// This can only be read back with [`Deserializer::with_bare_keys`].
+    ///
+    /// [`Deserializer::with_bare_keys`]: crate::Deserializer::with_bare_keys
+    BareKey,
+}
+
+/// Settings that affect the output, for [`to_string_pretty`] and [`to_writer_pretty`].
+///
+/// The default produces the same output as [`to_string`].
+///
+/// Only the indentation, the order of keys and compact output can be set here.
+/// The other settings, like [`Serializer::with_hex_floats`], are set on a [`Serializer`],
+/// and are kept by [`Serializer::with_options`].
+#[derive(Clone, Debug, Default)]
+pub struct SerializeOptions {
+    non_finite: NonFiniteMode,
+    sort_keys: bool,
+    // Format floats in their shortest round-trippable form.
+    shortest_floats: bool,
+    unit_fields: UnitFieldMode,
+    skip_none: bool,
+    blank_line_between_top_level: bool,
+    top_level_braces: bool,
+    bytes_encoding: BytesEncoding,
+    hex_floats: bool,
+    float_format: FloatFormat,
+    indent: Indent,
+    indent_mode: IndentMode,
+    trailing_comma_arrays: bool,
+    trailing_comma_objects: bool,
+    // The line width after which string values are moved below their key.
+    string_line_width: Option<usize>,
+    escape_non_ascii: bool,
+    multiline_strings: bool,
+    compact: bool,
+}
+
+impl SerializeOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the characters that each level of nesting is indented with.
+    ///
+    /// See [`Serializer::with_indent`].
+    pub fn with_indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Sets how the contents of nested objects and arrays are indented.
+    ///
+    /// See [`Serializer::with_indent_mode`].
+    pub fn with_indent_mode(mut self, mode: IndentMode) -> Self {
+        self.indent_mode = mode;
+        self
+    }
 
-// TODO: Make configurable
-const INDENT: [u8; 2] = [0x20, 0x20];
+    /// Sets whether map keys are written in sorted order.
+    ///
+    /// See [`Serializer::with_sort_keys`].
+    pub fn with_sort_keys(mut self, enabled: bool) -> Self {
+        self.sort_keys = enabled;
+        self
+    }
+
+    /// Sets whether the output is written on a single line.
+    ///
+    /// See [`Serializer::with_compact`].
+    pub fn with_compact(mut self, enabled: bool) -> Self {
+        self.compact = enabled;
+        self
+    }
+}
 
 /// A container for serializing Rust values into SJSON.
+///
+/// When serializing maps, every call to `serialize_key` must be followed by
+/// a call to `serialize_value`. A key without a value results in an error.
 pub struct Serializer<W> {
     // The current indentation level
     level: usize,
+    // The column the contents of each open object or array start at.
+    indents: Vec<usize>,
+    // The column the next write starts at.
+    column: usize,
     writer: W,
+    options: SerializeOptions,
+    // Whether the string currently being serialized is a map key.
+    is_key: bool,
+    // Whether a map key has been written that is still waiting for its value.
+    pending_key: bool,
+    // The serialized keys and values of each open map, when keys are sorted.
+    sorted_entries: Vec<Vec<(Vec<u8>, Vec<u8>)>>,
+    // Whether a unit value has been written, to tell it apart from a string `"null"`.
+    wrote_unit: bool,
+    // Whether the unit value came from `serialize_none`.
+    wrote_none: bool,
+    // A map key that is only written once its value turns out not to be `None`.
+    deferred_key: Option<Vec<u8>>,
+    // Whether a string value has been written, to tell it apart from other scalars.
+    wrote_str: bool,
+    // Whether a field of the top-level struct has been written.
+    wrote_top_level_field: bool,
+    // The most recent byte written, to tell sequences of bytes apart from other sequences.
+    wrote_byte: Option<u8>,
+    // The sequences currently being collected, when bytes are encoded as base64.
+    byte_seqs: Vec<ByteSeq>,
+    // The comments to write before top-level fields, by field name.
+    field_docs: HashMap<String, String>,
+    // Whether nothing has been written yet in the innermost object or array,
+    // to separate the entries in compact output.
+    container_empty: bool,
 }
 
 /// Serializes a value into a generic `io::Write`.
@@ -21,10 +260,47 @@ where
     W: io::Write,
     T: Serialize,
 {
-    let mut serializer = Serializer::new(writer);
+    to_writer_pretty(writer, value, &SerializeOptions::default())
+}
+
+/// Serializes a value into a generic `io::Write`, formatted according to `options`.
+#[inline]
+pub fn to_writer_pretty<T, W>(writer: &mut W, value: &T, options: &SerializeOptions) -> Result<()>
+where
+    W: io::Write,
+    T: Serialize,
+{
+    let mut serializer = Serializer::new(writer).with_options(options.clone());
     value.serialize(&mut serializer)
 }
 
+/// Serializes a value into a generic `io::Write`, flushes the writer, and returns
+/// the number of bytes written.
+///
+/// Unlike [`to_writer`], this is safe to use with a buffered writer like
+/// `BufWriter<File>` without flushing it separately.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// let mut writer = std::io::BufWriter::new(Vec::new());
+/// let value = HashMap::from([("width", 800)]);
+/// let len = serde_sjson::to_writer_flushed(&mut writer, &value).unwrap();
+///
+/// assert_eq!(len, 12);
+/// assert_eq!(writer.get_ref(), b"width = 800\n");
+/// ```
+pub fn to_writer_flushed<T, W>(writer: &mut W, value: &T) -> Result<usize>
+where
+    W: io::Write,
+    T: Serialize,
+{
+    let mut counter = ByteCounter { writer, count: 0 };
+    to_writer(&mut counter, value)?;
+    io::Write::flush(&mut counter)?;
+    Ok(counter.count)
+}
+
 /// Serializes a value into a byte vector.
 #[inline]
 pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
@@ -36,19 +312,188 @@ where
     Ok(vec)
 }
 
+/// Serializes a value into a fixed-size buffer, and returns the number of bytes written.
+///
+/// If the output doesn't fit, this fails with an error instead of allocating more space.
+/// The contents of the buffer are unspecified in that case.
+#[inline]
+pub fn to_slice<T>(buf: &mut [u8], value: &T) -> Result<usize>
+where
+    T: Serialize,
+{
+    let capacity = buf.len();
+    let mut writer = buf;
+    to_writer(&mut writer, value).map_err(Error::or_buffer_full)?;
+    Ok(capacity - writer.len())
+}
+
+/// Returns the number of bytes that serializing a value would produce, without
+/// writing the output anywhere.
+///
+/// This can be used to size a buffer for [`to_slice`] up front.
+#[inline]
+pub fn serialized_size<T>(value: &T) -> Result<usize>
+where
+    T: Serialize,
+{
+    let mut counter = ByteCounter {
+        writer: io::sink(),
+        count: 0,
+    };
+    to_writer(&mut counter, value)?;
+    Ok(counter.count)
+}
+
 /// Serializes a value into a string.
 #[inline]
 pub fn to_string<T>(value: &T) -> Result<String>
 where
     T: Serialize,
 {
-    let vec = to_vec(value)?;
-    let string = if cfg!(debug_assertions) {
+    to_string_pretty(value, &SerializeOptions::default())
+}
+
+/// Serializes a value into a string, formatted according to `options`.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use serde_sjson::{Indent, SerializeOptions};
+///
+/// let value = HashMap::from([("size", vec![800, 600])]);
+/// let options = SerializeOptions::new().with_indent(Indent::Spaces(4));
+/// let s = serde_sjson::to_string_pretty(&value, &options).unwrap();
+///
+/// assert_eq!(s, "size = [\n    800\n    600\n]\n");
+/// ```
+#[inline]
+pub fn to_string_pretty<T>(value: &T, options: &SerializeOptions) -> Result<String>
+where
+    T: Serialize,
+{
+    let mut vec = Vec::with_capacity(128);
+    to_writer_pretty(&mut vec, value, options)?;
+    Ok(into_string(vec))
+}
+
+/// Serializes a value into a string, with a comment before each top-level field
+/// that has an entry in `docs`.
+///
+/// Each line of a comment is written as a `//` line comment.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Config {
+///     width: u32,
+///     height: u32,
+/// }
+///
+/// let docs = HashMap::from([(String::from("height"), String::from("In pixels."))]);
+/// let config = Config { width: 800, height: 600 };
+/// let s = serde_sjson::to_string_documented(&config, &docs).unwrap();
+///
+/// assert_eq!(s, "width = 800\n// In pixels.\nheight = 600\n");
+/// ```
+#[inline]
+pub fn to_string_documented<T>(value: &T, docs: &HashMap<String, String>) -> Result<String>
+where
+    T: Serialize,
+{
+    let mut vec = Vec::with_capacity(128);
+    let mut serializer = Serializer::new(&mut vec);
+    serializer.field_docs = docs.clone();
+    value.serialize(&mut serializer)?;
+    Ok(into_string(vec))
+}
+
+/// Serializes a value into a string in canonical form.
+///
+/// Equal values always produce byte-identical output, which makes it suitable
+/// for hashing or content addressing:
+///
+/// - map keys are sorted
+/// - floats are written in their shortest form that reads back to the same value,
+///   always including a `.0` or exponent
+/// - indentation, line endings and quoting are fixed
+#[inline]
+pub fn to_string_canonical<T>(value: &T) -> Result<String>
+where
+    T: Serialize,
+{
+    let mut vec = Vec::with_capacity(128);
+    let mut serializer = Serializer::new(&mut vec);
+    serializer.options.sort_keys = true;
+    serializer.options.shortest_floats = true;
+    value.serialize(&mut serializer)?;
+    Ok(into_string(vec))
+}
+
+/// Serializes a value into a string on a single line.
+///
+/// See [`Serializer::with_compact`].
+///
+/// ```
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Window {
+///     title: String,
+///     size: Vec<u32>,
+/// }
+///
+/// let window = Window { title: String::from("main"), size: vec![800, 600] };
+/// let s = serde_sjson::to_string_compact(&window).unwrap();
+///
+/// assert_eq!(s, "title = \"main\", size = [800, 600]");
+/// ```
+#[inline]
+pub fn to_string_compact<T>(value: &T) -> Result<String>
+where
+    T: Serialize,
+{
+    let mut vec = Vec::with_capacity(128);
+    let mut serializer = Serializer::new(&mut vec).with_compact(true);
+    value.serialize(&mut serializer)?;
+    Ok(into_string(vec))
+}
+
+// A writer that counts the bytes written through it.
+struct ByteCounter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W> io::Write for ByteCounter<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        self.count += len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// The number of characters in UTF-8 encoded bytes.
+fn width(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| b & 0xc0 != 0x80).count()
+}
+
+#[inline]
+fn into_string(vec: Vec<u8>) -> String {
+    if cfg!(debug_assertions) {
         String::from_utf8(vec).expect("We do not emit invalid UTF-8")
     } else {
         unsafe { String::from_utf8_unchecked(vec) }
-    };
-    Ok(string)
+    }
 }
 
 impl<W> Serializer<W>
@@ -57,18 +502,733 @@ where
 {
     /// Creates a new `Serializer`.
     pub fn new(writer: W) -> Self {
-        Self { level: 0, writer }
+        Self {
+            level: 0,
+            indents: Vec::new(),
+            column: 0,
+            writer,
+            options: SerializeOptions::default(),
+            is_key: false,
+            pending_key: false,
+            sorted_entries: Vec::new(),
+            wrote_unit: false,
+            wrote_none: false,
+            deferred_key: None,
+            wrote_str: false,
+            wrote_top_level_field: false,
+            wrote_byte: None,
+            byte_seqs: Vec::new(),
+            field_docs: HashMap::new(),
+            container_empty: false,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly may produce invalid SJSON.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Consumes the serializer and returns the underlying writer.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Window {
+    ///     width: u32,
+    /// }
+    ///
+    /// let mut ser = serde_sjson::Serializer::new(Vec::new());
+    /// Window { width: 800 }.serialize(&mut ser).unwrap();
+    ///
+    /// assert_eq!(ser.into_inner(), b"width = 800\n");
+    /// ```
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Applies the indentation, key order and compact output from `options` at once.
+    ///
+    /// Every other setting, like [`Serializer::with_hex_floats`], is kept as it is.
+    pub fn with_options(mut self, options: SerializeOptions) -> Self {
+        self.options.indent = options.indent;
+        self.options.indent_mode = options.indent_mode;
+        self.options.sort_keys = options.sort_keys;
+        self.options.compact = options.compact;
+        self
+    }
+
+    /// Sets how non-finite floating point numbers are serialized.
+    pub fn with_non_finite_mode(mut self, mode: NonFiniteMode) -> Self {
+        self.options.non_finite = mode;
+        self
+    }
+
+    /// Sets how struct fields with a unit value are serialized.
+    pub fn with_unit_field_mode(mut self, mode: UnitFieldMode) -> Self {
+        self.options.unit_fields = mode;
+        self
+    }
+
+    /// Sets whether struct fields and map entries whose value is `None` are left out.
+    ///
+    /// Unlike [`UnitFieldMode::Omit`], this only applies to `None`, not to `()`,
+    /// and also to the entries of maps. Explicit `null`s written by
+    /// [`double_option`](crate::double_option) are kept.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Config {
+    ///     name: String,
+    ///     version: Option<String>,
+    /// }
+    ///
+    /// let config = Config { name: String::from("app"), version: None };
+    ///
+    /// let mut buf = Vec::new();
+    /// let mut ser = serde_sjson::Serializer::new(&mut buf).with_skip_none(true);
+    /// config.serialize(&mut ser).unwrap();
+    ///
+    /// assert_eq!(buf, b"name = app\n");
+    /// ```
+    pub fn with_skip_none(mut self, enabled: bool) -> Self {
+        self.options.skip_none = enabled;
+        self
+    }
+
+    /// Enables writing floats as hexadecimal literals, like `0x1.8p+3`.
+    ///
+    /// These represent the exact bits of a value. They can be read back with
+    /// [`Deserializer::with_hex_floats`].
+    ///
+    /// [`Deserializer::with_hex_floats`]: crate::Deserializer::with_hex_floats
+    pub fn with_hex_floats(mut self) -> Self {
+        self.options.hex_floats = true;
+        self
+    }
+
+    /// Sets how finite floating point numbers are formatted.
+    ///
+    /// This has no effect when [`Serializer::with_hex_floats`] is enabled.
+    pub fn with_float_format(mut self, format: FloatFormat) -> Self {
+        self.options.float_format = format;
+        self
+    }
+
+    /// Sets the characters that each level of nesting is indented with.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_sjson::Indent;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Window {
+    ///     size: Vec<u32>,
+    /// }
+    ///
+    /// let mut buf = Vec::new();
+    /// let mut ser = serde_sjson::Serializer::new(&mut buf).with_indent(Indent::Spaces(4));
+    /// Window { size: vec![800] }.serialize(&mut ser).unwrap();
+    ///
+    /// assert_eq!(buf, b"size = [\n    800\n]\n");
+    /// ```
+    pub fn with_indent(mut self, indent: Indent) -> Self {
+        self.options.indent = indent;
+        self
+    }
+
+    /// Indents each level of nesting with one tab.
+    ///
+    /// This is the same as `with_indent(Indent::Tab)`.
+    pub fn with_indent_tabs(self) -> Self {
+        self.with_indent(Indent::Tab)
+    }
+
+    /// Sets how the contents of nested objects and arrays are indented.
+    pub fn with_indent_mode(mut self, mode: IndentMode) -> Self {
+        self.options.indent_mode = mode;
+        self
+    }
+
+    /// Sets whether map keys are written in sorted order.
+    ///
+    /// The entries of each map are collected until it ends, and then written ordered by
+    /// their serialized keys. This makes the output of maps without a fixed order, like
+    /// `HashMap`, reproducible. Struct fields keep their declaration order.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use serde::Serialize;
+    ///
+    /// let value = HashMap::from([("width", 800), ("height", 600)]);
+    ///
+    /// let mut buf = Vec::new();
+    /// let mut ser = serde_sjson::Serializer::new(&mut buf).with_sort_keys(true);
+    /// value.serialize(&mut ser).unwrap();
+    ///
+    /// assert_eq!(buf, b"height = 600\nwidth = 800\n");
+    /// ```
+    pub fn with_sort_keys(mut self, enabled: bool) -> Self {
+        self.options.sort_keys = enabled;
+        self
+    }
+
+    /// Sets how byte arrays are represented.
+    pub fn with_bytes_encoding(mut self, encoding: BytesEncoding) -> Self {
+        self.options.bytes_encoding = encoding;
+        self
+    }
+
+    /// Sets whether a blank line is written between the fields of the top-level struct.
+    ///
+    /// Nested structs are not affected.
+    pub fn with_blank_line_between_top_level(mut self, enabled: bool) -> Self {
+        self.options.blank_line_between_top_level = enabled;
+        self
+    }
+
+    /// Sets whether the top-level object is wrapped in braces, like a nested one.
+    ///
+    /// By default, the fields of the top-level object are written without braces, as is
+    /// idiomatic for SJSON. With braces, the output is also a single object for consumers
+    /// that expect one. The [`Deserializer`](crate::Deserializer) reads both forms.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Window {
+    ///     width: u32,
+    /// }
+    ///
+    /// let mut buf = Vec::new();
+    /// let mut ser = serde_sjson::Serializer::new(&mut buf).with_top_level_braces(true);
+    /// Window { width: 800 }.serialize(&mut ser).unwrap();
+    ///
+    /// assert_eq!(buf, b"{\n  width = 800\n}\n");
+    /// ```
+    pub fn with_top_level_braces(mut self, enabled: bool) -> Self {
+        self.options.top_level_braces = enabled;
+        self
+    }
+
+    /// Sets whether every element of an array is followed by a comma, including the last one.
+    pub fn with_trailing_comma_arrays(mut self, enabled: bool) -> Self {
+        self.options.trailing_comma_arrays = enabled;
+        self
+    }
+
+    /// Sets whether every entry of an object is followed by a comma, including the last one.
+    ///
+    /// The top-level object has no closing delimiter, so its fields are not affected,
+    /// unless it is wrapped in braces with [`Serializer::with_top_level_braces`].
+    pub fn with_trailing_comma_objects(mut self, enabled: bool) -> Self {
+        self.options.trailing_comma_objects = enabled;
+        self
+    }
+
+    /// Moves string values onto their own line below their key, when they would
+    /// otherwise make the line longer than `width` characters:
+    ///
+    /// ```text
+    /// description =
+    ///   "A string that is too long to fit on the same line as its key"
+    /// ```
+    ///
+    /// SJSON has no line continuations or string concatenation, so strings are never
+    /// split. A string that is longer than `width` on its own still exceeds it.
+    /// Only values of object entries are moved, array elements are always on their own line.
+    pub fn with_string_line_width(mut self, width: usize) -> Self {
+        self.options.string_line_width = Some(width);
+        self
+    }
+
+    /// Sets whether characters outside of ASCII are written as `\uXXXX` escapes,
+    /// so that the output is pure ASCII.
+    ///
+    /// Strings containing them are quoted, and characters outside the Basic Multilingual
+    /// Plane are split into a UTF-16 surrogate pair, like `"\ud83d\ude00"`.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Order {
+    ///     drink: String,
+    /// }
+    ///
+    /// let mut buf = Vec::new();
+    /// let mut ser = serde_sjson::Serializer::new(&mut buf).with_escape_non_ascii(true);
+    /// Order { drink: String::from("café") }.serialize(&mut ser).unwrap();
+    ///
+    /// assert_eq!(buf, b"drink = \"caf\\u00e9\"\n");
+    /// ```
+    pub fn with_escape_non_ascii(mut self, enabled: bool) -> Self {
+        self.options.escape_non_ascii = enabled;
+        self
+    }
+
+    /// Sets whether strings containing line breaks are written as triple-quoted strings,
+    /// which take their contents verbatim, instead of escaping the line breaks as `\n`:
+    ///
+    /// ```text
+    /// script = """print("Hello")
+    /// print("world")"""
+    /// ```
+    ///
+    /// Strings that can't be written this way, because they contain `"""` or end with `"`,
+    /// are still escaped. So are strings that need escaping for
+    /// [`Serializer::with_escape_non_ascii`].
+    pub fn with_multiline_strings(mut self, enabled: bool) -> Self {
+        self.options.multiline_strings = enabled;
+        self
+    }
+
+    /// Sets whether the output is written on a single line, without indentation.
+    ///
+    /// Entries are separated by `, `, like `a = 1, b = [1, 2], c = { d = "e" }`.
+    /// String values are always quoted, so that they end before the following `,`.
+    /// Options that add line breaks, like [`Serializer::with_multiline_strings`]
+    /// or trailing commas, have no effect.
+    pub fn with_compact(mut self, enabled: bool) -> Self {
+        self.options.compact = enabled;
+        self
+    }
+
+    // Creates a serializer that writes into a buffer, at the current indentation level
+    // and with the same options.
+    fn fork(&self) -> Serializer<Vec<u8>> {
+        let mut serializer = Serializer::new(Vec::new());
+        serializer.level = self.level;
+        serializer.indents = self.indents.clone();
+        serializer.column = self.column;
+        serializer.options = self.options.clone();
+        serializer.container_empty = self.container_empty;
+        serializer
+    }
+
+    fn open_object(&mut self) -> Result<()> {
+        if self.level > 0 || self.options.top_level_braces {
+            self.open(b"{")
+        } else {
+            // The top-level object has no delimiters, so there is no line break to
+            // write before its first entry.
+            self.level += 1;
+            self.indents.push(0);
+            self.container_empty = self.options.compact;
+            Ok(())
+        }
+    }
+
+    // Closes an object that was opened with `open_object`.
+    fn close_object(&mut self) -> Result<()> {
+        if self.level > 1 {
+            self.close(b"}")
+        } else if self.options.top_level_braces {
+            self.close(b"}")?;
+            if self.options.compact {
+                Ok(())
+            } else {
+                self.write(b"\n")
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    // Writes an opening delimiter, and indents the following contents.
+    fn open(&mut self, delimiter: &[u8]) -> Result<()> {
+        let indent = match self.options.indent_mode {
+            IndentMode::Fixed => self.indent(),
+            IndentMode::KeyRelative => self.column,
+        };
+
+        if self.options.compact && delimiter == b"{" {
+            self.write(b"{ ")?;
+        } else {
+            self.write(delimiter)?;
+        }
+        // The line break after the delimiter is only written along with the first entry,
+        // so that empty objects and arrays stay on one line.
+        self.container_empty = true;
+
+        self.level += 1;
+        self.indents.push(indent + self.options.indent.width());
+        Ok(())
+    }
+
+    // Writes a closing delimiter, lined up with the opening one.
+    fn close(&mut self, delimiter: &[u8]) -> Result<()> {
+        self.level -= 1;
+
+        if self.options.compact {
+            self.indents.pop();
+            if delimiter == b"}" && !self.container_empty {
+                self.write(b" ")?;
+            }
+            // The container itself is an entry of its parent.
+            self.container_empty = false;
+            return self.write(delimiter);
+        }
+
+        let width = self.options.indent.width();
+        let indent = self
+            .indents
+            .pop()
+            .map_or(0, |indent| indent.saturating_sub(width));
+
+        if std::mem::replace(&mut self.container_empty, false) {
+            return match delimiter {
+                b"}" => self.write(b" }"),
+                _ => self.write(delimiter),
+            };
+        }
+
+        self.write_indent(indent)?;
+        self.write(delimiter)
     }
 
     #[inline]
     fn write(&mut self, bytes: impl AsRef<[u8]>) -> Result<()> {
-        self.writer.write_all(bytes.as_ref()).map_err(Error::from)
+        let bytes = bytes.as_ref();
+
+        match bytes.iter().rposition(|&b| b == b'\n') {
+            Some(i) => self.column = width(&bytes[i + 1..]),
+            None => self.column += width(bytes),
+        }
+
+        self.writer.write_all(bytes).map_err(Error::from)
+    }
+
+    // The column the contents of the innermost object or array start at.
+    #[inline]
+    fn indent(&self) -> usize {
+        self.indents.last().copied().unwrap_or_default()
     }
 
+    // Starts an entry of an object or array.
     #[inline]
     fn add_indent(&mut self) -> Result<()> {
-        for _ in 0..self.level.saturating_sub(1) {
-            self.write(INDENT)?;
+        if self.options.compact {
+            if !std::mem::replace(&mut self.container_empty, false) {
+                self.write(b", ")?;
+            }
+            return Ok(());
+        }
+
+        if std::mem::replace(&mut self.container_empty, false) {
+            self.write(b"\n")?;
+        }
+        self.write_indent(self.indent())
+    }
+
+    // Writes the whitespace up to the given column at the start of a line.
+    fn write_indent(&mut self, column: usize) -> Result<()> {
+        // Each tab is one level of nesting. Contents lined up with a delimiter can
+        // start at any column, so they always use spaces.
+        let fill = if self.options.indent == Indent::Tab
+            && self.options.indent_mode == IndentMode::Fixed
+        {
+            TABS
+        } else {
+            SPACES
+        };
+
+        // Written in chunks, rather than allocating a string for each line.
+        let mut remaining = column;
+        while remaining > 0 {
+            let len = remaining.min(fill.len());
+            self.write(&fill[..len])?;
+            remaining -= len;
+        }
+
+        Ok(())
+    }
+
+    fn begin_field(&mut self, key: &'static str) -> Result<()> {
+        if self.level == 1 && self.options.blank_line_between_top_level && !self.options.compact {
+            if self.wrote_top_level_field {
+                self.write(b"\n")?;
+            }
+            self.wrote_top_level_field = true;
+        }
+
+        if let Some(doc) = self.field_docs.get(key).filter(|_| self.level == 1) {
+            let mut comment = String::new();
+            for line in doc.lines() {
+                comment.push_str(format!("// {}", line).trim_end());
+                comment.push('\n');
+            }
+            self.write(comment)?;
+        }
+
+        self.add_indent()?;
+        self.write_string(key, false)
+    }
+
+    // Writes the `=` after a key, followed by the value.
+    fn write_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.options.string_line_width.is_none() || self.options.compact {
+            self.write(b" = ")?;
+            return value.serialize(&mut *self);
+        }
+
+        // We only know whether the value is a string once it has been serialized.
+        let mut serializer = self.fork();
+        serializer.column = self.column + " = ".len();
+        value.serialize(&mut serializer)?;
+        self.write_forked_value(serializer)
+    }
+
+    // Writes the `=` after a key, followed by a value that was serialized into a fork.
+    fn write_forked_value(&mut self, serializer: Serializer<Vec<u8>>) -> Result<()> {
+        let is_str = serializer.wrote_str && !serializer.writer.contains(&b'\n');
+        let too_long = self
+            .options
+            .string_line_width
+            .is_some_and(|max| self.column + " = ".len() + width(&serializer.writer) > max);
+
+        if is_str && too_long {
+            self.write(b" =\n")?;
+            self.write_indent(self.indent() + self.options.indent.width())?;
+        } else {
+            self.write(b" = ")?;
+        }
+
+        self.write(serializer.writer)
+    }
+
+    // Ends the line of an entry, with a comma if those are enabled for the container.
+    fn end_entry(&mut self, in_array: bool) -> Result<()> {
+        // Compact entries are separated when the next one starts instead.
+        if self.options.compact {
+            return Ok(());
+        }
+
+        let comma = if in_array {
+            self.options.trailing_comma_arrays
+        } else {
+            self.options.trailing_comma_objects && (self.level > 1 || self.options.top_level_braces)
+        };
+
+        if comma {
+            self.write(b",\n")
+        } else {
+            self.write(b"\n")
+        }
+    }
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.options.unit_fields == UnitFieldMode::Null && !self.options.skip_none {
+            self.begin_field(key)?;
+            self.write_value(value)?;
+            return self.end_entry(false);
+        }
+
+        // We only know whether the value is a unit once it has been serialized.
+        let mut key_serializer = self.fork();
+        key_serializer.write_string(key, false)?;
+
+        let mut serializer = self.fork();
+        serializer.column = self.indent() + width(&key_serializer.writer) + " = ".len();
+        value.serialize(&mut serializer)?;
+        let is_unit = serializer.wrote_unit && serializer.writer == b"null";
+        let is_none = serializer.wrote_none && is_unit;
+
+        if is_none && self.options.skip_none {
+            return Ok(());
+        }
+
+        if is_unit && self.options.unit_fields == UnitFieldMode::Omit {
+            return Ok(());
+        }
+
+        self.begin_field(key)?;
+
+        if !is_unit || self.options.unit_fields == UnitFieldMode::Null {
+            self.write_forked_value(serializer)?;
+        }
+
+        self.end_entry(false)
+    }
+
+    fn write_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.byte_seqs.is_empty() {
+            self.add_indent()?;
+            value.serialize(&mut *self)?;
+            return self.end_entry(true);
+        }
+
+        let mut serializer = self.fork();
+        serializer.column = self.indent();
+        value.serialize(&mut serializer)?;
+
+        let byte = serializer
+            .wrote_byte
+            .filter(|byte| serializer.writer == byte.to_string().as_bytes());
+
+        let seq = self
+            .byte_seqs
+            .last_mut()
+            .expect("serialize_seq pushes a sequence");
+        match byte {
+            Some(byte) => seq.bytes.push(byte),
+            None => seq.only_bytes = false,
+        }
+        seq.elements.push(serializer.writer);
+
+        Ok(())
+    }
+
+    fn end_seq(&mut self) -> Result<()> {
+        if let Some(seq) = self.byte_seqs.pop() {
+            // Nothing has been written since the sequence started, so the contents
+            // can be indented again from the same column.
+            self.level -= 1;
+            self.indents.pop();
+            self.container_empty = false;
+
+            if seq.only_bytes && !seq.elements.is_empty() {
+                return serde::ser::Serializer::serialize_str(self, &BASE64.encode(seq.bytes));
+            }
+
+            self.open(b"[")?;
+            for element in seq.elements {
+                self.add_indent()?;
+                self.write(element)?;
+                self.end_entry(true)?;
+            }
+        }
+
+        self.close(b"]")
+    }
+
+    // Integers never need quoting, so they are formatted on the stack and written directly,
+    // rather than allocating a string for each one.
+    fn write_integer(&mut self, v: impl std::fmt::Display) -> Result<()> {
+        self.ensure_top_level_struct()?;
+
+        // Long enough for `i64::MIN` and `u64::MAX`.
+        let mut buf = [0u8; 20];
+        let mut cursor = io::Cursor::new(&mut buf[..]);
+        io::Write::write_fmt(&mut cursor, format_args!("{}", v))?;
+
+        let len = cursor.position() as usize;
+        self.write(&buf[..len])
+    }
+
+    // Like `write_integer`, but through `write_string`, which may move long values
+    // onto their own line.
+    fn write_float(&mut self, args: std::fmt::Arguments) -> Result<()> {
+        // Long enough for `-f64::MAX` with 255 decimals, which is longer than
+        // the smallest subnormal written in full.
+        let mut buf = [0u8; 600];
+        let mut cursor = io::Cursor::new(&mut buf[..]);
+        io::Write::write_fmt(&mut cursor, args)?;
+
+        let len = cursor.position() as usize;
+        let v = std::str::from_utf8(&buf[..len]).expect("Formatted floats are ASCII");
+        self.write_string(v, false)
+    }
+
+    // Writes a string, quoted and escaped if necessary.
+    // Strings that are valid unquoted are only quoted if `force_quotes` is set.
+    fn write_string(&mut self, v: &str, force_quotes: bool) -> Result<()> {
+        self.ensure_top_level_struct()?;
+
+        // A key containing any of these would break the `key = value` structure
+        // when read back, even if quoted.
+        if self.is_key && v.contains(['\n', '\r', '=', '{', '}', '[', ']']) {
+            return Err(Error::new(ErrorCode::InvalidMapKey, 0, 0, None));
+        }
+
+        // A trailing `"` would be read as part of the closing `"""`.
+        let multiline = self.options.multiline_strings
+            && !self.options.compact
+            && v.contains('\n')
+            && !v.contains("\"\"\"")
+            && !v.ends_with('"')
+            && (v.is_ascii() || !self.options.escape_non_ascii);
+
+        if multiline {
+            self.write(b"\"\"\"")?;
+            self.write(v)?;
+            return self.write(b"\"\"\"");
+        }
+
+        // Unquoted, a leading `//` or `/*` would be read as the start of a comment,
+        // and `,`, `{`, `}`, `[` or `]` would end the string early.
+        let needs_quotes = v.is_empty()
+            || v.contains([
+                ' ', '\n', '\r', '\t', '=', '\'', '"', '\\', ':', ',', '{', '}', '[', ']',
+            ])
+            || v.starts_with("//")
+            || v.starts_with("/*")
+            || force_quotes
+            || (self.options.escape_non_ascii && !v.is_ascii());
+
+        if needs_quotes {
+            self.write(b"\"")?;
+
+            // Since we've added a layer of quotes, we now need to escape
+            // certain characters.
+            for c in v.chars() {
+                match c {
+                    '\t' => {
+                        self.write(b"\\")?;
+                        self.write(b"t")?;
+                    }
+                    '\n' => {
+                        self.write(b"\\")?;
+                        self.write(b"n")?;
+                    }
+                    '\r' => {
+                        self.write(b"\\")?;
+                        self.write(b"r")?;
+                    }
+                    '"' => {
+                        self.write(b"\\")?;
+                        self.write(b"\"")?;
+                    }
+                    '\\' => {
+                        self.write(b"\\")?;
+                        self.write(b"\\")?;
+                    }
+                    c if self.options.escape_non_ascii && !c.is_ascii() => {
+                        let mut buf = [0; 2];
+                        for unit in c.encode_utf16(&mut buf) {
+                            self.write(format!("\\u{:04x}", unit))?;
+                        }
+                    }
+                    c => {
+                        let mut buf = [0; 4];
+                        self.write(c.encode_utf8(&mut buf).as_bytes())?;
+                    }
+                };
+            }
+
+            self.write(b"\"")?;
+        } else {
+            self.write(v.as_bytes())?;
         }
 
         Ok(())
@@ -84,7 +1244,7 @@ where
     }
 }
 
-impl<'a, W> serde::ser::Serializer for &'a mut Serializer<W>
+impl<W> serde::ser::Serializer for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -118,11 +1278,11 @@ where
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
-        self.ensure_top_level_struct()?;
-        self.serialize_str(&format!("{}", v))
+        self.write_integer(v)
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        self.wrote_byte = Some(v);
         self.serialize_u64(v.into())
     }
 
@@ -135,8 +1295,7 @@ where
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        self.ensure_top_level_struct()?;
-        self.serialize_str(&format!("{}", v))
+        self.write_integer(v)
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
@@ -146,79 +1305,88 @@ where
     fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
         self.ensure_top_level_struct()?;
         if !v.is_finite() {
-            return Err(Error::new(ErrorCode::NonFiniteFloat, 0, 0, None));
+            return match &self.options.non_finite {
+                NonFiniteMode::Error => Err(Error::new(ErrorCode::NonFiniteFloat, 0, 0, None)),
+                NonFiniteMode::Null => self.write(b"null"),
+                NonFiniteMode::String(spellings) => {
+                    let spelling = spellings.spell(v).to_string();
+                    self.write_string(&spelling, false)
+                }
+            };
+        }
+
+        if self.options.hex_floats {
+            return self.write_string(&float::format_hex(v), false);
         }
 
-        self.serialize_str(&format!("{}", v))
+        match self.options.float_format {
+            FloatFormat::Shortest if self.options.shortest_floats => {
+                let mut buf = ryu::Buffer::new();
+                self.write_string(buf.format_finite(v), false)
+            }
+            FloatFormat::Shortest => self.write_float(format_args!("{}", v)),
+            FloatFormat::Fixed(decimals) => {
+                self.write_float(format_args!("{:.*}", usize::from(decimals), v))
+            }
+            FloatFormat::Significant(digits) => {
+                self.write_string(&float::format_significant(v, digits), false)
+            }
+        }
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        // A character needs the same quoting and escaping as a string.
         let mut buf = [0; 4];
-        self.serialize_bytes(v.encode_utf8(&mut buf).as_bytes())
+        self.serialize_str(v.encode_utf8(&mut buf))
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        self.ensure_top_level_struct()?;
-
-        let needs_quotes =
-            v.is_empty() || v.contains([' ', '\n', '\r', '\t', '=', '\'', '"', '\\', ':']);
-
-        if needs_quotes {
-            self.write(b"\"")?;
-
-            // Since we've added a layer of quotes, we now need to escape
-            // certain characters.
-            for c in v.chars() {
-                match c {
-                    '\t' => {
-                        self.write(b"\\")?;
-                        self.write(b"t")?;
-                    }
-                    '\n' => {
-                        self.write(b"\\")?;
-                        self.write(b"n")?;
-                    }
-                    '\r' => {
-                        self.write(b"\\")?;
-                        self.write(b"r")?;
-                    }
-                    '"' => {
-                        self.write(b"\\")?;
-                        self.write(b"\"")?;
-                    }
-                    '\\' => {
-                        self.write(b"\\")?;
-                        self.write(b"\\")?;
-                    }
-                    c => {
-                        self.serialize_char(c)?;
-                    }
-                };
+        self.wrote_str = true;
+        // In compact output, an unquoted string would take the following `,` with it.
+        // And a string like `true` or `42` would read back as a different value.
+        // Hexadecimal floats are only read back as such when they are written, too.
+        let options = parser::ParseOptions {
+            hex_floats: self.options.hex_floats,
+            ..Default::default()
+        };
+        let default_spellings;
+        let non_finite = match &self.options.non_finite {
+            NonFiniteMode::String(spellings) => spellings,
+            _ => {
+                default_spellings = NonFiniteSpellings::default();
+                &default_spellings
             }
-
-            self.write(b"\"")?;
-        } else {
-            self.write(v.as_bytes())?;
-        }
-
-        Ok(())
+        };
+        let force_quotes = !self.is_key
+            && (self.options.compact || !parser::is_plain_string(v, options, non_finite));
+        self.write_string(v, force_quotes)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
         self.ensure_top_level_struct()?;
-        // For now we assume that the byte array contains
-        // valid SJSON.
-        // TODO: Turn this into an actual array of encoded bytes.
-        self.write(v)
+
+        match self.options.bytes_encoding {
+            // The byte array is assumed to contain valid SJSON.
+            BytesEncoding::Raw => self.write(v),
+            BytesEncoding::Array => {
+                let mut seq = self.serialize_seq(Some(v.len()))?;
+                for byte in v {
+                    seq.serialize_element(byte)?;
+                }
+                seq.end()
+            }
+            BytesEncoding::Base64 => self.serialize_str(&BASE64.encode(v)),
+        }
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
+        self.wrote_none = true;
         self.serialize_unit()
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         self.ensure_top_level_struct()?;
 
@@ -229,10 +1397,17 @@ where
 
     fn serialize_unit(self) -> Result<Self::Ok> {
         self.ensure_top_level_struct()?;
+        self.wrote_unit = true;
         self.write(b"null")
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok> {
+        // At the top level, it is written like a struct without fields.
+        if self.level == 0 {
+            let serializer = self.serialize_struct(name, 0)?;
+            return serde::ser::SerializeStruct::end(serializer);
+        }
+
         self.serialize_unit()
     }
 
@@ -245,9 +1420,9 @@ where
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         self.ensure_top_level_struct()?;
 
@@ -255,7 +1430,9 @@ where
     }
 
     // Serialize an externally tagged enum: `{ NAME = VALUE }`.
-    fn serialize_newtype_variant<T: ?Sized>(
+    // The outer braces share their lines with `VALUE`'s own delimiters, so an object
+    // or array in `VALUE` is only indented once, e.g. `{ NAME = {` ... `} }`.
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -263,12 +1440,12 @@ where
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         self.ensure_top_level_struct()?;
 
         self.write(b"{ ")?;
-        variant.serialize(&mut *self)?;
+        self.write_string(variant, false)?;
         self.write(b" = ")?;
         value.serialize(&mut *self)?;
         self.write(b" }")
@@ -278,8 +1455,17 @@ where
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
         self.ensure_top_level_struct()?;
 
-        self.write(b"[\n")?;
-        self.level += 1;
+        // Whether this is written as base64 is only known once all elements have been seen.
+        if self.options.bytes_encoding == BytesEncoding::Base64 {
+            self.byte_seqs.push(ByteSeq {
+                only_bytes: true,
+                ..Default::default()
+            });
+            self.open(b"")?;
+        } else {
+            self.open(b"[")?;
+        }
+
         Ok(self)
     }
 
@@ -307,24 +1493,30 @@ where
     ) -> Result<Self::SerializeTupleVariant> {
         self.ensure_top_level_struct()?;
 
-        variant.serialize(&mut *self)?;
+        self.open(b"{")?;
+        self.add_indent()?;
 
-        self.write(b" = [\n")?;
-        self.level += 1;
+        self.write_string(variant, false)?;
+
+        self.write(b" = ")?;
+        self.open(b"[")?;
 
         Ok(self)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        if self.level > 0 {
-            self.write(b"{\n")?;
+        self.open_object()?;
+
+        if self.options.sort_keys {
+            self.sorted_entries.push(Vec::new());
         }
-        self.level += 1;
+
         Ok(self)
     }
 
-    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        self.serialize_map(Some(len))
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.open_object()?;
+        Ok(self)
     }
 
     // Serialize the externally tagged representation: `{ NAME = { K = V, ... } }`.
@@ -337,214 +1529,263 @@ where
     ) -> Result<Self::SerializeStructVariant> {
         self.ensure_top_level_struct()?;
 
-        variant.serialize(&mut *self)?;
+        self.open(b"{")?;
+        self.add_indent()?;
 
-        self.write(b" = {\n")?;
-        self.level += 1;
+        self.write_string(variant, false)?;
+
+        self.write(b" = ")?;
+        self.open(b"{")?;
 
         Ok(self)
     }
 
-    fn collect_str<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn collect_str<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: std::fmt::Display,
+        T: ?Sized + std::fmt::Display,
     {
         self.serialize_str(&value.to_string())
     }
 }
 
-impl<'a, W> serde::ser::SerializeSeq for &'a mut Serializer<W>
+impl<W> serde::ser::SerializeSeq for &mut Serializer<W>
 where
     W: io::Write,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add_indent()?;
-        value.serialize(&mut **self)?;
-        self.write(b"\n")
+        self.write_element(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.level -= 1;
-        self.add_indent()?;
-        self.write(b"]")
+        self.end_seq()
     }
 }
 
-impl<'a, W> serde::ser::SerializeTuple for &'a mut Serializer<W>
+impl<W> serde::ser::SerializeTuple for &mut Serializer<W>
 where
     W: io::Write,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add_indent()?;
-        value.serialize(&mut **self)?;
-        self.write(b"\n")
+        self.write_element(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.level -= 1;
-        self.add_indent()?;
-        self.write(b"]")
+        self.end_seq()
     }
 }
 
-impl<'a, W> serde::ser::SerializeTupleStruct for &'a mut Serializer<W>
+impl<W> serde::ser::SerializeTupleStruct for &mut Serializer<W>
 where
     W: io::Write,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add_indent()?;
-        value.serialize(&mut **self)?;
-        self.write(b"\n")
+        self.write_element(value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.level -= 1;
-        self.add_indent()?;
-        self.write(b"]")
+        self.end_seq()
     }
 }
 
-impl<'a, W> serde::ser::SerializeTupleVariant for &'a mut Serializer<W>
+impl<W> serde::ser::SerializeTupleVariant for &mut Serializer<W>
 where
     W: io::Write,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.add_indent()?;
         value.serialize(&mut **self)?;
-        self.write(b"\n")
+        self.end_entry(true)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.level -= 1;
-        self.add_indent()?;
-        self.write(b"]\n")?;
-
-        self.level -= 1;
-
-        if self.level > 0 {
-            self.add_indent()?;
-            self.write(b"}")?;
-        }
-
-        Ok(())
+        self.close(b"]")?;
+        self.end_entry(false)?;
+        self.close(b"}")
     }
 }
 
-impl<'a, W> serde::ser::SerializeMap for &'a mut Serializer<W>
+impl<W> serde::ser::SerializeMap for &mut Serializer<W>
 where
     W: io::Write,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add_indent()?;
-        key.serialize(&mut **self)
+        if self.pending_key {
+            return Err(Error::new(ErrorCode::MapKeyWithoutValue, 0, 0, None));
+        }
+
+        if self.options.sort_keys {
+            let mut serializer = self.fork();
+            serializer.column = self.indent();
+            serializer.is_key = true;
+            key.serialize(&mut serializer)?;
+
+            self.sorted_entries
+                .last_mut()
+                .expect("serialize_map pushes a list of entries")
+                .push((serializer.writer, Vec::new()));
+        } else if self.options.skip_none {
+            let mut serializer = self.fork();
+            serializer.column = self.indent();
+            serializer.is_key = true;
+            key.serialize(&mut serializer)?;
+
+            self.deferred_key = Some(serializer.writer);
+        } else {
+            self.add_indent()?;
+
+            self.is_key = true;
+            let res = key.serialize(&mut **self);
+            self.is_key = false;
+            res?;
+        }
+
+        self.pending_key = true;
+        Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         // It doesn't make a difference where the `=` is added. But doing it here
         // means `serialize_key` is only a call to a different function, which should
         // have greater optimization potential for the compiler.
-        self.write(b" = ")?;
-        value.serialize(&mut **self)?;
-        self.write(b"\n")
+        self.pending_key = false;
+
+        if self.options.sort_keys {
+            let key_width = self
+                .sorted_entries
+                .last()
+                .and_then(|entries| entries.last())
+                .map_or(0, |(key, _)| width(key));
+
+            let mut serializer = self.fork();
+            serializer.column = self.indent() + key_width;
+            serializer.write_value(value)?;
+
+            let entries = self
+                .sorted_entries
+                .last_mut()
+                .expect("serialize_map pushes a list of entries");
+
+            if self.options.skip_none && serializer.wrote_none && serializer.writer == b" = null" {
+                entries.pop();
+                return Ok(());
+            }
+
+            serializer.end_entry(false)?;
+            if let Some((_, entry)) = entries.last_mut() {
+                *entry = serializer.writer;
+            }
+
+            return Ok(());
+        }
+
+        if let Some(key) = self.deferred_key.take() {
+            let mut serializer = self.fork();
+            serializer.column = self.indent() + width(&key) + " = ".len();
+            value.serialize(&mut serializer)?;
+
+            if serializer.wrote_none && serializer.writer == b"null" {
+                return Ok(());
+            }
+
+            self.add_indent()?;
+            self.write(key)?;
+            self.write_forked_value(serializer)?;
+            return self.end_entry(false);
+        }
+
+        self.write_value(value)?;
+        self.end_entry(false)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        if self.level > 1 {
-            self.level -= 1;
-            self.add_indent()?;
-            self.write(b"}")?;
+        if self.pending_key {
+            return Err(Error::new(ErrorCode::MapKeyWithoutValue, 0, 0, None));
         }
-        Ok(())
+
+        if self.options.sort_keys {
+            let mut entries = self.sorted_entries.pop().unwrap_or_default();
+            entries.sort();
+
+            for (key, value) in entries {
+                self.add_indent()?;
+                self.write(key)?;
+                self.write(value)?;
+            }
+        }
+
+        self.close_object()
     }
 }
 
-impl<'a, W> serde::ser::SerializeStruct for &'a mut Serializer<W>
+impl<W> serde::ser::SerializeStruct for &mut Serializer<W>
 where
     W: io::Write,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add_indent()?;
-        key.serialize(&mut **self)?;
-
-        self.write(b" = ")?;
-
-        value.serialize(&mut **self)?;
-        self.write(b"\n")
+        Serializer::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        if self.level > 1 {
-            self.level -= 1;
-            self.add_indent()?;
-            self.write(b"}")?;
-        }
-        Ok(())
+        self.close_object()
     }
 }
 
-impl<'a, W> serde::ser::SerializeStructVariant for &'a mut Serializer<W>
+impl<W> serde::ser::SerializeStructVariant for &mut Serializer<W>
 where
     W: std::io::Write,
 {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add_indent()?;
-        key.serialize(&mut **self)?;
-        self.write(b" = ")?;
-        value.serialize(&mut **self)?;
-        self.write(b"\n")
+        Serializer::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        if self.level > 0 {
-            self.level -= 1;
-            self.add_indent()?;
-            self.write(b"}")?;
-        }
-        Ok(())
+        self.close(b"}")?;
+        self.end_entry(false)?;
+        self.close(b"}")
     }
 }
@@ -0,0 +1,229 @@
+use crate::de::SourceSpan;
+use crate::error::{Error, ErrorCode, Result};
+use crate::parser::{self, *};
+
+/// A token of SJSON, as returned by [`Tokenizer`].
+///
+/// More kinds may be added in the future.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Token {
+    /// `[`
+    ArrayStart,
+    /// `]`
+    ArrayEnd,
+    /// `{`
+    ObjectStart,
+    /// `}`
+    ObjectEnd,
+    /// The `=` between a key and its value.
+    Equals,
+    /// A `,` between entries.
+    ///
+    /// Line breaks separate entries as well, but are skipped like other whitespace.
+    Separator,
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Boolean(bool),
+    /// An integer that fits into an `i64`.
+    Integer(i64),
+    /// An integer above `i64::MAX`.
+    UnsignedInteger(u64),
+    /// A floating point number.
+    Float(f64),
+    /// A key or a string value, with escape sequences resolved.
+    String(String),
+}
+
+impl Token {
+    // The end of the input isn't a token, but ends the iteration.
+    fn from_parsed(token: parser::Token) -> Option<Self> {
+        let token = match token {
+            parser::Token::ArrayStart => Token::ArrayStart,
+            parser::Token::ArrayEnd => Token::ArrayEnd,
+            parser::Token::ObjectStart => Token::ObjectStart,
+            parser::Token::ObjectEnd => Token::ObjectEnd,
+            parser::Token::Equals => Token::Equals,
+            parser::Token::Separator => Token::Separator,
+            parser::Token::Null => Token::Null,
+            parser::Token::Boolean(val) => Token::Boolean(val),
+            parser::Token::Integer(val) => Token::Integer(val),
+            parser::Token::UnsignedInteger(val) => Token::UnsignedInteger(val),
+            parser::Token::Float(val) => Token::Float(val),
+            parser::Token::String(val) => Token::String(val),
+            parser::Token::Eof => return None,
+        };
+        Some(token)
+    }
+}
+
+/// An iterator over the tokens of an SJSON document, along with where they were found.
+///
+/// This only splits the input into tokens, it doesn't check that they form a valid
+/// document. Whitespace and comments between tokens are skipped. The iterator ends
+/// at the end of the input, or after the first error.
+///
+/// ```
+/// use serde_sjson::{Token, Tokenizer};
+///
+/// let tokens = Tokenizer::new("size = [800, 600] // pixels")
+///     .map(|res| res.map(|(_, token)| token))
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// assert_eq!(
+///     tokens,
+///     [
+///         Token::String(String::from("size")),
+///         Token::Equals,
+///         Token::ArrayStart,
+///         Token::Integer(800),
+///         Token::Separator,
+///         Token::Integer(600),
+///         Token::ArrayEnd,
+///     ]
+/// );
+/// ```
+pub struct Tokenizer<'a> {
+    input: Span<'a>,
+    failed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Creates a new `Tokenizer` reading from a string.
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input: Span::from(input),
+            failed: false,
+        }
+    }
+
+    fn parse_error(&mut self, err: nom::Err<nom::error::Error<Span<'_>>>) -> Error {
+        self.failed = true;
+
+        if let Some(span) = unterminated_comment(self.input, &err) {
+            self.error_at(ErrorCode::UnterminatedComment, span)
+        } else if is_invalid_unicode_escape(&err) {
+            self.error_at(ErrorCode::InvalidUnicodeEscape, self.input)
+        } else {
+            self.error_at(ErrorCode::Message(err.to_string()), self.input)
+        }
+    }
+
+    fn error_at(&self, code: ErrorCode, span: Span<'_>) -> Error {
+        Error::new(
+            code,
+            span.location_line(),
+            span.get_utf8_column(),
+            Some(span.fragment().to_string()),
+        )
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<(SourceSpan, Token)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        match parse_next_token(self.input) {
+            Ok((rest, SpannedToken { token, start })) => {
+                let token = Token::from_parsed(token)?;
+                let span = SourceSpan {
+                    range: start.location_offset()..rest.location_offset(),
+                    line: start.location_line(),
+                    column: start.get_utf8_column(),
+                };
+
+                self.input = rest;
+                Some(Ok((span, token)))
+            }
+            Err(err) => Some(Err(self.parse_error(err))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ErrorKind;
+
+    fn tokens(input: &str) -> Vec<(SourceSpan, Token)> {
+        Tokenizer::new(input).collect::<Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn tokenize() {
+        let input = "// The window\nwindow = {\n  title = \"Main \\\"Window\\\"\", size = [800 0x1p3]\n  visible = true /* yes */\n  icon = null\n}\n";
+        let expected = [
+            (14..20, 2, 1, Token::String(String::from("window"))),
+            (21..22, 2, 8, Token::Equals),
+            (23..24, 2, 10, Token::ObjectStart),
+            (27..32, 3, 3, Token::String(String::from("title"))),
+            (33..34, 3, 9, Token::Equals),
+            (
+                35..52,
+                3,
+                11,
+                Token::String(String::from("Main \"Window\"")),
+            ),
+            (52..53, 3, 28, Token::Separator),
+            (54..58, 3, 30, Token::String(String::from("size"))),
+            (59..60, 3, 35, Token::Equals),
+            (61..62, 3, 37, Token::ArrayStart),
+            (62..65, 3, 38, Token::Integer(800)),
+            (66..71, 3, 42, Token::String(String::from("0x1p3"))),
+            (71..72, 3, 47, Token::ArrayEnd),
+            (75..82, 4, 3, Token::String(String::from("visible"))),
+            (83..84, 4, 11, Token::Equals),
+            (85..89, 4, 13, Token::Boolean(true)),
+            (102..106, 5, 3, Token::String(String::from("icon"))),
+            (107..108, 5, 8, Token::Equals),
+            (109..113, 5, 10, Token::Null),
+            (114..115, 6, 1, Token::ObjectEnd),
+        ];
+
+        let actual = tokens(input);
+        assert_eq!(actual.len(), expected.len());
+        for ((span, token), (range, line, column, expected)) in actual.into_iter().zip(expected) {
+            assert_eq!(
+                span,
+                SourceSpan {
+                    range,
+                    line,
+                    column
+                }
+            );
+            assert_eq!(token, expected);
+        }
+
+        assert!(tokens("").is_empty());
+        assert!(tokens("  // Only a comment\n/* and another */").is_empty());
+        assert_eq!(
+            tokens("18446744073709551615")[0].1,
+            Token::UnsignedInteger(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn tokenize_err() {
+        let mut tokenizer = Tokenizer::new("a = 1 /* oops");
+        assert_eq!(
+            tokenizer.next().unwrap().unwrap().1,
+            Token::String(String::from("a"))
+        );
+        assert_eq!(tokenizer.next().unwrap().unwrap().1, Token::Equals);
+        assert_eq!(tokenizer.next().unwrap().unwrap().1, Token::Integer(1));
+
+        let err = tokenizer.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnterminatedComment);
+        assert_eq!((err.line(), err.column()), (1, 7));
+        assert!(tokenizer.next().is_none());
+
+        let err = Tokenizer::new("\"\\u12\"").next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidUnicodeEscape);
+    }
+}
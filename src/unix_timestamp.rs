@@ -0,0 +1,48 @@
+//! Serializes a `chrono::DateTime<Utc>` as an integer of seconds since the Unix epoch,
+//! instead of an RFC 3339 string.
+//!
+//! Use it with `#[serde(with = "serde_sjson::unix_timestamp")]`. Sub-second precision
+//! is discarded when serializing.
+//!
+//! ```
+//! use chrono::{DateTime, TimeZone, Utc};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Build {
+//!     #[serde(with = "serde_sjson::unix_timestamp")]
+//!     created: DateTime<Utc>,
+//! }
+//!
+//! let build = Build {
+//!     created: Utc.with_ymd_and_hms(2024, 3, 21, 12, 0, 0).unwrap(),
+//! };
+//!
+//! let s = serde_sjson::to_string(&build).unwrap();
+//! assert_eq!(s, "created = 1711022400\n");
+//!
+//! let build: Build = serde_sjson::from_str(&s).unwrap();
+//! assert_eq!(build.created.timestamp(), 1711022400);
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serializes a timestamp as seconds since the Unix epoch.
+pub fn serialize<S>(value: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_i64(value.timestamp())
+}
+
+/// Deserializes a timestamp from seconds since the Unix epoch.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let secs = i64::deserialize(deserializer)?;
+    DateTime::from_timestamp(secs, 0)
+        .ok_or_else(|| D::Error::custom(format!("timestamp {} is out of range", secs)))
+}
@@ -0,0 +1,303 @@
+use crate::error::{Error, ErrorCode, Result};
+use crate::parser::*;
+
+/// The kind of container that is currently open.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Container {
+    // The implicit object that makes up the whole document.
+    TopLevel,
+    Object,
+    Array,
+}
+
+/// What the validator expects to see next inside the innermost container.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    // The start of an entry, or the end of the container.
+    Entry,
+    // A value, after a key and `=` or inside an array.
+    Value,
+    // A separator, or the end of the container.
+    Next,
+}
+
+struct Validator<'a> {
+    input: Span<'a>,
+    // Every open delimiter is tracked here, rather than on the call stack,
+    // so that the nesting depth is only limited by available memory.
+    stack: Vec<Container>,
+    state: State,
+}
+
+impl<'a> Validator<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input: Span::from(input),
+            stack: vec![Container::TopLevel],
+            state: State::Entry,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token> {
+        match parse_next_token(self.input) {
+            Ok((span, token)) => {
+                self.input = span;
+                Ok(token.token)
+            }
+            Err(err) => Err(self.parse_error(err)),
+        }
+    }
+
+    fn peek_token(&self) -> Result<Token> {
+        match parse_next_token(self.input) {
+            Ok((_, token)) => Ok(token.token),
+            Err(err) => Err(self.parse_error(err)),
+        }
+    }
+
+    fn parse_error(&self, err: nom::Err<nom::error::Error<Span<'_>>>) -> Error {
+        if let Some(span) = unterminated_comment(self.input, &err) {
+            self.error_at(ErrorCode::UnterminatedComment, span)
+        } else if is_invalid_unicode_escape(&err) {
+            self.error(ErrorCode::InvalidUnicodeEscape)
+        } else {
+            self.error(ErrorCode::Message(err.to_string()))
+        }
+    }
+
+    fn error(&self, code: ErrorCode) -> Error {
+        self.error_at(code, self.input)
+    }
+
+    fn error_at(&self, code: ErrorCode, span: Span<'_>) -> Error {
+        Error::new(
+            code,
+            span.location_line(),
+            span.get_utf8_column(),
+            Some(span.fragment().to_string()),
+        )
+    }
+
+    fn current(&self) -> Container {
+        *self
+            .stack
+            .last()
+            .expect("the stack is only empty once validation finished")
+    }
+
+    // Consumes the closing delimiter of the innermost container, if it is next.
+    fn close(&mut self) -> Result<bool> {
+        let end = match self.current() {
+            Container::TopLevel => Token::Eof,
+            Container::Object => Token::ObjectEnd,
+            Container::Array => Token::ArrayEnd,
+        };
+
+        if self.peek_token()? != end {
+            return Ok(false);
+        }
+
+        self.next_token()?;
+        self.stack.pop();
+        self.state = State::Next;
+        Ok(true)
+    }
+
+    fn entry(&mut self) -> Result<()> {
+        if self.close()? {
+            return Ok(());
+        }
+
+        if self.current() == Container::Array {
+            self.state = State::Value;
+            return Ok(());
+        }
+
+        // Like the `Deserializer`, any scalar can be read as a key.
+        match self.next_token()? {
+            Token::Boolean(_)
+            | Token::Float(_)
+            | Token::Integer(_)
+            | Token::UnsignedInteger(_)
+            | Token::Null
+            | Token::String(_) => {}
+            _ => return Err(self.error(ErrorCode::ExpectedString)),
+        }
+
+        if self.next_token()? != Token::Equals {
+            return Err(self.error(ErrorCode::ExpectedMapEquals));
+        }
+
+        self.state = State::Value;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<()> {
+        match self.next_token()? {
+            Token::Boolean(_)
+            | Token::Float(_)
+            | Token::Integer(_)
+            | Token::UnsignedInteger(_)
+            | Token::Null
+            | Token::String(_) => {
+                self.state = State::Next;
+            }
+            Token::ArrayStart => {
+                self.stack.push(Container::Array);
+                self.state = State::Entry;
+            }
+            Token::ObjectStart => {
+                self.stack.push(Container::Object);
+                self.state = State::Entry;
+            }
+            _ => return Err(self.error(ErrorCode::ExpectedValue)),
+        }
+
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        if let Ok((span, _)) = parse_separator(self.input) {
+            self.input = span;
+            self.state = State::Entry;
+            return Ok(());
+        }
+
+        if self.close()? {
+            return Ok(());
+        }
+
+        let code = match self.current() {
+            Container::Array => ErrorCode::ExpectedArraySeparator,
+            Container::Object | Container::TopLevel => ErrorCode::ExpectedMapSeparator,
+        };
+        Err(self.error(code))
+    }
+
+    fn run(mut self) -> Result<()> {
+        // The top-level object may also be wrapped in braces, like a nested one.
+        // And a document may consist of a single array instead.
+        let wrapped = match self.peek_token()? {
+            Token::ObjectStart => Some(Container::Object),
+            Token::ArrayStart => Some(Container::Array),
+            _ => None,
+        };
+        if let Some(container) = wrapped {
+            self.next_token()?;
+            self.stack = vec![container];
+        }
+
+        while !self.stack.is_empty() {
+            match self.state {
+                State::Entry => self.entry()?,
+                State::Value => self.value()?,
+                State::Next => self.next()?,
+            }
+        }
+
+        if wrapped.is_some() && self.peek_token()? != Token::Eof {
+            return Err(self.error(ErrorCode::TrailingCharacters));
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that a string is structurally valid SJSON, without deserializing it.
+///
+/// Unlike the [`Deserializer`](crate::Deserializer), this does not recurse for nested
+/// objects and arrays, so arbitrarily deep documents can be validated without
+/// growing the call stack.
+pub fn validate(input: &str) -> Result<()> {
+    Validator::new(input).run()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::{Error, ErrorCode};
+
+    use super::validate;
+
+    #[test]
+    fn validate_ok() {
+        assert_eq!(validate(""), Ok(()));
+        assert_eq!(validate("foo = bar"), Ok(()));
+        assert_eq!(validate("foo = [1, 2, 3]\nbar = { baz = null }"), Ok(()));
+        assert_eq!(validate("foo = [\n    1\n    2\n]\n"), Ok(()));
+        assert_eq!(validate("foo = []\nbar = {}"), Ok(()));
+        assert_eq!(validate("{\n  foo = 1\n  bar = {}\n}\n"), Ok(()));
+        assert_eq!(validate("{}"), Ok(()));
+        assert_eq!(validate("1 = foo"), Ok(()));
+        assert_eq!(validate("true = 1"), Ok(()));
+        assert_eq!(validate("null = 1"), Ok(()));
+        assert_eq!(validate("foo = { 1.5 = a, -2 = b }"), Ok(()));
+        assert_eq!(validate("[1, 2]"), Ok(()));
+        assert_eq!(validate("[\n  { foo = 1 }\n  [bar]\n]\n"), Ok(()));
+    }
+
+    #[test]
+    fn validate_err() {
+        let err = Error::new(ErrorCode::ExpectedMapEquals, 1, 8, Some("".to_string()));
+        assert_eq!(validate("foo bar"), Err(err));
+
+        let err = Error::new(ErrorCode::ExpectedValue, 1, 8, Some("".to_string()));
+        assert_eq!(validate("foo = ["), Err(err));
+
+        let err = Error::new(ErrorCode::ExpectedString, 1, 8, Some("".to_string()));
+        assert_eq!(validate("foo = {"), Err(err));
+
+        let err = Error::new(
+            ErrorCode::ExpectedArraySeparator,
+            1,
+            9,
+            Some(" 2]".to_string()),
+        );
+        assert_eq!(validate("foo = [1 2]"), Err(err));
+
+        let err = Error::new(
+            ErrorCode::UnterminatedComment,
+            1,
+            11,
+            Some("/* bar".to_string()),
+        );
+        assert_eq!(validate("foo = bar /* bar"), Err(err));
+
+        let err = Error::new(
+            ErrorCode::InvalidUnicodeEscape,
+            1,
+            6,
+            Some(r#" "\uD83D""#.to_string()),
+        );
+        assert_eq!(validate(r#"foo = "\uD83D""#), Err(err));
+
+        let err = Error::new(
+            ErrorCode::TrailingCharacters,
+            1,
+            12,
+            Some(" bar".to_string()),
+        );
+        assert_eq!(validate("{ foo = 1 } bar"), Err(err));
+
+        let err = Error::new(
+            ErrorCode::TrailingCharacters,
+            1,
+            7,
+            Some(" bar".to_string()),
+        );
+        assert_eq!(validate("[1, 2] bar"), Err(err));
+    }
+
+    #[test]
+    fn validate_deep_nesting() {
+        let depth = 100_000;
+        let sjson = format!("value = {}{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(validate(&sjson), Ok(()));
+
+        let sjson = format!(
+            "value = {}null{}",
+            "{ a = ".repeat(depth),
+            "}".repeat(depth)
+        );
+        assert_eq!(validate(&sjson), Ok(()));
+    }
+}
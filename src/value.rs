@@ -0,0 +1,619 @@
+use std::fmt;
+use std::ops::Index;
+
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap as _, SerializeSeq as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Any SJSON value, for documents without a predefined structure.
+///
+/// A document deserializes into a [`Value::Object`], or a [`Value::Array`] if it
+/// consists of a single array. Object keys are kept in the order they appear in.
+///
+/// ```
+/// use serde_sjson::Value;
+///
+/// let value: Value = serde_sjson::from_str("name = Marc\nsize = [800, 600]").unwrap();
+///
+/// assert_eq!(value["name"].as_str(), Some("Marc"));
+/// assert_eq!(value["size"][1].as_i64(), Some(600));
+/// assert!(value["missing"].is_null());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Value {
+    /// `null`, which is also what `()` and `None` are written as.
+    #[default]
+    Null,
+    /// `true` or `false`.
+    Bool(bool),
+    /// A number without a fraction or exponent, like `42`.
+    Integer(i64),
+    /// A number with a fraction or exponent, like `1.5`.
+    Float(f64),
+    /// A quoted or unquoted string.
+    String(String),
+    /// An array, like `[1, 2]`.
+    Array(Vec<Value>),
+    /// An object, like `{ foo = 1 }`.
+    Object(Map),
+}
+
+/// The entries of a [`Value::Object`], in the order they were inserted.
+///
+/// Lookups search the entries one by one, which is fast enough for the size of
+/// typical configuration files.
+#[derive(Clone, Default)]
+pub struct Map {
+    entries: Vec<(String, Value)>,
+}
+
+impl Map {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the value for a key.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Returns a mutable reference to the value for a key.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Returns whether there is a value for a key.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value, and returns the previous value for the key.
+    ///
+    /// A new key is added at the end. An existing key keeps its position.
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        match self.get_mut(&key) {
+            Some(old) => Some(std::mem::replace(old, value)),
+            None => {
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes a key, and returns its value. The other entries keep their order.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Returns an iterator over the entries, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    /// Returns an iterator over the keys, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    /// Returns an iterator over the values, in order.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+// Maps with the same entries are equal, regardless of their order.
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<(String, Value)> for Map {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Value)>,
+    {
+        let mut map = Map::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (String, Value);
+    type IntoIter = std::vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+// What indexing returns for a missing key or index.
+static NULL: Value = Value::Null;
+
+impl Value {
+    /// Returns whether this is `null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Returns the boolean, if this is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer, if this is one.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Integer(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as a float, if this is a float or an integer.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Integer(val) => Some(*val as f64),
+            Self::Float(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Returns the string, if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements, if this is an array.
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Self::Array(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries, if this is an object.
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            Self::Object(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the value for a key, if this is an object that contains it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object().and_then(|object| object.get(key))
+    }
+}
+
+/// Looks up the value for a key, like `value["name"]`.
+///
+/// This returns [`Value::Null`] if this is not an object, or if the key is missing.
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Looks up an element of an array, like `value[0]`.
+///
+/// This returns [`Value::Null`] if this is not an array, or if the index is out of bounds.
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        self.as_array()
+            .and_then(|array| array.get(index))
+            .unwrap_or(&NULL)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Bool(val) => serializer.serialize_bool(*val),
+            Self::Integer(val) => serializer.serialize_i64(*val),
+            Self::Float(val) => serializer.serialize_f64(*val),
+            Self::String(val) => serializer.serialize_str(val),
+            Self::Array(elements) => {
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                for element in elements {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            Self::Object(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries.iter() {
+                    map.serialize_entry(key.as_str(), value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any SJSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        // Integers beyond the range of `i64` are only representable approximately.
+        Ok(i64::try_from(v).map_or(Value::Float(v as f64), Value::Integer))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+
+        Ok(Value::Array(elements))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Map::new();
+        while let Some(key) = map.next_key_seed(KeySeed)? {
+            let value = map.next_value()?;
+            entries.insert(key, value);
+        }
+
+        Ok(Value::Object(entries))
+    }
+}
+
+// Keys are always strings, even if they look like a different kind of value, like `1 = foo`.
+struct KeySeed;
+
+impl<'de> DeserializeSeed<'de> for KeySeed {
+    type Value = String;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<String, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeySeed {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string key")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{Map, Value};
+    use crate::{from_str, to_string};
+
+    #[test]
+    fn deserialize_value() {
+        let sjson = r#"
+        name = Marc
+        age = 21
+        height = 1.8
+        active = true
+        spouse = null
+        friends = ["Jessica", "Paul"]
+        address = { city = "Hamburg", zip = "20095" }
+        "#;
+
+        let expected = Value::Object(Map::from_iter([
+            (String::from("name"), Value::String(String::from("Marc"))),
+            (String::from("age"), Value::Integer(21)),
+            (String::from("height"), Value::Float(1.8)),
+            (String::from("active"), Value::Bool(true)),
+            (String::from("spouse"), Value::Null),
+            (
+                String::from("friends"),
+                Value::Array(vec![
+                    Value::String(String::from("Jessica")),
+                    Value::String(String::from("Paul")),
+                ]),
+            ),
+            (
+                String::from("address"),
+                Value::Object(Map::from_iter([
+                    (String::from("city"), Value::String(String::from("Hamburg"))),
+                    (String::from("zip"), Value::String(String::from("20095"))),
+                ])),
+            ),
+        ]));
+
+        assert_eq!(from_str::<Value>(sjson), Ok(expected));
+        assert_eq!(from_str::<Value>(""), Ok(Value::Object(Map::new())));
+        assert_eq!(
+            from_str::<Value>("[1, 2]"),
+            Ok(Value::Array(vec![Value::Integer(1), Value::Integer(2)]))
+        );
+
+        // Numbers, `null` and booleans that are followed by more characters start a string.
+        for val in [
+            "information",
+            "nancy",
+            "inferno",
+            "infinite",
+            "nullable",
+            "truely",
+            "1foo",
+            "1e",
+            "2024-03-21",
+            "0.1.0",
+            "12:30",
+        ] {
+            let value = from_str::<Value>(&format!("value = {val}")).unwrap();
+            assert_eq!(value["value"], Value::String(val.to_string()), "{val}");
+
+            let value = from_str::<Value>(&format!("value = [{val}, 1]")).unwrap();
+            assert_eq!(value["value"][0], Value::String(val.to_string()), "{val}");
+            assert_eq!(value["value"][1], Value::Integer(1), "{val}");
+        }
+
+        // The same goes for keys.
+        let value = from_str::<Value>("1e = 1\nvalue = { 2x = 2, 1.5 = 3 }").unwrap();
+        assert_eq!(value["1e"], Value::Integer(1));
+        assert_eq!(value["value"]["2x"], Value::Integer(2));
+        assert_eq!(value["value"]["1.5"], Value::Integer(3));
+        let map = from_str::<BTreeMap<String, u64>>("{ 1e = 1, 2x = 2 }").unwrap();
+        assert_eq!(map, BTreeMap::from([("1e".into(), 1), ("2x".into(), 2)]));
+    }
+
+    #[test]
+    fn value_accessors() {
+        let value: Value =
+            from_str("name = Marc\nsize = [800, 600.5]\nnested = { on = true }").unwrap();
+
+        assert_eq!(value["name"].as_str(), Some("Marc"));
+        assert_eq!(value["size"][0].as_i64(), Some(800));
+        assert_eq!(value["size"][0].as_f64(), Some(800.0));
+        assert_eq!(value["size"][1].as_i64(), None);
+        assert_eq!(value["size"][1].as_f64(), Some(600.5));
+        assert_eq!(value["nested"]["on"].as_bool(), Some(true));
+        assert_eq!(
+            value.get("size").and_then(Value::as_array).map(Vec::len),
+            Some(2)
+        );
+        assert_eq!(value.as_object().map(Map::len), Some(3));
+
+        assert!(value["missing"].is_null());
+        assert!(value["size"][2].is_null());
+        assert!(value["name"]["foo"].is_null());
+        assert!(value[0].is_null());
+        assert_eq!(value.get("missing"), None);
+    }
+
+    #[test]
+    fn roundtrip_value() {
+        let sjson = "\
+name = Marc
+height = 1.8
+spouse = null
+friends = [
+  Jessica
+  Paul
+]
+address = {
+  zip = \"20095\"
+  city = Hamburg
+}
+active = true
+";
+
+        let value: Value = from_str(sjson).unwrap();
+        assert_eq!(to_string(&value).unwrap(), sjson);
+
+        // Strings that look like other values are quoted, so they stay strings.
+        // Those that only start like one, like a version or a date, are not.
+        let value = Value::Array(
+            [
+                "null",
+                "true",
+                "42",
+                "-1.5e3",
+                "0x1p3",
+                "1.2.3",
+                "nullable",
+                "1.2.3a",
+                "2024-03-21",
+            ]
+            .into_iter()
+            .map(|val| Value::String(val.to_string()))
+            .collect(),
+        );
+        let value = Value::Object(Map::from_iter([(String::from("list"), value)]));
+        let sjson = to_string(&value).unwrap();
+        assert_eq!(
+            sjson,
+            "list = [\n  \"null\"\n  \"true\"\n  \"42\"\n  \"-1.5e3\"\n  0x1p3\n  1.2.3\n  nullable\n  1.2.3a\n  2024-03-21\n]\n"
+        );
+        assert_eq!(from_str::<Value>(&sjson), Ok(value));
+    }
+
+    // Checks the example from
+    // https://help.autodesk.com/view/Stingray/ENU/?guid=__stingray_help_managing_content_sjson_html
+    #[test]
+    fn roundtrip_value_stingray_example() {
+        let sjson = r#"
+// The script that should be started when the application runs.
+boot_script = "boot"
+
+// The port on which the console server runs.
+console_port = 14030
+
+// Settings for the win32 platform
+win32 = {
+    /* Sets the affinity mask for
+       QueryPerformanceCounter() */
+    query_performance_counter_affinity_mask = 0
+}
+
+render_config = "core/rendering/renderer"
+"#;
+
+        let value: Value = from_str(sjson).unwrap();
+        let keys: Vec<_> = value.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            ["boot_script", "console_port", "win32", "render_config"]
+        );
+
+        let expected = "\
+boot_script = boot
+console_port = 14030
+win32 = {
+  query_performance_counter_affinity_mask = 0
+}
+render_config = core/rendering/renderer
+";
+        assert_eq!(to_string(&value).unwrap(), expected);
+    }
+
+    #[test]
+    fn value_map() {
+        let mut map = Map::new();
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert(String::from("b"), Value::Integer(1)), None);
+        assert_eq!(map.insert(String::from("a"), Value::Integer(2)), None);
+        assert_eq!(map.insert(String::from("c"), Value::Integer(3)), None);
+
+        // Replacing a value keeps its position.
+        assert_eq!(
+            map.insert(String::from("b"), Value::Bool(true)),
+            Some(Value::Integer(1))
+        );
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "a", "c"]);
+        assert_eq!(map.get("b"), Some(&Value::Bool(true)));
+
+        assert_eq!(map.remove("a"), Some(Value::Integer(2)));
+        assert_eq!(map.remove("a"), None);
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "c"]);
+        assert!(!map.contains_key("a"));
+        assert_eq!(map.len(), 2);
+
+        if let Some(value) = map.get_mut("c") {
+            *value = Value::Null;
+        }
+        assert_eq!(
+            map.values().collect::<Vec<_>>(),
+            [&Value::Bool(true), &Value::Null]
+        );
+
+        // Equality does not depend on the order.
+        let other = Map::from_iter([
+            (String::from("c"), Value::Null),
+            (String::from("b"), Value::Bool(true)),
+        ]);
+        assert_eq!(map, other);
+        assert_ne!(
+            map,
+            Map::from_iter([(String::from("b"), Value::Bool(true))])
+        );
+
+        // Duplicate keys in a document behave like repeated inserts.
+        let value: Value = from_str("b = 1\na = 2\nb = 3").unwrap();
+        let entries: Vec<_> = value.as_object().unwrap().clone().into_iter().collect();
+        assert_eq!(
+            entries,
+            [
+                (String::from("b"), Value::Integer(3)),
+                (String::from("a"), Value::Integer(2))
+            ]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Checks that serializing does not allocate per element.
+//!
+//! This is a separate test binary, since it counts allocations through the global allocator,
+//! which other tests running in parallel would interfere with.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn serialize_large_sequence_without_allocating_per_element() {
+    #[derive(Serialize)]
+    struct Data {
+        values: Vec<u64>,
+        negative: Vec<i64>,
+    }
+
+    const LEN: u64 = 1_000_000;
+
+    let data = Data {
+        values: (0..LEN).map(|i| i * 1_000_003).collect(),
+        negative: (0..LEN as i64).map(|i| -i).collect(),
+    };
+
+    // Reserve enough for the whole output, so that only the serializer's own allocations
+    // are counted.
+    let mut buf = Vec::with_capacity(64 * 1024 * 1024);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    serde_sjson::to_writer(&mut buf, &data).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert!(
+        allocations < 16,
+        "serializing {} elements allocated {} times",
+        LEN * 2,
+        allocations
+    );
+
+    let output = String::from_utf8(buf).unwrap();
+    assert_eq!(output.lines().count(), 2 * (LEN as usize + 2));
+    assert!(output.starts_with("values = [\n  0\n  1000003\n"));
+    assert!(output.ends_with("\n  -999999\n]\n"));
+}
@@ -1,4 +1,10 @@
-use serde_sjson::to_string;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_sjson::{
+    to_string, to_string_documented, BytesEncoding, FloatFormat, Indent, IndentMode, NonFiniteMode,
+    NonFiniteSpellings, SerializeOptions, Serializer, UnitFieldMode,
+};
 
 #[test]
 fn serialize_null() {
@@ -59,6 +65,8 @@ fn serialize_f64() {
         f64::MAX,
         f64::EPSILON,
         std::f64::consts::PI,
+        // The smallest subnormal, written out with all of its leading zeros.
+        f64::from_bits(1),
     ];
     for value in tests {
         let expected = format!("value = {value}\n");
@@ -66,9 +74,19 @@ fn serialize_f64() {
         let actual = to_string(&value).unwrap();
         assert_eq!(actual, expected);
     }
+
+    // The longest output any float format can produce.
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_float_format(FloatFormat::Fixed(u8::MAX));
+    Value { value: f64::MIN }.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        format!("value = {:.255}\n", f64::MIN)
+    );
 }
 
 #[test]
+#[allow(clippy::legacy_numeric_constants)]
 fn serialize_non_representable_floats() {
     #[derive(serde::Serialize)]
     struct Value64 {
@@ -80,16 +98,198 @@ fn serialize_non_representable_floats() {
         value: f32,
     }
 
+    #[derive(serde::Serialize)]
+    struct Optional {
+        value: Option<f64>,
+    }
+
+    fn assert_non_finite<T: Serialize>(value: &T) {
+        let err = to_string(value).unwrap_err();
+        assert_eq!(err.kind(), serde_sjson::ErrorKind::NonFiniteFloat);
+        assert_eq!(
+            err.to_string(),
+            "got a floating point number that is NaN or infinite"
+        );
+    }
+
+    // Whether the float is a plain field or inside an `Option` makes no difference.
     let tests = [std::f64::NAN, std::f64::INFINITY, std::f64::NEG_INFINITY];
     for value in tests {
-        let value = Value64 { value };
-        assert!(to_string(&value).is_err());
+        assert_non_finite(&Value64 { value });
+        assert_non_finite(&Optional { value: Some(value) });
     }
     let tests = [std::f32::NAN, std::f32::INFINITY, std::f32::NEG_INFINITY];
     for value in tests {
-        let value = Value32 { value };
-        assert!(to_string(&value).is_err());
+        assert_non_finite(&Value32 { value });
+    }
+
+    assert_eq!(
+        to_string(&Optional { value: None }).unwrap(),
+        "value = null\n"
+    );
+}
+
+#[test]
+fn serialize_non_finite_modes() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: f64,
+        option: Option<f64>,
+        list: Vec<f32>,
+    }
+
+    fn serialize(value: f64, mode: NonFiniteMode) -> serde_sjson::Result<String> {
+        let value = Value {
+            value,
+            option: Some(value),
+            list: vec![1.5, value as f32],
+        };
+
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_non_finite_mode(mode);
+        value.serialize(&mut ser)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let err = serialize(value, NonFiniteMode::Error).unwrap_err();
+        assert_eq!(err.kind(), serde_sjson::ErrorKind::NonFiniteFloat);
+
+        let actual = serialize(value, NonFiniteMode::Null).unwrap();
+        assert_eq!(
+            actual,
+            "value = null\noption = null\nlist = [\n  1.5\n  null\n]\n"
+        );
+
+        let spellings = NonFiniteSpellings::default();
+        let spelling = if value.is_nan() {
+            "nan"
+        } else if value > 0.0 {
+            "inf"
+        } else {
+            "-inf"
+        };
+        let actual = serialize(value, NonFiniteMode::String(spellings)).unwrap();
+        assert_eq!(
+            actual,
+            format!("value = {spelling}\noption = {spelling}\nlist = [\n  1.5\n  {spelling}\n]\n")
+        );
+    }
+
+    // Finite values are not affected.
+    let actual = serialize(2.0, NonFiniteMode::Null).unwrap();
+    assert_eq!(actual, "value = 2\noption = 2\nlist = [\n  1.5\n  2\n]\n");
+
+    // `null` only reads back into an `Option`.
+    #[derive(Debug, serde::Deserialize, PartialEq)]
+    struct Nullable {
+        value: Option<f64>,
+    }
+    let actual: Nullable = serde_sjson::from_str("value = null").unwrap();
+    assert_eq!(actual, Nullable { value: None });
+
+    #[derive(Debug, serde::Deserialize)]
+    struct NotNullable {
+        #[allow(dead_code)]
+        value: f64,
+    }
+    assert!(serde_sjson::from_str::<NotNullable>("value = null").is_err());
+}
+
+#[test]
+fn serialize_non_finite_floats_as_string() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: f64,
+    }
+
+    let tests = [
+        (NonFiniteSpellings::default(), f64::NAN, "nan"),
+        (NonFiniteSpellings::default(), f64::INFINITY, "inf"),
+        (NonFiniteSpellings::default(), f64::NEG_INFINITY, "-inf"),
+        (
+            NonFiniteSpellings::new("NaN", "+Infinity", "-Infinity"),
+            f64::INFINITY,
+            "+Infinity",
+        ),
+        (
+            NonFiniteSpellings::new("not a number", "inf", "-inf"),
+            f64::NAN,
+            "\"not a number\"",
+        ),
+    ];
+    for (spellings, value, spelling) in tests {
+        let mut buf = Vec::new();
+        let mut ser =
+            Serializer::new(&mut buf).with_non_finite_mode(NonFiniteMode::String(spellings));
+        Value { value }.serialize(&mut ser).unwrap();
+
+        let expected = format!("value = {spelling}\n");
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+}
+
+#[test]
+fn serialize_float_formats() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Value {
+        value: f64,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value32 {
+        value: f32,
     }
+
+    let tests = [
+        (FloatFormat::Shortest, 1.5, "1.5"),
+        (FloatFormat::Shortest, 0.1, "0.1"),
+        (FloatFormat::Fixed(3), 1.5, "1.500"),
+        (FloatFormat::Fixed(3), -0.0004, "-0.000"),
+        (FloatFormat::Fixed(3), 2.0 / 3.0, "0.667"),
+        (FloatFormat::Fixed(2), 1234567.891, "1234567.89"),
+        (FloatFormat::Fixed(0), 2.5, "2"),
+        (FloatFormat::Fixed(0), 3.7, "4"),
+        (FloatFormat::Fixed(0), -0.4, "-0"),
+        (FloatFormat::Significant(3), 1.5, "1.50"),
+        (FloatFormat::Significant(3), 0.0, "0.00"),
+        (FloatFormat::Significant(3), 12345.678, "1.23e4"),
+        (FloatFormat::Significant(3), 123.456, "123"),
+        (FloatFormat::Significant(3), -0.00123456, "-0.00123"),
+        (FloatFormat::Significant(3), 0.0000123456, "1.23e-5"),
+        (FloatFormat::Significant(2), 9.99, "10"),
+        (FloatFormat::Significant(1), 9.99, "1e1"),
+        (FloatFormat::Significant(0), 0.25, "0.2"),
+    ];
+    for (format, value, expected) in tests {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_float_format(format);
+        Value { value }.serialize(&mut ser).unwrap();
+
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            actual,
+            format!("value = {expected}\n"),
+            "{format:?} {value}"
+        );
+
+        // Every format is still a valid number, even if it isn't the same value.
+        let parsed: Value = serde_sjson::from_str(&actual).unwrap();
+        assert_eq!(parsed.value, expected.parse::<f64>().unwrap());
+    }
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_float_format(FloatFormat::Fixed(2));
+    Value32 { value: 0.1 }.serialize(&mut ser).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), "value = 0.10\n");
+
+    // Hex floats take precedence, since they are exact.
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf)
+        .with_float_format(FloatFormat::Fixed(2))
+        .with_hex_floats();
+    Value { value: 1.5 }.serialize(&mut ser).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), "value = 0x1.8p+0\n");
 }
 
 #[test]
@@ -123,6 +323,9 @@ fn serialize_string() {
         ("foo\r\nbar", "\"foo\\r\\nbar\""),
         ("foo\tbar", "\"foo\\tbar\""),
         ("foo/bar", "foo/bar"),
+        ("foo//bar", "foo//bar"),
+        ("//foo/bar", "\"//foo/bar\""),
+        ("/*foo", "\"/*foo\""),
         ("foo\\bar", "\"foo\\\\bar\""),
         // Regression test for #7.
         ("scripts/mods/test\\new", "\"scripts/mods/test\\\\new\""),
@@ -131,6 +334,10 @@ fn serialize_string() {
             "+002023-03-03T16:42:33.944311860Z",
             "\"+002023-03-03T16:42:33.944311860Z\"",
         ),
+        // These would be read back as floats.
+        (".5", "\".5\""),
+        ("5.", "\"5.\""),
+        (".", "."),
     ];
     for (value, expected) in tests {
         let expected = format!("value = {expected}\n");
@@ -142,6 +349,50 @@ fn serialize_string() {
     }
 }
 
+#[test]
+fn serialize_collect_str() {
+    // Serialized through `collect_str`, like many `Display` types are.
+    struct Label(&'static str);
+
+    impl Serialize for Label {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self.0)
+        }
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: Label,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Parsed {
+        value: String,
+    }
+
+    let tests = [
+        ("foo", "foo"),
+        ("foo bar", "\"foo bar\""),
+        ("say \"hi\"", "\"say \\\"hi\\\"\""),
+        ("line\nbreak", "\"line\\nbreak\""),
+        ("42", "\"42\""),
+    ];
+    for (value, expected) in tests {
+        let expected = format!("value = {expected}\n");
+        let actual = to_string(&Value {
+            value: Label(value),
+        })
+        .unwrap();
+        assert_eq!(actual, expected);
+
+        let parsed: Parsed = serde_sjson::from_str(&actual).unwrap();
+        assert_eq!(parsed.value, value);
+    }
+
+    let err = to_string(&Label("foo")).unwrap_err();
+    assert_eq!(err.kind(), serde_sjson::ErrorKind::ExpectedTopLevelObject);
+}
+
 #[test]
 fn serialize_char() {
     #[derive(serde::Serialize)]
@@ -168,6 +419,137 @@ fn serialize_char() {
         let actual = to_string(&value).unwrap();
         assert_eq!(actual, expected);
     }
+
+    // A `char` itself is written just like the string.
+    #[derive(serde::Serialize)]
+    struct Char {
+        value: char,
+    }
+
+    for (value, expected) in tests {
+        let expected = format!("value = {expected}\n");
+        let actual = to_string(&Char { value }).unwrap();
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn serialize_escape_non_ascii() {
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Value {
+        value: String,
+        letter: char,
+    }
+
+    let tests = [
+        (
+            "café",
+            'é',
+            "value = \"caf\\u00e9\"\nletter = \"\\u00e9\"\n",
+        ),
+        (
+            "a😀b",
+            '😀',
+            "value = \"a\\ud83d\\ude00b\"\nletter = \"\\ud83d\\ude00\"\n",
+        ),
+        (
+            "\"ü\"\n",
+            'x',
+            "value = \"\\\"\\u00fc\\\"\\n\"\nletter = x\n",
+        ),
+        ("ascii", 'a', "value = ascii\nletter = a\n"),
+    ];
+
+    for (value, letter, expected) in tests {
+        let value = Value {
+            value: String::from(value),
+            letter,
+        };
+
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_escape_non_ascii(true);
+        value.serialize(&mut ser).unwrap();
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(actual, expected);
+        assert!(actual.is_ascii());
+        assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+    }
+
+    // Field names are escaped and quoted like map keys, and read back the same way.
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Renamed {
+        #[serde(rename = "café")]
+        cafe: u64,
+    }
+
+    let value = Renamed { cafe: 1 };
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_escape_non_ascii(true);
+    value.serialize(&mut ser).unwrap();
+    let actual = String::from_utf8(buf).unwrap();
+    assert_eq!(actual, "\"caf\\u00e9\" = 1\n");
+    assert_eq!(serde_sjson::from_str::<Renamed>(&actual).unwrap(), value);
+
+    // By default, characters are written as they are.
+    let value = Value {
+        value: String::from("café"),
+        letter: 'é',
+    };
+    let actual = to_string(&value).unwrap();
+    assert_eq!(actual, "value = café\nletter = é\n");
+}
+
+#[test]
+fn serialize_multiline_strings() {
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Value {
+        value: String,
+        after: u64,
+    }
+
+    let tests = [
+        (
+            "line 1\nline \"2\"\t\\n",
+            "value = \"\"\"line 1\nline \"2\"\t\\n\"\"\"\nafter = 1\n",
+        ),
+        ("\n", "value = \"\"\"\n\"\"\"\nafter = 1\n"),
+        ("a\r\nb\n", "value = \"\"\"a\r\nb\n\"\"\"\nafter = 1\n"),
+        // These can't be written verbatim.
+        ("a\n\"\"\"", "value = \"a\\n\\\"\\\"\\\"\"\nafter = 1\n"),
+        ("a\n\"b\"", "value = \"a\\n\\\"b\\\"\"\nafter = 1\n"),
+        // Strings without line breaks are not affected.
+        ("a \"b\"", "value = \"a \\\"b\\\"\"\nafter = 1\n"),
+    ];
+
+    for (value, expected) in tests {
+        let value = Value {
+            value: String::from(value),
+            after: 1,
+        };
+
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_multiline_strings(true);
+        value.serialize(&mut ser).unwrap();
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+
+        // By default, line breaks are escaped.
+        let actual = to_string(&value).unwrap();
+        assert!(!actual.contains("\"\"\""), "{actual}");
+        assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+    }
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf)
+        .with_multiline_strings(true)
+        .with_escape_non_ascii(true);
+    let value = Value {
+        value: String::from("é\n"),
+        after: 1,
+    };
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(buf, b"value = \"\\u00e9\\n\"\nafter = 1\n");
 }
 
 #[test]
@@ -441,6 +823,66 @@ fn serialize_enum_newtype_variant() {
     );
 }
 
+#[test]
+fn serialize_enum_struct_variant() {
+    #[derive(serde::Serialize)]
+    enum Variant {
+        Point { x: i64, y: i64 },
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: Variant,
+    }
+
+    let value = Value {
+        value: Variant::Point { x: 1, y: 2 },
+    };
+    assert_eq!(
+        to_string(&value).unwrap(),
+        String::from(
+            "\
+value = {
+  Point = {
+    x = 1
+    y = 2
+  }
+}
+"
+        )
+    );
+}
+
+#[test]
+fn serialize_enum_tuple_variant() {
+    #[derive(serde::Serialize)]
+    enum Variant {
+        Point(i64, i64),
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: Variant,
+    }
+
+    let value = Value {
+        value: Variant::Point(1, 2),
+    };
+    assert_eq!(
+        to_string(&value).unwrap(),
+        String::from(
+            "\
+value = {
+  Point = [
+    1
+    2
+  ]
+}
+"
+        )
+    );
+}
+
 #[test]
 fn serialize_enum_tagged_variant() {
     #[derive(serde::Serialize)]
@@ -565,3 +1007,1308 @@ fn serialize_option_string() {
         String::from("value = \"foo bar\"\n")
     );
 }
+
+#[test]
+fn serialize_map_invalid_keys() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: BTreeMap<String, u64>,
+    }
+
+    let tests = ["foo\nbar", "foo\r\nbar", "foo=bar", "=", "foo{", "[bar]"];
+    for key in tests {
+        let value = Value {
+            value: BTreeMap::from([(String::from(key), 1)]),
+        };
+        assert!(to_string(&value).is_err(), "{key:?}");
+    }
+
+    let value = Value {
+        value: BTreeMap::from([(String::from("foo bar"), 1)]),
+    };
+    assert_eq!(
+        to_string(&value).unwrap(),
+        String::from("value = {\n  \"foo bar\" = 1\n}\n")
+    );
+}
+
+#[test]
+fn serialize_map_structural_characters() {
+    #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Value {
+        value: BTreeMap<String, String>,
+    }
+
+    let value = Value {
+        value: BTreeMap::from([
+            (String::from("a,b"), String::from("c,d")),
+            (String::from("e"), String::from("{f}")),
+            (String::from("g,"), String::from("[h]")),
+        ]),
+    };
+    let actual = to_string(&value).unwrap();
+    assert_eq!(
+        actual,
+        "value = {\n  \"a,b\" = \"c,d\"\n  e = \"{f}\"\n  \"g,\" = \"[h]\"\n}\n"
+    );
+    assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+}
+
+#[test]
+fn serialize_map_key_without_value() {
+    use serde::ser::SerializeMap;
+
+    struct KeyOnly;
+
+    impl serde::Serialize for KeyOnly {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(None)?;
+            map.serialize_key("foo")?;
+            map.end()
+        }
+    }
+
+    struct KeyTwice;
+
+    impl serde::Serialize for KeyTwice {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(None)?;
+            map.serialize_key("foo")?;
+            map.serialize_key("bar")?;
+            map.serialize_value(&1)?;
+            map.end()
+        }
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value<T> {
+        value: T,
+    }
+
+    assert!(to_string(&KeyOnly).is_err());
+    assert!(to_string(&KeyTwice).is_err());
+    assert!(to_string(&Value { value: KeyOnly }).is_err());
+    assert!(to_string(&Value { value: KeyTwice }).is_err());
+}
+
+#[test]
+fn serialize_canonical() {
+    use std::collections::HashMap;
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        map: HashMap<String, HashMap<String, f64>>,
+    }
+
+    let keys = [
+        "foo", "bar", "baz", "qux", "quux", "corge", "grault", "garply",
+    ];
+
+    let mut forward = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        let inner = HashMap::from([(String::from("b"), i as f64), (String::from("a"), 0.1)]);
+        forward.insert(key.to_string(), inner);
+    }
+
+    let mut backward = HashMap::new();
+    for (i, key) in keys.iter().enumerate().rev() {
+        let inner = HashMap::from([(String::from("a"), 0.1), (String::from("b"), i as f64)]);
+        backward.insert(key.to_string(), inner);
+    }
+
+    let forward = serde_sjson::to_string_canonical(&Value { map: forward }).unwrap();
+    let backward = serde_sjson::to_string_canonical(&Value { map: backward }).unwrap();
+    assert_eq!(forward, backward);
+
+    let expected = "\
+map = {
+  bar = {
+    a = 0.1
+    b = 1.0
+  }
+  baz = {
+    a = 0.1
+    b = 2.0
+  }
+";
+    assert!(forward.starts_with(expected), "{forward}");
+}
+
+#[test]
+fn serialize_canonical_floats() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: f64,
+    }
+
+    let tests = [
+        (0.0, "0.0"),
+        (1.0, "1.0"),
+        (-12.5, "-12.5"),
+        (0.1, "0.1"),
+        (1e300, "1e300"),
+        (f64::MAX, "1.7976931348623157e308"),
+    ];
+    for (value, expected) in tests {
+        let expected = format!("value = {expected}\n");
+        let actual = serde_sjson::to_string_canonical(&Value { value }).unwrap();
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn serialize_unit_field_modes() {
+    #[derive(serde::Serialize)]
+    struct Inner {
+        marker: (),
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: (),
+        option: Option<u64>,
+        text: String,
+        inner: Inner,
+    }
+
+    let value = Value {
+        value: (),
+        option: None,
+        text: String::from("null"),
+        inner: Inner { marker: () },
+    };
+
+    let tests = [
+        (
+            UnitFieldMode::Null,
+            "value = null\noption = null\ntext = \"null\"\ninner = {\n  marker = null\n}\n",
+        ),
+        (UnitFieldMode::Omit, "text = \"null\"\ninner = { }\n"),
+        (
+            UnitFieldMode::BareKey,
+            "value\noption\ntext = \"null\"\ninner = {\n  marker\n}\n",
+        ),
+    ];
+    for (mode, expected) in tests {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_unit_field_mode(mode);
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+}
+
+#[test]
+fn serialize_skip_none() {
+    #[derive(serde::Serialize)]
+    struct Inner {
+        marker: (),
+        extra: BTreeMap<String, Option<u64>>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        option: Option<u64>,
+        nested: Option<Option<u64>>,
+        present: Option<u64>,
+        text: Option<String>,
+        #[serde(with = "serde_sjson::double_option")]
+        explicit: Option<Option<u64>>,
+        inner: Inner,
+    }
+
+    let value = Value {
+        option: None,
+        nested: Some(None),
+        present: Some(1),
+        text: Some(String::from("null")),
+        explicit: Some(None),
+        inner: Inner {
+            marker: (),
+            extra: BTreeMap::from([
+                (String::from("b"), None),
+                (String::from("a"), Some(2)),
+                (String::from("c"), None),
+            ]),
+        },
+    };
+
+    // `()` and explicit `null`s are kept. Only `None` is left out.
+    let expected =
+        "present = 1\ntext = \"null\"\nexplicit = null\ninner = {\n  marker = null\n  extra = {\n    a = 2\n  }\n}\n";
+
+    for sort_keys in [false, true] {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf)
+            .with_skip_none(true)
+            .with_sort_keys(sort_keys);
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected, "{sort_keys}");
+    }
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf)
+        .with_skip_none(true)
+        .with_unit_field_mode(UnitFieldMode::BareKey);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "present = 1\ntext = \"null\"\nexplicit\ninner = {\n  marker\n  extra = {\n    a = 2\n  }\n}\n"
+    );
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf)
+        .with_skip_none(true)
+        .with_compact(true);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "present = 1, text = \"null\", explicit = null, inner = { marker = null, extra = { a = 2 } }"
+    );
+
+    // Without the option, everything is written.
+    let actual = to_string(&value).unwrap();
+    assert!(actual.starts_with("option = null\nnested = null\npresent = 1\n"));
+    assert!(actual.contains("  extra = {\n    a = 2\n    b = null\n    c = null\n  }\n"));
+}
+
+#[test]
+fn serialize_blank_line_between_top_level() {
+    #[derive(serde::Serialize)]
+    struct Inner {
+        a: u64,
+        b: u64,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        name: String,
+        inner: Inner,
+        list: Vec<Inner>,
+    }
+
+    let value = Value {
+        name: String::from("foo"),
+        inner: Inner { a: 1, b: 2 },
+        list: vec![Inner { a: 3, b: 4 }],
+    };
+
+    let expected = "\
+name = foo
+
+inner = {
+  a = 1
+  b = 2
+}
+
+list = [
+  {
+    a = 3
+    b = 4
+  }
+]
+";
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_blank_line_between_top_level(true);
+    value.serialize(&mut ser).unwrap();
+    let actual = String::from_utf8(buf).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn serialize_empty_containers() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Empty {}
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Value {
+        empty: Empty,
+        list: Vec<u64>,
+        map: BTreeMap<String, u64>,
+        nested: Vec<Vec<Empty>>,
+    }
+
+    let value = Value {
+        empty: Empty {},
+        list: vec![],
+        map: BTreeMap::new(),
+        nested: vec![vec![], vec![Empty {}]],
+    };
+
+    let expected = "\
+empty = { }
+list = []
+map = { }
+nested = [
+  []
+  [
+    { }
+  ]
+]
+";
+    let actual = to_string(&value).unwrap();
+    assert_eq!(actual, expected);
+    assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+
+    let expected = "empty = { }, list = [], map = { }, nested = [[], [{ }]]";
+    let actual = serde_sjson::to_string_compact(&value).unwrap();
+    assert_eq!(actual, expected);
+    assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+}
+
+#[test]
+fn serialize_into_inner() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: u64,
+    }
+
+    let mut ser = Serializer::new(Vec::new());
+    ser.get_mut().extend_from_slice(b"// header\n");
+    Value { value: 1 }.serialize(&mut ser).unwrap();
+    assert_eq!(ser.get_ref(), b"// header\nvalue = 1\n");
+    assert_eq!(ser.into_inner(), b"// header\nvalue = 1\n");
+}
+
+#[test]
+fn serialize_top_level_braces() {
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Inner {
+        a: u64,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Value {
+        name: String,
+        inner: Inner,
+        list: Vec<u64>,
+    }
+
+    fn serialize<T: Serialize>(value: &T, braces: bool, compact: bool) -> String {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf)
+            .with_top_level_braces(braces)
+            .with_compact(compact);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    let value = Value {
+        name: String::from("foo"),
+        inner: Inner { a: 1 },
+        list: vec![2, 3],
+    };
+
+    let tests = [
+        (
+            false,
+            false,
+            "\
+name = foo
+inner = {
+  a = 1
+}
+list = [
+  2
+  3
+]
+",
+        ),
+        (
+            true,
+            false,
+            "\
+{
+  name = foo
+  inner = {
+    a = 1
+  }
+  list = [
+    2
+    3
+  ]
+}
+",
+        ),
+        (
+            false,
+            true,
+            "name = \"foo\", inner = { a = 1 }, list = [2, 3]",
+        ),
+        (
+            true,
+            true,
+            "{ name = \"foo\", inner = { a = 1 }, list = [2, 3] }",
+        ),
+    ];
+
+    for (braces, compact, expected) in tests {
+        let actual = serialize(&value, braces, compact);
+        assert_eq!(actual, expected);
+        assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+    }
+
+    let map = BTreeMap::from([(String::from("a"), 1)]);
+    assert_eq!(serialize(&map, true, false), "{\n  a = 1\n}\n");
+    assert_eq!(
+        serialize(&BTreeMap::<String, u64>::new(), true, false),
+        "{ }\n"
+    );
+}
+
+#[test]
+fn serialize_bytes_consistently() {
+    #[derive(serde::Serialize)]
+    struct Value<T> {
+        value: T,
+    }
+
+    fn serialize<T: Serialize>(value: T, encoding: BytesEncoding) -> String {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_bytes_encoding(encoding);
+        Value { value }.serialize(&mut ser).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    let data = [0u8, 1, 127, 255];
+    let tests = [
+        (
+            BytesEncoding::Array,
+            "value = [\n  0\n  1\n  127\n  255\n]\n",
+        ),
+        (BytesEncoding::Base64, "value = \"AAF//w==\"\n"),
+    ];
+    for (encoding, expected) in tests {
+        assert_eq!(serialize(data, encoding), expected);
+        assert_eq!(serialize(data.to_vec(), encoding), expected);
+        assert_eq!(
+            serialize(serde_bytes::Bytes::new(&data), encoding),
+            expected
+        );
+        assert_eq!(
+            serialize(serde_bytes::ByteBuf::from(data), encoding),
+            expected
+        );
+    }
+
+    // Sequences of anything but bytes are unaffected.
+    let expected = "value = [\n  0\n  1\n]\n";
+    assert_eq!(serialize([0u16, 1], BytesEncoding::Base64), expected);
+    assert_eq!(
+        serialize(Vec::<u8>::new(), BytesEncoding::Base64),
+        "value = []\n"
+    );
+    assert_eq!(
+        serialize(vec![vec![1u8], vec![]], BytesEncoding::Base64),
+        "value = [\n  \"AQ==\"\n  []\n]\n"
+    );
+}
+
+#[test]
+fn serialize_to_slice() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: u64,
+    }
+
+    let value = Value { value: 1234 };
+    let expected = "value = 1234\n";
+
+    let mut buf = [0u8; 64];
+    let len = serde_sjson::to_slice(&mut buf, &value).unwrap();
+    assert_eq!(&buf[..len], expected.as_bytes());
+
+    let mut buf = [0u8; 13];
+    let len = serde_sjson::to_slice(&mut buf, &value).unwrap();
+    assert_eq!(len, expected.len());
+    assert_eq!(&buf[..], expected.as_bytes());
+
+    let mut buf = [0u8; 12];
+    let err = serde_sjson::to_slice(&mut buf, &value).unwrap_err();
+    assert_eq!(err.to_string(), "output buffer is full");
+}
+
+#[test]
+fn serialize_to_writer_flushed() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: u64,
+        text: &'static str,
+    }
+
+    #[derive(Default)]
+    struct Output {
+        data: Vec<u8>,
+        flushed: bool,
+    }
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushed = true;
+            Ok(())
+        }
+    }
+
+    let value = Value {
+        value: 1234,
+        text: "ünïcödé",
+    };
+    let expected = "value = 1234\ntext = ünïcödé\n";
+
+    // A plain `to_writer` leaves the output in the buffer.
+    let mut writer = std::io::BufWriter::new(Output::default());
+    serde_sjson::to_writer(&mut writer, &value).unwrap();
+    assert!(writer.get_ref().data.is_empty());
+
+    let mut writer = std::io::BufWriter::new(Output::default());
+    let len = serde_sjson::to_writer_flushed(&mut writer, &value).unwrap();
+    assert_eq!(len, expected.len());
+    assert_eq!(writer.get_ref().data, expected.as_bytes());
+    assert!(writer.get_ref().flushed);
+}
+
+#[test]
+fn serialize_size() {
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize)]
+    struct Inner {
+        name: String,
+        weights: Vec<f64>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        value: u64,
+        text: &'static str,
+        inner: Inner,
+        labels: BTreeMap<&'static str, Option<bool>>,
+    }
+
+    fn assert_size<T: serde::Serialize>(value: &T) {
+        let size = serde_sjson::serialized_size(value).unwrap();
+        assert_eq!(size, serde_sjson::to_vec(value).unwrap().len());
+
+        let mut buf = vec![0u8; size];
+        assert_eq!(serde_sjson::to_slice(&mut buf, value).unwrap(), size);
+    }
+
+    assert_size(&Value {
+        value: 1234,
+        text: "with \"quotes\" and ünïcödé",
+        inner: Inner {
+            name: String::from("nested"),
+            weights: vec![0.5, -1.25, 3.0],
+        },
+        labels: BTreeMap::from([("a", Some(true)), ("b c", None)]),
+    });
+    assert_size(&BTreeMap::from([("empty", Vec::<u64>::new())]));
+    assert_size(&BTreeMap::<String, u64>::new());
+
+    let err = serde_sjson::serialized_size(&[1, 2, 3]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        serde_sjson::to_vec(&[1, 2, 3]).unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn serialize_indent_modes() {
+    #[derive(serde::Serialize)]
+    struct Window {
+        width: u64,
+        size: (u64, u64),
+    }
+
+    #[derive(serde::Serialize)]
+    struct Value {
+        settings: Window,
+        windows: Vec<Window>,
+    }
+
+    let value = Value {
+        settings: Window {
+            width: 1,
+            size: (2, 3),
+        },
+        windows: vec![Window {
+            width: 4,
+            size: (5, 6),
+        }],
+    };
+
+    let fixed = "\
+settings = {
+  width = 1
+  size = [
+    2
+    3
+  ]
+}
+windows = [
+  {
+    width = 4
+    size = [
+      5
+      6
+    ]
+  }
+]
+";
+
+    let key_relative = "\
+settings = {
+             width = 1
+             size = [
+                      2
+                      3
+                    ]
+           }
+windows = [
+            {
+              width = 4
+              size = [
+                       5
+                       6
+                     ]
+            }
+          ]
+";
+
+    for (mode, expected) in [
+        (IndentMode::Fixed, fixed),
+        (IndentMode::KeyRelative, key_relative),
+    ] {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf).with_indent_mode(mode);
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+}
+
+#[test]
+fn serialize_indent() {
+    #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+    struct Window {
+        width: u64,
+        title: String,
+        size: (u64, u64),
+    }
+
+    #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+    struct Value {
+        windows: Vec<Window>,
+    }
+
+    let value = Value {
+        windows: vec![Window {
+            width: 4,
+            title: String::from("A window with a long title"),
+            size: (5, 6),
+        }],
+    };
+
+    let four_spaces = "\
+windows = [
+    {
+        width = 4
+        title = \"A window with a long title\"
+        size = [
+            5
+            6
+        ]
+    }
+]
+";
+
+    let tab = "\
+windows = [
+\t{
+\t\twidth = 4
+\t\ttitle =
+\t\t\t\"A window with a long title\"
+\t\tsize = [
+\t\t\t5
+\t\t\t6
+\t\t]
+\t}
+]
+";
+
+    let tab_key_relative = "\
+windows = [
+           {
+            width = 4
+            title =
+             \"A window with a long title\"
+            size = [
+                    5
+                    6
+                   ]
+           }
+          ]
+";
+
+    let tests = [
+        (Indent::Spaces(4), IndentMode::Fixed, None, four_spaces),
+        (Indent::Tab, IndentMode::Fixed, Some(20), tab),
+        (
+            Indent::Tab,
+            IndentMode::KeyRelative,
+            Some(20),
+            tab_key_relative,
+        ),
+    ];
+
+    for (indent, mode, width, expected) in tests {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf)
+            .with_indent(indent)
+            .with_indent_mode(mode);
+        if let Some(width) = width {
+            ser = ser.with_string_line_width(width);
+        }
+
+        value.serialize(&mut ser).unwrap();
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+    }
+}
+
+#[test]
+fn serialize_indent_tabs() {
+    #[derive(serde::Serialize)]
+    struct Inner {
+        values: Vec<Vec<u64>>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Outer {
+        inner: Inner,
+        list: Vec<Inner>,
+    }
+
+    let value = Outer {
+        inner: Inner {
+            values: vec![vec![1]],
+        },
+        list: vec![Inner { values: vec![] }],
+    };
+
+    let expected = "\
+inner = {
+\tvalues = [
+\t\t[
+\t\t\t1
+\t\t]
+\t]
+}
+list = [
+\t{
+\t\tvalues = []
+\t}
+]
+";
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_indent_tabs();
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), expected);
+}
+
+#[test]
+fn serialize_documented() {
+    #[derive(Serialize)]
+    struct Window {
+        width: u32,
+    }
+
+    #[derive(Serialize)]
+    struct Value {
+        name: String,
+        width: u32,
+        window: Window,
+    }
+
+    let value = Value {
+        name: String::from("main"),
+        width: 1,
+        window: Window { width: 2 },
+    };
+
+    let docs = HashMap::from([
+        (String::from("width"), String::from("The width in pixels.")),
+        (
+            String::from("window"),
+            String::from("The main window.\n\nIt is always open."),
+        ),
+        (String::from("height"), String::from("Not a field.")),
+    ]);
+
+    // Only top-level fields are documented, even if a nested field has the same name.
+    let expected = "\
+name = main
+// The width in pixels.
+width = 1
+// The main window.
+//
+// It is always open.
+window = {
+  width = 2
+}
+";
+
+    let actual = to_string_documented(&value, &docs).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn serialize_trailing_commas() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Window {
+        width: u64,
+        size: Vec<u64>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Value {
+        windows: Vec<Window>,
+        count: u64,
+    }
+
+    let value = Value {
+        windows: vec![Window {
+            width: 1,
+            size: vec![2, 3],
+        }],
+        count: 4,
+    };
+
+    let tests = [
+        (
+            false,
+            false,
+            "windows = [\n  {\n    width = 1\n    size = [\n      2\n      3\n    ]\n  }\n]\ncount = 4\n",
+        ),
+        (
+            true,
+            false,
+            "windows = [\n  {\n    width = 1\n    size = [\n      2,\n      3,\n    ]\n  },\n]\ncount = 4\n",
+        ),
+        (
+            false,
+            true,
+            "windows = [\n  {\n    width = 1,\n    size = [\n      2\n      3\n    ],\n  }\n]\ncount = 4\n",
+        ),
+        (
+            true,
+            true,
+            "windows = [\n  {\n    width = 1,\n    size = [\n      2,\n      3,\n    ],\n  },\n]\ncount = 4\n",
+        ),
+    ];
+
+    for (arrays, objects, expected) in tests {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf)
+            .with_trailing_comma_arrays(arrays)
+            .with_trailing_comma_objects(objects);
+        value.serialize(&mut ser).unwrap();
+
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+    }
+}
+
+#[test]
+fn serialize_string_line_width() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Window {
+        title: String,
+        size: u64,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Value {
+        name: String,
+        description: String,
+        path: String,
+        window: Window,
+        tags: Vec<String>,
+        extra: BTreeMap<String, String>,
+        count: u64,
+    }
+
+    let value = Value {
+        name: String::from("short"),
+        description: String::from("A description that is too long"),
+        path: String::from("content/levels/very_long_level_name"),
+        window: Window {
+            title: String::from("A long window title"),
+            size: 123456789012345,
+        },
+        tags: vec![String::from("a tag that is longer than the width")],
+        extra: BTreeMap::from([(String::from("key"), String::from("a long map value"))]),
+        count: 123456789012345678,
+    };
+
+    let expected = r#"name = short
+description =
+  "A description that is too long"
+path =
+  content/levels/very_long_level_name
+window = {
+  title =
+    "A long window title"
+  size = 123456789012345
+}
+tags = [
+  "a tag that is longer than the width"
+]
+extra = {
+  key =
+    "a long map value"
+}
+count = 123456789012345678
+"#;
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_string_line_width(24);
+    value.serialize(&mut ser).unwrap();
+
+    let actual = String::from_utf8(buf).unwrap();
+    assert_eq!(actual, expected);
+    assert_eq!(serde_sjson::from_str::<Value>(&actual).unwrap(), value);
+
+    // Strings that fit stay on the same line.
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_string_line_width(80);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), to_string(&value).unwrap());
+}
+
+#[test]
+fn serialize_forward_slashes() {
+    use std::path::PathBuf;
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Mod {
+        #[serde(with = "serde_sjson::forward_slashes")]
+        script: PathBuf,
+        #[serde(with = "serde_sjson::forward_slashes")]
+        resource: String,
+        pattern: String,
+    }
+
+    let tests = [
+        (
+            "scripts\\mods\\test\\new",
+            "scripts/mods/test/new",
+            "script = scripts/mods/test/new\n",
+        ),
+        (
+            "C:\\Users\\public\\test.txt",
+            "C:/Users/public/test.txt",
+            "script = \"C:/Users/public/test.txt\"\n",
+        ),
+        (
+            "content/levels\\intro",
+            "content/levels/intro",
+            "script = content/levels/intro\n",
+        ),
+        (
+            "\\\\server\\share\\mods",
+            "//server/share/mods",
+            "script = \"//server/share/mods\"\n",
+        ),
+    ];
+
+    for (path, expected, line) in tests {
+        let value = Mod {
+            script: PathBuf::from(path),
+            resource: String::from(path),
+            pattern: String::from("\\d+\\n"),
+        };
+
+        let actual = to_string(&value).unwrap();
+        assert!(actual.starts_with(line), "{actual}");
+        // Fields without the module keep their backslashes.
+        assert!(actual.ends_with("pattern = \"\\\\d+\\\\n\"\n"), "{actual}");
+
+        let actual: Mod = serde_sjson::from_str(&actual).unwrap();
+        assert_eq!(actual.script, PathBuf::from(expected));
+        assert_eq!(actual.resource, expected);
+        assert_eq!(actual.pattern, "\\d+\\n");
+    }
+}
+
+#[test]
+fn serialize_compact() {
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { width: u32, height: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Inner {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Value {
+        inner: Inner,
+        empty: Inner,
+        grid: Vec<Vec<u8>>,
+        shapes: Vec<Shape>,
+        counts: BTreeMap<String, u32>,
+    }
+
+    let value = Value {
+        inner: Inner {
+            name: String::from("main"),
+            tags: vec![String::from("a"), String::from("b c")],
+        },
+        empty: Inner {
+            name: String::new(),
+            tags: vec![],
+        },
+        grid: vec![vec![1, 2], vec![]],
+        shapes: vec![
+            Shape::Empty,
+            Shape::Circle(1.5),
+            Shape::Rect {
+                width: 2,
+                height: 3,
+            },
+        ],
+        counts: BTreeMap::from([(String::from("x"), 1), (String::from("y"), 2)]),
+    };
+
+    let expected = "inner = { name = \"main\", tags = [\"a\", \"b c\"] }, \
+empty = { name = \"\", tags = [] }, \
+grid = [[1, 2], []], \
+shapes = [\"Empty\", { Circle = 1.5 }, { Rect = { width = 2, height = 3 } }], \
+counts = { x = 1, y = 2 }";
+
+    let actual = serde_sjson::to_string_compact(&value).unwrap();
+    assert_eq!(actual, expected);
+    assert!(!to_string(&value).unwrap().starts_with(expected));
+
+    let actual: Value = serde_sjson::from_str(&actual).unwrap();
+    assert_eq!(actual, value);
+
+    #[derive(Serialize)]
+    struct Data {
+        data: Vec<u8>,
+        list: Vec<Vec<u8>>,
+    }
+
+    let value = Data {
+        data: vec![0, 1],
+        list: vec![vec![1], vec![]],
+    };
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf)
+        .with_compact(true)
+        .with_bytes_encoding(BytesEncoding::Base64);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "data = \"AAE=\", list = [\"AQ==\", []]"
+    );
+}
+
+#[test]
+fn serialize_pretty() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        counts: HashMap<&'static str, u32>,
+        list: Vec<u32>,
+    }
+
+    let value = Value {
+        counts: HashMap::from([("b", 2), ("a", 1), ("c", 3)]),
+        list: vec![1, 2],
+    };
+
+    assert_eq!(
+        serde_sjson::to_string_pretty(&value, &SerializeOptions::default()).unwrap(),
+        to_string(&value).unwrap()
+    );
+
+    let options = SerializeOptions::new()
+        .with_indent(Indent::Spaces(4))
+        .with_sort_keys(true);
+    let expected = "\
+counts = {
+    a = 1
+    b = 2
+    c = 3
+}
+list = [
+    1
+    2
+]
+";
+    assert_eq!(
+        serde_sjson::to_string_pretty(&value, &options).unwrap(),
+        expected
+    );
+
+    let mut buf = Vec::new();
+    serde_sjson::to_writer_pretty(&mut buf, &value, &options.with_compact(true)).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "counts = { a = 1, b = 2, c = 3 }, list = [1, 2]"
+    );
+
+    // A serializer can be configured from the same options.
+    let options = SerializeOptions::new().with_indent_mode(IndentMode::KeyRelative);
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_options(options.clone());
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        serde_sjson::to_string_pretty(&value, &options).unwrap()
+    );
+
+    // Settings the options don't cover are kept.
+    #[derive(serde::Serialize)]
+    struct Float {
+        value: f64,
+    }
+
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf)
+        .with_hex_floats()
+        .with_options(SerializeOptions::new().with_compact(true));
+    Float { value: 12.0 }.serialize(&mut ser).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), "value = 0x1.8p+3");
+}
+
+#[test]
+fn serialize_sort_keys() {
+    #[derive(serde::Serialize)]
+    struct Value {
+        zone: u32,
+        map: HashMap<String, HashMap<String, u32>>,
+    }
+
+    let keys = ["foo", "bar", "baz", "qux", "quux", "corge"];
+    // Each map has its own random hash state, so its iteration order differs between maps.
+    let build = || Value {
+        zone: 1,
+        map: keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let inner = HashMap::from([(String::from("b"), i as u32), (String::from("a"), 0)]);
+                (key.to_string(), inner)
+            })
+            .collect(),
+    };
+
+    let serialize = |value: &Value, mode| {
+        let mut buf = Vec::new();
+        let mut ser = Serializer::new(&mut buf)
+            .with_sort_keys(true)
+            .with_indent_mode(mode);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(buf).unwrap()
+    };
+
+    // Struct fields keep their order, only map keys are sorted.
+    let expected = "\
+zone = 1
+map = {
+  bar = {
+    a = 0
+    b = 1
+  }
+  baz = {
+    a = 0
+    b = 2
+  }
+  corge = {
+    a = 0
+    b = 5
+  }
+  foo = {
+    a = 0
+    b = 0
+  }
+  quux = {
+    a = 0
+    b = 4
+  }
+  qux = {
+    a = 0
+    b = 3
+  }
+}
+";
+    for _ in 0..4 {
+        assert_eq!(serialize(&build(), IndentMode::Fixed), expected);
+    }
+
+    let actual = serialize(&build(), IndentMode::KeyRelative);
+    assert!(
+        actual.starts_with("zone = 1\nmap = {\n        bar = {\n                a = 0\n"),
+        "{actual}"
+    );
+
+    // A top-level map is sorted as well.
+    let value = HashMap::from([("b", 2), ("c", 3), ("a", 1)]);
+    let mut buf = Vec::new();
+    let mut ser = Serializer::new(&mut buf).with_sort_keys(true);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(buf, b"a = 1\nb = 2\nc = 3\n");
+}
+
+#[test]
+fn serialize_io_error_source() {
+    use std::error::Error as _;
+    use std::io;
+
+    struct Failing;
+
+    impl io::Write for Failing {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let value = BTreeMap::from([("value", 1)]);
+    let err = serde_sjson::to_writer(&mut Failing, &value).unwrap_err();
+    assert_eq!(err.kind(), serde_sjson::ErrorKind::Io);
+    assert_eq!(err.to_string(), "pipe closed");
+
+    let source = err.source().expect("the io::Error is the source");
+    let source = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::BrokenPipe);
+
+    // Outside of `to_slice`, a writer that is full is not reported as such.
+    let err = serde_sjson::to_writer(&mut io::Cursor::new([0; 4]), &value).unwrap_err();
+    assert_eq!(err.kind(), serde_sjson::ErrorKind::Io);
+    let source = err.source().expect("the io::Error is the source");
+    let source = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::WriteZero);
+
+    // Other errors have no source.
+    let err = to_string(&f64::NAN).unwrap_err();
+    assert!(err.source().is_none());
+}